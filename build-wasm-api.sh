@@ -0,0 +1,15 @@
+#!/bin/sh
+# Builds the JavaScript API of the `wasm-api` feature and regenerates its glue and typings in out/
+set -e
+
+cargo build --target wasm32-unknown-unknown --release --lib --no-default-features --features wasm-api
+wasm-bindgen --target web --out-dir out target/wasm32-unknown-unknown/release/muehle_agent.wasm
+
+for name in "class MuehleGame" "interface GameView"; do
+    grep -q "$name" out/muehle_agent.d.ts || { echo "out/muehle_agent.d.ts is missing $name" >&2; exit 1; }
+done
+# imports of "env" come from miniquad and only resolve on the page of the canvas game
+if grep -q "from [\"']env[\"']" out/muehle_agent.js; then
+    echo "out/muehle_agent.js imports from \"env\", the API must build without the ui feature" >&2
+    exit 1
+fi
@@ -1,50 +1,113 @@
 /* tslint:disable */
 /* eslint-disable */
 
+export type Player = "white" | "black";
+
+export interface Pieces {
+    white: number;
+    black: number;
+}
+
+/** State of a game, the same as the one of the HTTP API */
+export interface GameView {
+    /** Always 0 outside of the HTTP API */
+    id: number;
+    /** 24 of W, B and E */
+    board: string;
+    /** Side to move, in the take state the side which closed the mill */
+    player: Player;
+    state: "setup" | "normal" | "take" | "win";
+    winner: Player | null;
+    plies: number;
+    in_hand: Pieces;
+    pieces: Pieces;
+    /** Moves which led to this state, e.g. "3-4x10" */
+    moves: string[];
+}
+
+
+
+/**
+ * A game with the moves played in it
+ */
+export class MuehleGame {
+    free(): void;
+    [Symbol.dispose](): void;
+    /**
+     * The move of the engine for the side to move without playing it, none once the game is over. At full
+     * strength, the default `level` 20, it searches for `time_limit` seconds.
+     */
+    bestMove(time_limit: number, level?: number | null): string | undefined;
+    /**
+     * The game at the position string `board` of 24 `W`, `B` and `E` with `player` to move, in the setup
+     * phase while pieces are left in hand
+     */
+    static fromPosition(board: string, player: string, in_hand_white: number, in_hand_black: number): MuehleGame;
+    /**
+     * The game at a state of [`MuehleGame::view`] or of the HTTP API, without the moves which led to it
+     */
+    static fromView(view: GameView): MuehleGame;
+    /**
+     * Legal moves of the side to move, e.g. `["3-4", "3-4x10"]`
+     */
+    legalMoves(): string[];
+    /**
+     * A new game, white places the first piece
+     */
+    constructor();
+    /**
+     * Plays `action`, e.g. `3-4x10`, an illegal move throws and leaves the game as it was
+     */
+    play(action: string): void;
+    /**
+     * Position string of the board, 24 of `W`, `B` and `E`
+     */
+    position(): string;
+    view(): GameView;
+}
+
 export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
 
 export interface InitOutput {
-  readonly memory: WebAssembly.Memory;
-  readonly main: (a: number, b: number) => number;
-  readonly macroquad_audio_crate_version: () => number;
-  readonly crate_version: () => number;
-  readonly allocate_vec_u8: (a: number) => number;
-  readonly on_clipboard_paste: (a: number, b: number) => void;
-  readonly frame: () => void;
-  readonly mouse_move: (a: number, b: number) => void;
-  readonly raw_mouse_move: (a: number, b: number) => void;
-  readonly mouse_down: (a: number, b: number, c: number) => void;
-  readonly mouse_up: (a: number, b: number, c: number) => void;
-  readonly mouse_wheel: (a: number, b: number) => void;
-  readonly key_down: (a: number, b: number, c: number) => void;
-  readonly key_press: (a: number) => void;
-  readonly key_up: (a: number, b: number) => void;
-  readonly resize: (a: number, b: number) => void;
-  readonly touch: (a: number, b: number, c: number, d: number) => void;
-  readonly on_files_dropped_start: () => void;
-  readonly on_files_dropped_finish: () => void;
-  readonly on_file_dropped: (a: number, b: number, c: number, d: number) => void;
-  readonly file_loaded: (a: number) => void;
-  readonly __wbindgen_start: () => void;
+    readonly memory: WebAssembly.Memory;
+    readonly __wbg_muehlegame_free: (a: number, b: number) => void;
+    readonly muehlegame_bestMove: (a: number, b: number, c: number) => [number, number, number, number];
+    readonly muehlegame_fromPosition: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number];
+    readonly muehlegame_fromView: (a: any) => [number, number, number];
+    readonly muehlegame_legalMoves: (a: number) => [number, number];
+    readonly muehlegame_new: () => number;
+    readonly muehlegame_play: (a: number, b: number, c: number) => [number, number];
+    readonly muehlegame_position: (a: number) => [number, number];
+    readonly muehlegame_view: (a: number) => any;
+    readonly __wbindgen_malloc: (a: number, b: number) => number;
+    readonly __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
+    readonly __wbindgen_exn_store: (a: number) => void;
+    readonly __externref_table_alloc: () => number;
+    readonly __wbindgen_externrefs: WebAssembly.Table;
+    readonly __externref_table_dealloc: (a: number) => void;
+    readonly __wbindgen_free: (a: number, b: number, c: number) => void;
+    readonly __externref_drop_slice: (a: number, b: number) => void;
+    readonly __wbindgen_start: () => void;
 }
 
 export type SyncInitInput = BufferSource | WebAssembly.Module;
+
 /**
-* Instantiates the given `module`, which can either be bytes or
-* a precompiled `WebAssembly.Module`.
-*
-* @param {{ module: SyncInitInput }} module - Passing `SyncInitInput` directly is deprecated.
-*
-* @returns {InitOutput}
-*/
+ * Instantiates the given `module`, which can either be bytes or
+ * a precompiled `WebAssembly.Module`.
+ *
+ * @param {{ module: SyncInitInput }} module - Passing `SyncInitInput` directly is deprecated.
+ *
+ * @returns {InitOutput}
+ */
 export function initSync(module: { module: SyncInitInput } | SyncInitInput): InitOutput;
 
 /**
-* If `module_or_path` is {RequestInfo} or {URL}, makes a request and
-* for everything else, calls `WebAssembly.instantiate` directly.
-*
-* @param {{ module_or_path: InitInput | Promise<InitInput> }} module_or_path - Passing `InitInput` directly is deprecated.
-*
-* @returns {Promise<InitOutput>}
-*/
+ * If `module_or_path` is {RequestInfo} or {URL}, makes a request and
+ * for everything else, calls `WebAssembly.instantiate` directly.
+ *
+ * @param {{ module_or_path: InitInput | Promise<InitInput> }} module_or_path - Passing `InitInput` directly is deprecated.
+ *
+ * @returns {Promise<InitOutput>}
+ */
 export default function __wbg_init (module_or_path?: { module_or_path: InitInput | Promise<InitInput> } | InitInput | Promise<InitInput>): Promise<InitOutput>;
@@ -1,24 +1,21 @@
 /* tslint:disable */
 /* eslint-disable */
 export const memory: WebAssembly.Memory;
-export function main(a: number, b: number): number;
-export function macroquad_audio_crate_version(): number;
-export function crate_version(): number;
-export function allocate_vec_u8(a: number): number;
-export function on_clipboard_paste(a: number, b: number): void;
-export function frame(): void;
-export function mouse_move(a: number, b: number): void;
-export function raw_mouse_move(a: number, b: number): void;
-export function mouse_down(a: number, b: number, c: number): void;
-export function mouse_up(a: number, b: number, c: number): void;
-export function mouse_wheel(a: number, b: number): void;
-export function key_down(a: number, b: number, c: number): void;
-export function key_press(a: number): void;
-export function key_up(a: number, b: number): void;
-export function resize(a: number, b: number): void;
-export function touch(a: number, b: number, c: number, d: number): void;
-export function on_files_dropped_start(): void;
-export function on_files_dropped_finish(): void;
-export function on_file_dropped(a: number, b: number, c: number, d: number): void;
-export function file_loaded(a: number): void;
-export function __wbindgen_start(): void;
+export const __wbg_muehlegame_free: (a: number, b: number) => void;
+export const muehlegame_bestMove: (a: number, b: number, c: number) => [number, number, number, number];
+export const muehlegame_fromPosition: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number];
+export const muehlegame_fromView: (a: any) => [number, number, number];
+export const muehlegame_legalMoves: (a: number) => [number, number];
+export const muehlegame_new: () => number;
+export const muehlegame_play: (a: number, b: number, c: number) => [number, number];
+export const muehlegame_position: (a: number) => [number, number];
+export const muehlegame_view: (a: number) => any;
+export const __wbindgen_malloc: (a: number, b: number) => number;
+export const __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
+export const __wbindgen_exn_store: (a: number) => void;
+export const __externref_table_alloc: () => number;
+export const __wbindgen_externrefs: WebAssembly.Table;
+export const __externref_table_dealloc: (a: number) => void;
+export const __wbindgen_free: (a: number, b: number, c: number) => void;
+export const __externref_drop_slice: (a: number, b: number) => void;
+export const __wbindgen_start: () => void;
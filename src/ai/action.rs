@@ -1,9 +1,14 @@
-use std::iter;
+use std::{fmt, iter};
+use std::str::FromStr;
+
+use serde::Serialize;
 
 use crate::core::position::{create_token_iter, negate_token, set_token_at, BLACK_TOKEN_FIRST_POSITION, WHITE_TOKEN_FIRST_POSITION};
 use crate::core::utils::{extract_black_token_count_from_board, extract_white_token_count_from_board, is_beat_possible, is_mill_closing, is_move_valid, update_possible_move_count};
 use crate::ai::{Phase, PhaseType};
+use crate::core::enums::FieldError;
 
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
 pub struct Action {
     pub start_position: Option<usize>,
     pub end_position: usize,
@@ -20,28 +25,68 @@ impl Action {
     }
 }
 
+/// Positions of the action, e.g. `7` to place, `3-4` to move and `3-4x10` to move and take
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start_position) = self.start_position {
+            write!(f, "{}-", start_position)?;
+        }
+        write!(f, "{}", self.end_position)?;
+        if let Some(beatable_position) = self.beatable_position {
+            write!(f, "x{}", beatable_position)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the notation of [`Action`]'s `Display`
+impl FromStr for Action {
+    type Err = FieldError;
+
+    fn from_str(text: &str) -> Result<Action, FieldError> {
+        let position = |value: &str| match value.parse::<usize>() {
+            Ok(position) if position < 24 => Ok(position),
+            _ => Err(FieldError::new(format!("Invalid position \"{}\" in move \"{}\"", value, text)))
+        };
+
+        let (squares, beatable_position) = match text.split_once('x') {
+            Some((squares, beatable)) => (squares, Some(position(beatable)?)),
+            None => (text, None)
+        };
+        let (start_position, end_position) = match squares.split_once('-') {
+            Some((start, end)) => (Some(position(start)?), position(end)?),
+            None => (None, position(squares)?)
+        };
+        Ok(Action::new(start_position, end_position, beatable_position))
+    }
+}
+
 pub fn forward_step_boards(board: &u64, token_type: u8, phase: Phase) -> impl Iterator<Item=u64> + '_ {
     list_moves(board, token_type, phase)
         .flat_map(move |applyed_move_board| {
             if is_mill_closing(*board, applyed_move_board, token_type) {
-                itertools::Either::Left(
-                    create_token_iter(*board).enumerate()
-                        .filter(move |(index, _)| is_beat_possible(*board, *index, token_type))
-                        .map(move |(beatable_position, _)| {
-                            let mut new_board = set_token_at(applyed_move_board, beatable_position, 0b00);
-                            new_board -= if token_type == 0b11 {
-                                BLACK_TOKEN_FIRST_POSITION
-                            } else {
-                                WHITE_TOKEN_FIRST_POSITION
-                            };
-                            update_possible_move_count(new_board, negate_token(token_type), beatable_position, true)
-                        }))
+                itertools::Either::Left(capture_boards(applyed_move_board, token_type))
             } else {
                 itertools::Either::Right(iter::once(applyed_move_board))
             }
     })
 }
 
+/// Boards after `token_type` takes each piece it may take, once it has closed a mill
+pub fn capture_boards(board: u64, token_type: u8) -> impl Iterator<Item=u64> {
+    create_token_iter(board).enumerate()
+        .filter(move |(index, _)| is_beat_possible(board, *index, token_type))
+        .map(move |(beatable_position, _)| {
+            let mut new_board = set_token_at(board, beatable_position, 0b00);
+            new_board -= if token_type == 0b11 {
+                BLACK_TOKEN_FIRST_POSITION
+            } else {
+                WHITE_TOKEN_FIRST_POSITION
+            };
+            update_possible_move_count(new_board, negate_token(token_type), beatable_position, true)
+        })
+}
+
 pub fn list_moves(board: &u64, token_type: u8, phase: Phase) -> impl Iterator<Item=u64> + '_ {
     let token_extended: u64 = if token_type == 0b11 {
         0b111111111111111111111111111111111111111111111111
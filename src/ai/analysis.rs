@@ -0,0 +1,173 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::ai::action::Action;
+use crate::ai::endgame::EndgameDatabase;
+use crate::ai::evaluation::{Evaluator, MuehleEvaluator};
+use crate::ai::minimax::Search;
+use crate::ai::ordering::MoveOrdering;
+use crate::ai::score::Score;
+use crate::ai::{score_root_moves, RootPosition};
+use crate::core::game::Turn;
+
+/// Bits of the board which hold the pieces, the header is left out
+const POSITIONS_MASK: u64 = (1 << 48) - 1;
+
+/// Smallest loss against the best move in evaluation units for each class, a piece is worth about 1000
+const INACCURACY: isize = 200;
+const MISTAKE: isize = 500;
+const BLUNDER: isize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveClass {
+    Best,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+impl MoveClass {
+    /// Class of a move which scores `loss` below the best move. Throwing away a win or walking into a
+    /// loss always loses more than a blunder.
+    pub fn of(loss: isize) -> MoveClass {
+        match loss {
+            BLUNDER.. => MoveClass::Blunder,
+            MISTAKE.. => MoveClass::Mistake,
+            INACCURACY.. => MoveClass::Inaccuracy,
+            _ => MoveClass::Best
+        }
+    }
+}
+impl fmt::Display for MoveClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MoveClass::Best => "best",
+            MoveClass::Inaccuracy => "inaccuracy",
+            MoveClass::Mistake => "mistake",
+            MoveClass::Blunder => "blunder"
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct AnalyzedMove {
+    /// Index of the turn in the history of the game
+    pub ply: usize,
+    pub player: u8,
+    /// Position the move was played in
+    pub board: u64,
+    pub played: Action,
+    pub best: Action,
+    /// Score of the position with the best move for the player, before the move
+    pub before: Score,
+    /// Score of the position for the player after the move
+    pub after: Score,
+    pub class: MoveClass,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Analysis {
+    pub depth: usize,
+    pub moves: Vec<AnalyzedMove>,
+}
+impl Analysis {
+    pub fn count(&self, player: u8, class: MoveClass) -> usize {
+        self.moves.iter().filter(|analyzed| analyzed.player == player && analyzed.class == class).count()
+    }
+
+    /// One line per move and a summary of both players
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Analysis at depth {}\n", self.depth);
+        for analyzed in self.moves.iter() {
+            text += &format!("{:>4}. {:<5} {:<10} {:>10} -> {:<10} {}",
+                analyzed.ply + 1, player_name(analyzed.player), analyzed.played.to_string(), analyzed.before.to_string(), analyzed.after.to_string(), analyzed.class);
+            if analyzed.class != MoveClass::Best {
+                text += &format!(", best was {}", analyzed.best);
+            }
+            text += "\n";
+        }
+
+        for player in [0b11, 0b10] {
+            text += &format!("{}: {} inaccuracies, {} mistakes, {} blunders\n",
+                player_name(player), self.count(player, MoveClass::Inaccuracy), self.count(player, MoveClass::Mistake), self.count(player, MoveClass::Blunder));
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("analysis is always serializable")
+    }
+}
+
+pub fn player_name(player: u8) -> &'static str {
+    if player == 0b11 { "White" } else { "Black" }
+}
+
+pub fn analyze_game(history: &[Turn], depth: usize) -> Analysis {
+    analyze_game_with_evaluator(history, depth, &MuehleEvaluator::default())
+}
+
+/// Scores every legal move of each finished turn of `history` with a search of `depth` plies and
+/// compares the played move with the best one
+pub fn analyze_game_with_evaluator(history: &[Turn], depth: usize, evaluator: &dyn Evaluator) -> Analysis {
+    let endgame = EndgameDatabase::shared();
+
+    let moves = history.windows(2).enumerate()
+        .filter_map(|(ply, turns)| {
+            let root = RootPosition::of_turn(&turns[0]);
+            let forward_boards = root.forward_boards();
+            let played = *forward_boards.iter().find(|forward_board| *forward_board & POSITIONS_MASK == turns[1].board & POSITIONS_MASK)?;
+
+            let mut search = Search::new(evaluator, MoveOrdering::Heuristic, None);
+            search.endgame = endgame;
+            let scored = score_root_moves(&search, &forward_boards, root.token_type, root.phase, depth);
+            let (best, best_score) = *scored.iter().max_by_key(|(_, score)| *score)?;
+            let played_score = scored.iter().find(|(forward_board, _)| *forward_board == played)?.1;
+
+            Some(AnalyzedMove {
+                ply,
+                player: root.token_type,
+                board: root.board,
+                played: root.action(played),
+                best: root.action(best),
+                before: Score::from_raw(best_score),
+                after: Score::from_raw(played_score),
+                class: MoveClass::of(best_score.saturating_sub(played_score)),
+            })
+        })
+        .collect();
+
+    Analysis { depth, moves }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::analysis::{analyze_game, MoveClass};
+    use crate::core::enums::State;
+    use crate::core::game::Game;
+    use crate::core::logic::compute_button_up;
+    use crate::core::position::decode_positions;
+
+    #[test]
+    fn test_finds_missed_mill_and_exports() {
+        // black ignores the open mill 23 16 17 of white and white closes it
+        let board = decode_positions("EEEEWEEEBEEEEBEEWWEEEEEE".to_string());
+        let mut game = Game::from_state(board, 0b10, State::Setup, 13, 5).unwrap();
+        for position in [5, 23] {
+            compute_button_up(position, &mut game).unwrap();
+        }
+        assert_eq!(State::Take, game.get_state());
+        compute_button_up(8, &mut game).unwrap();
+
+        let analysis = analyze_game(game.get_history(), 2);
+        assert_eq!(2, analysis.moves.len());
+        assert_eq!(MoveClass::Blunder, analysis.moves[0].class);
+        assert_eq!(23, analysis.moves[0].best.end_position);
+        assert_eq!(MoveClass::Best, analysis.moves[1].class);
+        assert_eq!("23x8", analysis.moves[1].played.to_string());
+
+        assert!(analysis.to_text().contains("Black: 0 inaccuracies, 0 mistakes, 1 blunders"));
+        assert!(analysis.to_json().contains("\"class\": \"blunder\""));
+    }
+}
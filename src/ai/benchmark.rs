@@ -0,0 +1,119 @@
+use crate::ai::evaluation::Evaluator;
+use crate::ai::minimax::Search;
+use crate::ai::ordering::MoveOrdering;
+use crate::ai::timer;
+use crate::ai::{iterative_deepening, Phase, PhaseType};
+use crate::core::position::decode_positions;
+use crate::core::utils::{insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+pub struct BenchmarkPosition {
+    pub name: &'static str,
+    pub positions: &'static str,
+    pub phase: PhaseType,
+    pub step_counter: u8,
+    pub token_type: u8,
+}
+
+pub const BENCHMARK_POSITIONS: [BenchmarkPosition; 7] = [
+    BenchmarkPosition { name: "empty board", positions: "EEEEEEEEEEEEEEEEEEEEEEEE", phase: PhaseType::Set, step_counter: 0, token_type: 0b11 },
+    BenchmarkPosition { name: "early setup", positions: "WEEEBEEEEEWEEEBEEBEEWEEE", phase: PhaseType::Set, step_counter: 6, token_type: 0b11 },
+    BenchmarkPosition { name: "late setup", positions: "WBEWBEEEBWWBEEBWEBEBWEWE", phase: PhaseType::Set, step_counter: 14, token_type: 0b11 },
+    BenchmarkPosition { name: "full board", positions: "WWEWBBWEBWWBEBBWEBEBWEWB", phase: PhaseType::Move, step_counter: 18, token_type: 0b11 },
+    BenchmarkPosition { name: "middlegame", positions: "WBWEBBWEEWEBWEBWEBEBWEWB", phase: PhaseType::Move, step_counter: 30, token_type: 0b10 },
+    BenchmarkPosition { name: "endgame", positions: "WBEBEBEEEWEBWEBEEEEBWEWE", phase: PhaseType::Move, step_counter: 40, token_type: 0b11 },
+    BenchmarkPosition { name: "flying", positions: "EBEEEBEWEEWBEEBEEEEBWEEE", phase: PhaseType::Move, step_counter: 50, token_type: 0b11 },
+];
+
+impl BenchmarkPosition {
+    pub fn board(&self) -> u64 {
+        insert_number_of_possible_moves_to_board(insert_token_count_to_board(decode_positions(self.positions.to_string())))
+    }
+}
+
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub nodes: u64,
+    pub evaluations: u64,
+    pub seconds: f64,
+    pub best_board: u64,
+    pub score: isize,
+}
+
+/// Searches every benchmark position with iterative deepening up to `depth` and counts the visited nodes and evaluations
+pub fn run_benchmark(depth: usize, ordering: MoveOrdering, evaluator: &dyn Evaluator) -> Vec<BenchmarkResult> {
+    BENCHMARK_POSITIONS.iter()
+        .map(|position| {
+            let start_time = timer::time();
+            let mut search = Search::new(evaluator, ordering, None);
+            // helper threads would make the node counts differ from run to run
+            search.threads = 1;
+            let board = position.board();
+            let phase = Phase::new(position.phase, position.step_counter);
+
+            let result = iterative_deepening(&search, board, position.token_type, phase, depth + 1)
+                .expect("benchmark positions have legal moves");
+
+            BenchmarkResult {
+                name: position.name,
+                nodes: search.nodes(),
+                evaluations: search.evaluations(),
+                seconds: timer::time() - start_time,
+                best_board: result.forward_board,
+                score: result.score,
+            }
+        })
+        .collect()
+}
+
+/// Depth of the last finished iteration for every benchmark position within `time_limit` seconds
+pub fn run_timed_benchmark(time_limit: f64, threads: usize, evaluator: &dyn Evaluator) -> Vec<(&'static str, usize)> {
+    BENCHMARK_POSITIONS.iter()
+        .map(|position| {
+            let mut search = Search::new(evaluator, MoveOrdering::Heuristic, Some(time_limit));
+            search.threads = threads;
+            let phase = Phase::new(position.phase, position.step_counter);
+            let depth = iterative_deepening(&search, position.board(), position.token_type, phase, 50)
+                .map_or(0, |result| result.depth);
+            (position.name, depth)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::benchmark::{run_benchmark, BenchmarkResult};
+    use crate::ai::evaluation::MuehleEvaluator;
+    use crate::ai::ordering::MoveOrdering;
+
+    #[test]
+    fn test_heuristic_ordering_prunes_more() {
+        let evaluator = MuehleEvaluator::default();
+        let unordered_results = run_benchmark(4, MoveOrdering::None, &evaluator);
+        let static_results = run_benchmark(4, MoveOrdering::StaticEvaluation, &evaluator);
+        let heuristic_results = run_benchmark(4, MoveOrdering::Heuristic, &evaluator);
+
+        for (static_result, heuristic_result) in static_results.iter().zip(heuristic_results.iter()) {
+            // the ordering never changes the result of alpha beta
+            assert_eq!(static_result.score, heuristic_result.score, "{}", static_result.name);
+        }
+
+        let nodes = |results: &[BenchmarkResult]| results.iter().map(|result| result.nodes).sum::<u64>();
+        let heuristic_nodes = nodes(&heuristic_results);
+        assert!(heuristic_nodes < nodes(&static_results), "{} >= {}", heuristic_nodes, nodes(&static_results));
+        assert!(heuristic_nodes < nodes(&unordered_results), "{} >= {}", heuristic_nodes, nodes(&unordered_results));
+
+        let static_evaluations: u64 = static_results.iter().map(|result| result.evaluations).sum();
+        let heuristic_evaluations: u64 = heuristic_results.iter().map(|result| result.evaluations).sum();
+        assert!(heuristic_evaluations < static_evaluations, "{} >= {}", heuristic_evaluations, static_evaluations);
+    }
+
+    #[test]
+    fn test_killer_moves_prune_more() {
+        let evaluator = MuehleEvaluator::default();
+        let history_results = run_benchmark(4, MoveOrdering::History, &evaluator);
+        let heuristic_results = run_benchmark(4, MoveOrdering::Heuristic, &evaluator);
+
+        let nodes = |results: &[BenchmarkResult]| results.iter().map(|result| result.nodes).sum::<u64>();
+        assert!(nodes(&heuristic_results) < nodes(&history_results), "{} >= {}", nodes(&heuristic_results), nodes(&history_results));
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::ai::action::forward_step_boards;
+use crate::ai::evaluation::{piece_masks, Evaluator};
+use crate::ai::minimax::Search;
+use crate::ai::ordering::MoveOrdering;
+use crate::ai::random::Rng;
+use crate::ai::symmetry::{canonical, inverse, SYMMETRIES};
+use crate::ai::{score_root_moves, Phase, PhaseType};
+use crate::core::utils::{get_action_from_board, insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BookSelection {
+    /// Always search
+    Off,
+    /// The candidate with the highest weight
+    Best,
+    /// A candidate with a probability proportional to its weight
+    WeightedRandom
+}
+
+/// Placement with an optional capture, in the coordinates of the canonical position
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BookMove {
+    pub position: usize,
+    pub capture: Option<usize>,
+    pub weight: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct BookKey {
+    step: u8,
+    white: u32,
+    black: u32,
+}
+
+/// Candidate placements for positions of the setup, one entry for all 16 symmetric positions
+#[derive(Default)]
+pub struct OpeningBook {
+    entries: HashMap<BookKey, Vec<BookMove>>,
+}
+
+#[derive(Debug)]
+pub struct BookError {
+    pub message: String,
+}
+impl BookError {
+    pub fn new(message: String) -> BookError {
+        BookError {
+            message
+        }
+    }
+}
+
+static EMBEDDED_BOOK: OnceLock<OpeningBook> = OnceLock::new();
+
+// the key and the symmetry which maps the board onto it
+fn key_of(board: u64, phase: Phase) -> (BookKey, usize) {
+    let (white, black) = piece_masks(board);
+    let (white, black, symmetry) = canonical(white, black);
+    (BookKey { step: phase.step_counter, white, black }, symmetry)
+}
+
+impl OpeningBook {
+    /// The book in `src/ai/book.txt`, embedded at build time
+    pub fn shared() -> &'static OpeningBook {
+        EMBEDDED_BOOK.get_or_init(|| OpeningBook::parse(include_str!("book.txt")).expect("embedded opening book is invalid"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds the move from `board` to `forward_board` as a candidate, the weights of repeated moves add up
+    pub fn insert(&mut self, board: u64, forward_board: u64, token_type: u8, phase: Phase, weight: u32) {
+        let (key, symmetry) = key_of(board, phase);
+        let (_, position, capture) = get_action_from_board(board, forward_board, token_type).into();
+        let position = SYMMETRIES[symmetry][position];
+        let capture = capture.map(|capture| SYMMETRIES[symmetry][capture]);
+
+        let candidates = self.entries.entry(key).or_default();
+        match candidates.iter_mut().find(|candidate| candidate.position == position && candidate.capture == capture) {
+            Some(candidate) => candidate.weight += weight,
+            None => candidates.push(BookMove { position, capture, weight })
+        }
+    }
+
+    /// Boards after every book move in this position with their weights
+    pub fn candidates(&self, board: u64, token_type: u8, phase: Phase) -> Vec<(u64, u32)> {
+        if phase.phase != PhaseType::Set {
+            return Vec::new()
+        }
+        let (key, symmetry) = key_of(board, phase);
+        let Some(book_moves) = self.entries.get(&key) else {
+            return Vec::new()
+        };
+
+        let forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+        book_moves.iter()
+            .filter_map(|book_move| {
+                let position = inverse(book_move.position, symmetry);
+                let capture = book_move.capture.map(|capture| inverse(capture, symmetry));
+                forward_boards.iter()
+                    .find(|forward_board| {
+                        let (_, end_position, beatable_position) = get_action_from_board(board, **forward_board, token_type).into();
+                        end_position == position && beatable_position == capture
+                    })
+                    .map(|forward_board| (*forward_board, book_move.weight))
+            })
+            .collect()
+    }
+
+    /// Board after the book move for this position, `None` if the position is not in the book
+    pub fn choose(&self, board: u64, token_type: u8, phase: Phase, selection: BookSelection, rng: &mut Rng) -> Option<u64> {
+        let candidates = self.candidates(board, token_type, phase);
+        match selection {
+            BookSelection::Off => None,
+            BookSelection::Best => candidates.iter().max_by_key(|(_, weight)| *weight).map(|(forward_board, _)| *forward_board),
+            BookSelection::WeightedRandom => {
+                let weights: Vec<f64> = candidates.iter().map(|(_, weight)| *weight as f64).collect();
+                rng.weighted_index(&weights).map(|index| candidates[index].0)
+            }
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<OpeningBook, BookError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => OpeningBook::parse(&content),
+            Err(err) => Err(BookError::new(format!("Cannot read opening book {}: {}", path, err)))
+        }
+    }
+
+    /// Parses lines of `<step> <board> <move>:<weight> ...`, the board as 24 of W, B and E
+    /// and a move as the position, optionally followed by `x` and the captured position. `#` starts a comment.
+    pub fn parse(content: &str) -> Result<OpeningBook, BookError> {
+        let mut book = OpeningBook::default();
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue
+            }
+
+            let mut fields = line.split_whitespace();
+            let step = match fields.next().map(str::parse::<u8>) {
+                Some(Ok(step)) if step < 18 => step,
+                _ => return Err(BookError::new(format!("Invalid step in line \"{}\"", line)))
+            };
+            let (white, black) = match fields.next() {
+                Some(board) if board.len() == 24 => parse_board(board).ok_or(BookError::new(format!("Invalid board \"{}\"", board)))?,
+                _ => return Err(BookError::new(format!("Missing board in line \"{}\"", line)))
+            };
+
+            let book_moves = fields.map(parse_move).collect::<Result<Vec<BookMove>, BookError>>()?;
+            if book_moves.is_empty() {
+                return Err(BookError::new(format!("No moves in line \"{}\"", line)))
+            }
+            book.entries.insert(BookKey { step, white, black }, book_moves);
+        }
+        Ok(book)
+    }
+}
+
+fn parse_board(board: &str) -> Option<(u32, u32)> {
+    board.chars().enumerate().try_fold((0, 0), |(white, black), (position, token)| match token {
+        'W' => Some((white | 1 << position, black)),
+        'B' => Some((white, black | 1 << position)),
+        'E' => Some((white, black)),
+        _ => None
+    })
+}
+
+fn parse_move(text: &str) -> Result<BookMove, BookError> {
+    let invalid = || BookError::new(format!("Invalid move \"{}\"", text));
+    let parse_position = |position: &str| position.parse::<usize>().ok().filter(|position| *position < 24).ok_or_else(invalid);
+
+    let (placement, weight) = text.split_once(':').ok_or_else(invalid)?;
+    let weight = weight.parse().map_err(|_| invalid())?;
+    let (position, capture) = match placement.split_once('x') {
+        Some((position, capture)) => (parse_position(position)?, Some(parse_position(capture)?)),
+        None => (parse_position(placement)?, None)
+    };
+    Ok(BookMove { position, capture, weight })
+}
+
+impl fmt::Display for OpeningBook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut keys: Vec<&BookKey> = self.entries.keys().collect();
+        keys.sort_by_key(|key| (key.step, key.white, key.black));
+
+        for key in keys {
+            let board: String = (0..24)
+                .map(|position| if key.white & 1 << position != 0 { 'W' } else if key.black & 1 << position != 0 { 'B' } else { 'E' })
+                .collect();
+            write!(f, "{} {}", key.step, board)?;
+
+            let mut book_moves = self.entries[key].clone();
+            book_moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+            for book_move in book_moves {
+                match book_move.capture {
+                    Some(capture) => write!(f, " {}x{}:{}", book_move.position, capture, book_move.weight)?,
+                    None => write!(f, " {}:{}", book_move.position, book_move.weight)?
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct BookOptions {
+    /// Number of placements from the empty board which get an entry
+    pub plies: usize,
+    /// Search depth for scoring every placement
+    pub depth: usize,
+    /// At most this many candidates per position
+    pub candidates: usize,
+    /// Placements scoring worse than the best by more than this are left out
+    pub margin: isize,
+}
+impl Default for BookOptions {
+    fn default() -> Self {
+        BookOptions { plies: 6, depth: 5, candidates: 3, margin: 150 }
+    }
+}
+
+/// Scores every placement of the positions reachable through the book by a search and keeps the best as candidates.
+/// The weight falls linearly from 101 for the best placement to 1 at the margin. `progress` gets the number of finished positions.
+pub fn build_book(options: &BookOptions, evaluator: &dyn Evaluator, mut progress: impl FnMut(usize)) -> OpeningBook {
+    let mut book = OpeningBook::default();
+    let mut frontier = vec![insert_number_of_possible_moves_to_board(insert_token_count_to_board(0))];
+
+    for ply in 0..options.plies.min(18) {
+        let token_type = if ply % 2 == 0 { 0b11 } else { 0b10 };
+        let phase = Phase::new(PhaseType::Set, ply as u8);
+        let mut next_frontier = Vec::new();
+        let mut seen = HashSet::new();
+
+        for board in frontier {
+            if !seen.insert(key_of(board, phase).0) {
+                continue
+            }
+
+            let forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+            let mut scored = score_root_moves(&Search::new(evaluator, MoveOrdering::Heuristic, None), &forward_boards, token_type, phase, options.depth);
+            scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+            let best_score = scored[0].1;
+            // placements leading to symmetric positions are the same candidate
+            let mut seen_placements = HashSet::new();
+            for (forward_board, score) in scored.into_iter()
+                .filter(|(forward_board, _)| seen_placements.insert(key_of(*forward_board, phase.increased()).0))
+                .take(options.candidates)
+                .filter(|(_, score)| best_score - score <= options.margin)
+            {
+                let weight = 1 + (100 * (options.margin - (best_score - score)) / options.margin.max(1)) as u32;
+                book.insert(board, forward_board, token_type, phase, weight);
+                next_frontier.push(forward_board);
+            }
+            progress(book.len());
+        }
+        frontier = next_frontier;
+    }
+    book
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::book::{BookSelection, OpeningBook};
+    use crate::ai::random::Rng;
+    use crate::ai::symmetry::SYMMETRIES;
+    use crate::ai::{Phase, PhaseType};
+    use crate::core::position::{decode_positions, set_token_at};
+    use crate::core::utils::{get_action_from_board, insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+    #[test]
+    fn test_symmetric_positions_share_entry() {
+        let mut book = OpeningBook::default();
+        let phase = Phase::new(PhaseType::Set, 1);
+        let board = insert_number_of_possible_moves_to_board(insert_token_count_to_board(decode_positions("WEEEEEEEEEEEEEEEEEEEEEEE".to_string())));
+        book.insert(board, set_token_at(board, 8, 0b10), 0b10, phase, 5);
+        assert_eq!(1, book.len());
+
+        // the same position rotated and with the inner and outer ring swapped
+        let symmetry = 9;
+        let rotated = insert_number_of_possible_moves_to_board(insert_token_count_to_board(set_token_at(0, SYMMETRIES[symmetry][0], 0b11)));
+
+        let candidates = book.candidates(rotated, 0b10, phase);
+        assert_eq!(1, candidates.len());
+        let (_, end_position, _) = get_action_from_board(rotated, candidates[0].0, 0b10).into();
+        assert_eq!(SYMMETRIES[symmetry][8], end_position);
+        assert_eq!(Some(candidates[0].0), book.choose(rotated, 0b10, phase, BookSelection::Best, &mut Rng::new(1)));
+        assert_eq!(None, book.choose(rotated, 0b10, phase, BookSelection::Off, &mut Rng::new(1)));
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let content = "# comment\n2 WEEEEEEEBEEEEEEEEEEEEEEE 1:60 9:20\n5 WWEEEEEEBBEEEEEEEEEEEEEE 7x8:101\n";
+        let book = OpeningBook::parse(content).unwrap();
+
+        assert_eq!(2, book.len());
+        assert_eq!(content.lines().skip(1).collect::<Vec<&str>>().join("\n") + "\n", book.to_string());
+        assert!(OpeningBook::parse("2 WEEE 1:60").is_err());
+        assert!(OpeningBook::parse("2 WEEEEEEEBEEEEEEEEEEEEEEE 24:60").is_err());
+    }
+
+    #[test]
+    fn test_embedded_book() {
+        let book = OpeningBook::shared();
+        assert!(!book.is_empty());
+        assert!(!book.candidates(0, 0b11, Phase::new(PhaseType::Set, 0)).is_empty());
+    }
+}
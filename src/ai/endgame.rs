@@ -0,0 +1,570 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::ai::evaluation::{piece_masks, FULL_BOARD, MILL_MASKS, NEIGHBOR_MASKS};
+use crate::ai::symmetry::canonical;
+use crate::ai::{Phase, PhaseType};
+
+/// Where the tables are loaded from by default, relative to the working directory
+pub const ENDGAME_DIRECTORY: &str = "endgame";
+/// Longest distance to the end of the game a table can store, longer wins and losses are stored as draws
+pub const MAX_DISTANCE: u8 = 126;
+
+const MAGIC: &[u8; 4] = b"MEDB";
+
+/// Game theoretic value of a position for the side to move, distances are in plies
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+// 0 is a draw, 1..=127 a win in value - 1 and 128..=255 a loss in value - 128 plies
+fn encode(outcome: Outcome) -> u8 {
+    match outcome {
+        Outcome::Win(distance) => {
+            assert!(distance <= MAX_DISTANCE, "win in {} plies does not fit into the table", distance);
+            distance + 1
+        },
+        Outcome::Loss(distance) => {
+            assert!(distance <= MAX_DISTANCE, "loss in {} plies does not fit into the table", distance);
+            distance + 128
+        },
+        Outcome::Draw => 0
+    }
+}
+
+fn decode(value: u8) -> Outcome {
+    match value {
+        0 => Outcome::Draw,
+        1..=127 => Outcome::Win(value - 1),
+        _ => Outcome::Loss(value - 128)
+    }
+}
+
+const fn binomials() -> [[usize; 25]; 25] {
+    let mut table = [[0; 25]; 25];
+    let mut n = 0;
+    while n < 25 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + if k < n { table[n - 1][k] } else { 0 };
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+const BINOMIALS: [[usize; 25]; 25] = binomials();
+
+const fn position_mills() -> [[u32; 2]; 24] {
+    let mut mills = [[0; 2]; 24];
+    let mut found = [0; 24];
+    let mut i = 0;
+    while i < 16 {
+        let mut position = 0;
+        while position < 24 {
+            if MILL_MASKS[i] & 1 << position != 0 {
+                mills[position][found[position]] = MILL_MASKS[i];
+                found[position] += 1;
+            }
+            position += 1;
+        }
+        i += 1;
+    }
+    mills
+}
+const POSITION_MILLS: [[u32; 2]; 24] = position_mills();
+
+/// Iterates over the set bits from the lowest
+fn bits(mut mask: u32) -> impl Iterator<Item=usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None
+        }
+        let position = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(position)
+    })
+}
+
+/// Position of a subset with the same number of bits in colexicographic order
+fn rank(mask: u32) -> usize {
+    bits(mask).enumerate().map(|(i, position)| BINOMIALS[position][i + 1]).sum()
+}
+
+/// Inverse of [`rank`]
+fn unrank(mut rank: usize, count: usize) -> u32 {
+    let mut mask = 0;
+    for i in (1..=count).rev() {
+        let mut position = i - 1;
+        while BINOMIALS[position + 1][i] <= rank {
+            position += 1;
+        }
+        rank -= BINOMIALS[position][i];
+        mask |= 1 << position;
+    }
+    mask
+}
+
+/// All masks with `count` bits out of the lowest `length`, in the order of [`rank`]
+fn subsets(length: usize, count: usize) -> impl Iterator<Item=u32> {
+    let mut subset: u32 = (1 << count) - 1;
+    (0..BINOMIALS[length][count]).map(move |_| {
+        let current = subset;
+        if subset != 0 {
+            // Gosper's hack
+            let lowest = subset & subset.wrapping_neg();
+            let ripple = subset + lowest;
+            subset = (((ripple ^ subset) >> 2) / lowest) | ripple;
+        }
+        current
+    })
+}
+
+/// Packs the bits of `mask` which lie on `free` to the bottom
+fn compress(mask: u32, free: u32) -> u32 {
+    bits(mask).fold(0, |packed, position| packed | 1 << (free & ((1 << position) - 1)).count_ones())
+}
+
+/// Inverse of [`compress`]
+fn expand(packed: u32, free: u32) -> u32 {
+    bits(free).enumerate()
+        .filter(|(i, _)| packed & 1 << i != 0)
+        .fold(0, |mask, (_, position)| mask | 1 << position)
+}
+
+fn closes_mill(pieces: u32, position: usize) -> bool {
+    POSITION_MILLS[position].iter().any(|mill| pieces & mill == *mill)
+}
+
+/// Pieces which may be taken: those outside of closed mills, or all if every piece is in one
+fn capturable(pieces: u32) -> u32 {
+    let in_mills = MILL_MASKS.iter().filter(|mill| pieces & **mill == **mill).fold(0, |acc, mill| acc | mill);
+    if pieces & !in_mills != 0 { pieces & !in_mills } else { pieces }
+}
+
+fn is_blocked(pieces: u32, empty: u32) -> bool {
+    pieces.count_ones() > 3 && bits(pieces).all(|position| NEIGHBOR_MASKS[position] & empty == 0)
+}
+
+fn targets(pieces: u32, position: usize, empty: u32) -> u32 {
+    if pieces.count_ones() == 3 { empty } else { NEIGHBOR_MASKS[position] & empty }
+}
+
+/// Both masks of every position with these piece counts in index order
+fn positions(own_count: usize, opponent_count: usize) -> impl Iterator<Item=(u32, u32)> {
+    subsets(24, own_count).flat_map(move |own| {
+        let free = FULL_BOARD & !own;
+        subsets(24 - own_count, opponent_count).map(move |packed| (own, expand(packed, free)))
+    })
+}
+
+/// Values of every move phase position with `own` pieces for the side to move and `opponent` pieces for the other side
+pub struct Table {
+    pub own: usize,
+    pub opponent: usize,
+    values: Vec<u8>,
+}
+
+impl Table {
+    fn new(own: usize, opponent: usize) -> Self {
+        Table { own, opponent, values: vec![0; Table::size(own, opponent)] }
+    }
+
+    pub fn size(own: usize, opponent: usize) -> usize {
+        BINOMIALS[24][own] * BINOMIALS[24 - own][opponent]
+    }
+
+    pub fn file_name(own: usize, opponent: usize) -> String {
+        format!("{}v{}.medb", own, opponent)
+    }
+
+    pub fn index(&self, own: u32, opponent: u32) -> usize {
+        rank(own) * BINOMIALS[24 - self.own][self.opponent] + rank(compress(opponent, FULL_BOARD & !own))
+    }
+
+    /// Inverse of [`Table::index`]
+    fn masks(&self, index: usize) -> (u32, u32) {
+        let opponent_positions = BINOMIALS[24 - self.own][self.opponent];
+        let own = unrank(index / opponent_positions, self.own);
+        (own, expand(unrank(index % opponent_positions, self.opponent), FULL_BOARD & !own))
+    }
+
+    pub fn get(&self, own: u32, opponent: u32) -> Outcome {
+        decode(self.values[self.index(own, opponent)])
+    }
+
+    /// Every value in index order
+    pub fn outcomes(&self) -> impl Iterator<Item=Outcome> + '_ {
+        self.values.iter().map(|value| decode(*value))
+    }
+
+    /// Smallest index of all symmetric positions, its value is the same.
+    /// The index grows with the own and then with the opponent mask, so only the smallest pair needs an index.
+    fn canonical_index(&self, own: u32, opponent: u32) -> usize {
+        let (own, opponent, _) = canonical(own, opponent);
+        self.index(own, opponent)
+    }
+
+    /// Only the values of canonical positions are written, one byte each in index order.
+    /// Every other position is one of the up to 16 symmetric ones and gets its value on load.
+    pub fn save(&self, directory: &Path) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.values.len() / 8);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.own as u8);
+        bytes.push(self.opponent as u8);
+
+        for (index, (own, opponent)) in positions(self.own, self.opponent).enumerate() {
+            if self.canonical_index(own, opponent) == index {
+                bytes.push(self.values[index]);
+            }
+        }
+        fs::write(directory.join(Table::file_name(self.own, self.opponent)), bytes)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Table> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message));
+
+        let bytes = fs::read(path)?;
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC {
+            return Err(invalid("not an endgame table"))
+        }
+        let (own, opponent) = (bytes[4] as usize, bytes[5] as usize);
+        if !(3..=9).contains(&own) || !(3..=9).contains(&opponent) {
+            return Err(invalid("invalid piece count"))
+        }
+
+        let mut table = Table::new(own, opponent);
+        let mut canonical_values = bytes[6..].iter();
+        for (index, (own, opponent)) in positions(own, opponent).enumerate() {
+            let canonical_index = table.canonical_index(own, opponent);
+            table.values[index] = if canonical_index == index {
+                *canonical_values.next().ok_or_else(|| invalid("truncated"))?
+            } else {
+                table.values[canonical_index]
+            };
+        }
+        if canonical_values.next().is_some() {
+            return Err(invalid("too many positions"))
+        }
+        Ok(table)
+    }
+}
+
+/// Move phase tables for all piece counts from 3 up to `max_pieces` per side
+pub struct EndgameDatabase {
+    pub max_pieces: usize,
+    tables: Vec<Table>,
+}
+
+impl EndgameDatabase {
+    /// Solves all tables by retrograde analysis, `progress` is called after every finished table
+    pub fn generate(max_pieces: usize, mut progress: impl FnMut(&Table)) -> Self {
+        let mut tables: Vec<Table> = Vec::new();
+
+        // a capture leads into a table with one piece less, so smaller tables are solved first
+        for total in 6..=2 * max_pieces {
+            for own in 3..=max_pieces {
+                let opponent = total - own;
+                if opponent < own || opponent > max_pieces {
+                    continue
+                }
+
+                for table in solve(&tables, own, opponent, MAX_DISTANCE) {
+                    progress(&table);
+                    tables.push(table);
+                }
+            }
+        }
+        EndgameDatabase { max_pieces, tables }
+    }
+
+    pub fn save(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        self.tables.iter().try_for_each(|table| table.save(directory))
+    }
+
+    /// Loads the tables for as many pieces as are complete in the directory
+    pub fn load(directory: &Path) -> io::Result<Self> {
+        let mut tables = Vec::new();
+        let mut max_pieces = 2;
+        for pieces in 3..=9 {
+            let names: Vec<(usize, usize)> = (3..=pieces)
+                .flat_map(|other| [(pieces, other), (other, pieces)])
+                .filter(|(own, opponent)| own != opponent || *own == pieces)
+                .collect();
+            if names.iter().any(|(own, opponent)| !directory.join(Table::file_name(*own, *opponent)).exists()) {
+                break
+            }
+
+            for (own, opponent) in names.into_iter() {
+                if find_table(&tables, own, opponent).is_none() {
+                    tables.push(Table::load(&directory.join(Table::file_name(own, opponent)))?);
+                }
+            }
+            max_pieces = pieces;
+        }
+
+        if tables.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no endgame tables in {}", directory.display())))
+        }
+        Ok(EndgameDatabase { max_pieces, tables })
+    }
+
+    /// Tables from [`ENDGAME_DIRECTORY`] loaded on first use, `None` if there are none
+    pub fn shared() -> Option<&'static EndgameDatabase> {
+        static SHARED: OnceLock<Option<EndgameDatabase>> = OnceLock::new();
+        SHARED.get_or_init(|| {
+            if cfg!(target_arch = "wasm32") {
+                return None
+            }
+            EndgameDatabase::load(Path::new(ENDGAME_DIRECTORY)).ok()
+        }).as_ref()
+    }
+
+    pub fn table(&self, own: usize, opponent: usize) -> Option<&Table> {
+        find_table(&self.tables, own, opponent)
+    }
+
+    /// Value for `token_type` to move, `None` during the setup or if there are too many pieces
+    pub fn probe(&self, board: u64, token_type: u8, phase: Phase) -> Option<Outcome> {
+        if phase.phase != PhaseType::Move {
+            return None
+        }
+
+        let (white, black) = piece_masks(board);
+        let (own, opponent) = if token_type == 0b11 { (white, black) } else { (black, white) };
+        self.table(own.count_ones() as usize, opponent.count_ones() as usize)
+            .map(|table| table.get(own, opponent))
+    }
+}
+
+fn find_table(tables: &[Table], own: usize, opponent: usize) -> Option<&Table> {
+    tables.iter().find(|table| table.own == own && table.opponent == opponent)
+}
+
+/// Solves the table for `own` against `opponent` pieces together with its mirrored table,
+/// as moves without a capture switch between both. Results further than `max_distance` plies away are draws.
+fn solve(solved: &[Table], own: usize, opponent: usize, max_distance: u8) -> Vec<Table> {
+    let mut tables = vec![Table::new(own, opponent)];
+    if own != opponent {
+        tables.push(Table::new(opponent, own));
+    }
+    assert!(tables.iter().all(|table| table.values.len() < 1 << 31), "{}v{} is too large", own, opponent);
+    let last_slot = tables.len() - 1;
+    let slot = |pieces: u32| if pieces.count_ones() as usize == own { 0 } else { last_slot };
+
+    // remaining moves without a capture and what the captures already decide:
+    // up to `max_distance` the loss distance they force at least, 128 + distance a win and 255 that it can not be lost
+    let mut counters: Vec<Vec<u8>> = tables.iter().map(|table| vec![0; table.values.len()]).collect();
+    let mut known: Vec<Vec<u8>> = tables.iter().map(|table| vec![0; table.values.len()]).collect();
+    // finished positions by distance as table slot << 31 | index, and the wins by a capture which may still get shorter
+    let mut finished: Vec<Vec<u32>> = vec![Vec::new(); 256];
+    let mut capture_wins: Vec<Vec<u32>> = vec![Vec::new(); 256];
+
+    for (table_slot, table) in tables.iter_mut().enumerate() {
+        let captured_table = find_table(solved, table.opponent - 1, table.own);
+        for (index, (own, opponent)) in positions(table.own, table.opponent).enumerate() {
+            let entry = (table_slot as u32) << 31 | index as u32;
+            let empty = FULL_BOARD & !(own | opponent);
+            if is_blocked(own, empty) {
+                table.values[index] = encode(Outcome::Loss(0));
+                finished[0].push(entry);
+                continue
+            } else if is_blocked(opponent, empty) {
+                table.values[index] = encode(Outcome::Win(0));
+                finished[0].push(entry);
+                continue
+            }
+
+            let (mut win, mut loss, mut losable) = (None, 0, true);
+            for start in bits(own) {
+                for end in bits(targets(own, start, empty)) {
+                    let moved = own & !(1 << start) | 1 << end;
+                    if !closes_mill(moved, end) {
+                        counters[table_slot][index] += 1;
+                        continue
+                    }
+
+                    for captured in bits(capturable(opponent)) {
+                        let outcome = match captured_table {
+                            Some(captured_table) => captured_table.get(opponent & !(1 << captured), moved),
+                            None => Outcome::Loss(0)
+                        };
+                        match outcome {
+                            Outcome::Loss(distance) if distance < max_distance => win = Some(win.map_or(distance + 1, |win: u8| win.min(distance + 1))),
+                            Outcome::Win(distance) if distance < max_distance => loss = loss.max(distance + 1),
+                            _ => losable = false
+                        }
+                    }
+                }
+            }
+
+            known[table_slot][index] = match win {
+                Some(win) => {
+                    capture_wins[win as usize].push(entry);
+                    128 + win
+                },
+                None if !losable => 255,
+                None => loss
+            };
+            if counters[table_slot][index] == 0 && win.is_none() && losable {
+                table.values[index] = encode(Outcome::Loss(loss));
+                finished[loss as usize].push(entry);
+            }
+        }
+    }
+
+    // positions are finished in order of their distance, every finished one updates its predecessors
+    for distance in 0..=max_distance {
+        for entry in std::mem::take(&mut capture_wins[distance as usize]) {
+            let (table_slot, index) = ((entry >> 31) as usize, (entry & !(1 << 31)) as usize);
+            if tables[table_slot].values[index] == 0 {
+                tables[table_slot].values[index] = encode(Outcome::Win(distance));
+                finished[distance as usize].push(entry);
+            }
+        }
+        // the predecessors would be too far away, they stay draws
+        if distance == max_distance {
+            break
+        }
+
+        for entry in std::mem::take(&mut finished[distance as usize]) {
+            let (table_slot, index) = ((entry >> 31) as usize, (entry & !(1 << 31)) as usize);
+            let outcome = decode(tables[table_slot].values[index]);
+            let (own, opponent) = tables[table_slot].masks(index);
+
+            // the opponent just moved a piece to `end` without closing a mill
+            let empty = FULL_BOARD & !(own | opponent);
+            for end in bits(opponent) {
+                if closes_mill(opponent, end) {
+                    continue
+                }
+                for start in bits(targets(opponent, end, empty)) {
+                    let previous = opponent & !(1 << end) | 1 << start;
+                    let previous_slot = slot(previous);
+                    let previous_index = tables[previous_slot].index(previous, own);
+                    if tables[previous_slot].values[previous_index] != 0 {
+                        continue
+                    }
+
+                    let previous_outcome = match outcome {
+                        Outcome::Loss(_) => Outcome::Win(distance + 1),
+                        _ => {
+                            counters[previous_slot][previous_index] -= 1;
+                            let floor = known[previous_slot][previous_index];
+                            if counters[previous_slot][previous_index] != 0 || floor > max_distance {
+                                continue
+                            }
+                            Outcome::Loss(floor.max(distance + 1))
+                        }
+                    };
+                    let previous_distance = match previous_outcome { Outcome::Win(distance) | Outcome::Loss(distance) => distance, Outcome::Draw => 0 };
+                    tables[previous_slot].values[previous_index] = encode(previous_outcome);
+                    finished[previous_distance as usize].push((previous_slot as u32) << 31 | previous_index as u32);
+                }
+            }
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::endgame::{positions, rank, solve, subsets, EndgameDatabase, Outcome, Table};
+    use crate::ai::action::forward_step_boards;
+    use crate::ai::evaluation::MaterialEvaluator;
+    use crate::ai::minimax::Search;
+    use crate::ai::ordering::MoveOrdering;
+    use crate::ai::score::Score;
+    use crate::ai::{iterative_deepening, Phase, PhaseType};
+    use crate::core::position::{decode_positions, set_token_at};
+    use crate::core::utils::{insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+    // white owns the first, black the second mask
+    fn board_of(white: u32, black: u32) -> u64 {
+        let board = (0..24).fold(0, |board, position| {
+            let token = if white & 1 << position != 0 { 0b11 } else if black & 1 << position != 0 { 0b10 } else { 0b00 };
+            set_token_at(board, position, token)
+        });
+        insert_number_of_possible_moves_to_board(insert_token_count_to_board(board))
+    }
+
+    #[test]
+    fn test_index_is_dense() {
+        for (index, subset) in subsets(10, 3).enumerate() {
+            assert_eq!(index, rank(subset));
+        }
+
+        let table = Table::new(3, 4);
+        for (index, (own, opponent)) in positions(3, 4).enumerate().step_by(997) {
+            assert_eq!(index, table.index(own, opponent));
+            assert_eq!((own, opponent), table.masks(index));
+        }
+    }
+
+    #[test]
+    fn test_three_against_three() {
+        let database = EndgameDatabase::generate(3, |_| ());
+        let phase = Phase::new(PhaseType::Move, 40);
+        let board = |positions: &str| insert_number_of_possible_moves_to_board(insert_token_count_to_board(decode_positions(positions.to_string())));
+
+        // white flies into the gap of 7 0 1 and takes a third piece
+        assert_eq!(Some(Outcome::Win(1)), database.probe(board("WWEEEEEEEEBEEEBEEEEEBEEW"), 0b11, phase));
+        // black has two open mills and white can only block one of them
+        assert_eq!(Some(Outcome::Loss(2)), database.probe(board("WEEEWEEEBBEBEEEEEEEEEEWE"), 0b11, phase));
+        assert_eq!(Some(Outcome::Win(1)), database.probe(board("WEEEWEEEBBEBEEEEEEEEEEWE"), 0b10, phase));
+        assert_eq!(None, database.probe(board("WEEEWEEEBBEBEEEEEEEEEEWE"), 0b11, Phase::new(PhaseType::Set, 12)));
+
+        // every value follows from the values after the moves of the regular move generator
+        for (own, opponent) in positions(3, 3).step_by(4999) {
+            let board = board_of(own, opponent);
+            let expected = forward_step_boards(&board, 0b11, phase)
+                .map(|forward_board| match database.probe(forward_board, 0b10, phase.increased()) {
+                    Some(Outcome::Loss(distance)) => Outcome::Win(distance + 1),
+                    Some(Outcome::Win(distance)) => Outcome::Loss(distance + 1),
+                    Some(Outcome::Draw) => Outcome::Draw,
+                    None => Outcome::Win(1)
+                })
+                .max_by_key(|outcome| match outcome {
+                    Outcome::Win(distance) => 1000 - *distance as isize,
+                    Outcome::Draw => 0,
+                    Outcome::Loss(distance) => *distance as isize - 1000
+                });
+            assert_eq!(expected, database.probe(board, 0b11, phase));
+        }
+
+        // the search scores positions in the tables exactly, even behind its horizon
+        let (own, opponent) = positions(3, 3).find(|(own, opponent)| database.table(3, 3).unwrap().get(*own, *opponent) == Outcome::Win(5)).unwrap();
+        let mut search = Search::new(&MaterialEvaluator, MoveOrdering::Heuristic, None);
+        search.endgame = Some(&database);
+        let result = iterative_deepening(&search, board_of(own, opponent), 0b11, phase, 1).unwrap();
+        assert_eq!(Score::Win(5), Score::from_raw(result.score));
+
+        // results further away than the longest distance become draws, all others stay the same
+        let short_tables = solve(&[], 3, 3, 4);
+        let mut beyond = 0;
+        for (outcome, short_outcome) in database.table(3, 3).unwrap().outcomes().zip(short_tables[0].outcomes()) {
+            match outcome {
+                Outcome::Win(distance) | Outcome::Loss(distance) if distance > 4 => {
+                    assert_eq!(Outcome::Draw, short_outcome);
+                    beyond += 1;
+                },
+                _ => assert_eq!(outcome, short_outcome)
+            }
+        }
+        assert!(beyond > 0);
+
+        let directory = std::env::temp_dir().join("muehle_endgame_test");
+        database.save(&directory).unwrap();
+        let loaded = EndgameDatabase::load(&directory).unwrap();
+        assert_eq!(3, loaded.max_pieces);
+        assert!(loaded.table(3, 3).unwrap().outcomes().eq(database.table(3, 3).unwrap().outcomes()));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
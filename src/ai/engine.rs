@@ -0,0 +1,158 @@
+use crate::ai::action::Action;
+use crate::ai::evaluation::MuehleEvaluator;
+use crate::ai::mcts::{mcts, MctsOptions, MctsResult};
+use crate::ai::score::Score;
+use crate::ai::skill::Skill;
+use crate::ai::{compute_step, ChosenMove, RootPosition, MOVE_TIME};
+use crate::core::game::Game;
+
+/// The action an engine picked and its score for the side which plays it, if the engine has one
+pub struct EngineMove {
+    pub action: Action,
+    pub score: Option<Score>,
+    /// Depth of the alpha beta search, none for book moves, blunders and MCTS
+    pub depth: Option<usize>,
+    /// Positions the alpha beta search visited
+    pub nodes: u64,
+    pub playouts: Option<usize>,
+}
+impl EngineMove {
+    pub fn of_chosen(root: &RootPosition, chosen: &ChosenMove) -> EngineMove {
+        EngineMove { action: root.action(chosen.forward_board), score: chosen.score, depth: chosen.depth, nodes: chosen.nodes, playouts: None }
+    }
+
+    pub fn of_mcts(root: &RootPosition, result: &MctsResult) -> EngineMove {
+        EngineMove { action: root.action(result.forward_board), score: None, depth: None, nodes: 0, playouts: Some(result.playouts) }
+    }
+
+    /// How much the engine searched, e.g. `depth 7, 52340 nodes` or `1800 playouts`
+    pub fn statistics(&self) -> String {
+        match (self.playouts, self.depth) {
+            (Some(playouts), _) => format!("{} playouts", playouts),
+            (None, Some(depth)) => format!("depth {}, {} nodes", depth, self.nodes),
+            (None, None) if self.score.is_none() => "book move".to_string(),
+            (None, None) => "static evaluation".to_string()
+        }
+    }
+}
+
+/// Anything which can pick the next action for the side to move
+pub trait Engine: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn compute_step(&self, game: &Game, skill: Skill) -> Option<EngineMove>;
+}
+
+/// Alpha beta search of `ai::minimax` together with the opening book and endgame tables
+pub struct MinimaxEngine;
+impl Engine for MinimaxEngine {
+    fn name(&self) -> &'static str {
+        "Minimax"
+    }
+
+    fn compute_step(&self, game: &Game, skill: Skill) -> Option<EngineMove> {
+        compute_step(game, skill)
+    }
+}
+
+pub struct MctsEngine;
+impl MctsEngine {
+    /// Every level doubles the playouts of the level two below, full strength thinks for a second
+    pub fn options(skill: Skill) -> MctsOptions {
+        if skill.is_full_strength() {
+            MctsOptions { playouts: usize::MAX, time_limit: Some(MOVE_TIME), ..MctsOptions::default() }
+        } else {
+            let playouts = 25.0 * std::f64::consts::SQRT_2.powi(skill.level() as i32);
+            MctsOptions { playouts: playouts as usize, ..MctsOptions::default() }
+        }
+    }
+}
+impl Engine for MctsEngine {
+    fn name(&self) -> &'static str {
+        "MCTS"
+    }
+
+    fn compute_step(&self, game: &Game, skill: Skill) -> Option<EngineMove> {
+        let root = RootPosition::of(game);
+        let result = mcts(&root, &MctsEngine::options(skill), &MuehleEvaluator::default())?;
+        Some(EngineMove::of_mcts(&root, &result))
+    }
+}
+
+/// Engines a side can be played by
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EngineKind {
+    Minimax,
+    Mcts
+}
+impl EngineKind {
+    pub fn engine(self) -> &'static dyn Engine {
+        match self {
+            EngineKind::Minimax => &MinimaxEngine,
+            EngineKind::Mcts => &MctsEngine
+        }
+    }
+
+    pub fn next(self) -> EngineKind {
+        match self {
+            EngineKind::Minimax => EngineKind::Mcts,
+            EngineKind::Mcts => EngineKind::Minimax
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::compute_step_with_evaluator;
+    use crate::ai::engine::{EngineKind, MctsEngine};
+    use crate::ai::evaluation::MuehleEvaluator;
+    use crate::ai::random::Rng;
+    use crate::ai::skill::Skill;
+    use crate::core::enums::State;
+    use crate::core::game::Game;
+    use crate::core::logic::compute_computer_step;
+    use crate::core::position::decode_positions;
+
+    #[test]
+    fn test_engines_play_opening() {
+        let game = Game::new();
+        for kind in [EngineKind::Minimax, EngineKind::Mcts] {
+            let action = kind.engine().compute_step(&game, Skill::new(1)).unwrap().action;
+            assert!(action.start_position.is_none(), "{}", kind.engine().name());
+            assert_eq!(game.get_token_at(action.end_position), 0b00);
+        }
+    }
+
+    #[test]
+    fn test_engines_take_over_pending_capture() {
+        // white has just closed the mill 7 0 1 with its third piece and has to take a black one
+        let board = decode_positions("WWEEEEEWEEBEEEEEEBEEEEEE".to_string());
+        for kind in [EngineKind::Minimax, EngineKind::Mcts] {
+            for skill in [Skill::new(1), Skill::new(12)] {
+                let mut game = Game::from_state(board, 0b11, State::Take, 13, 4).unwrap();
+                let action = kind.engine().compute_step(&game, skill).unwrap().action;
+                compute_computer_step(action, &mut game).unwrap();
+
+                // the piece count in the header starts at two
+                let count = |color| (0..24).filter(|position| game.get_token_at(*position) == color).count();
+                assert_eq!((3, 1), (count(0b11), count(0b10)), "{}", kind.engine().name());
+                assert_eq!((State::Setup, 0b10, 5), (game.get_state(), game.get_player_turn(), game.get_step_counter()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_statistics() {
+        let board = decode_positions("WWBEWBEEBWEEWBEEBWEEEBWE".to_string());
+        let game = Game::from_state(board, 0b11, State::Normal, 0, 18).unwrap();
+
+        let minimax = compute_step_with_evaluator(&game, Skill::new(12), &MuehleEvaluator::default(), &mut Rng::new(3)).unwrap();
+        assert_eq!((Some(3), None), (minimax.depth, minimax.playouts));
+        assert!(minimax.nodes > 0);
+        assert_eq!(format!("depth 3, {} nodes", minimax.nodes), minimax.statistics());
+
+        let mcts = EngineKind::Mcts.engine().compute_step(&game, Skill::new(1)).unwrap();
+        let playouts = MctsEngine::options(Skill::new(1)).playouts;
+        assert_eq!(Some(playouts), mcts.playouts);
+        assert_eq!(format!("{} playouts", playouts), mcts.statistics());
+    }
+}
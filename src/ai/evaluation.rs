@@ -0,0 +1,501 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::core::position::get_token_at;
+use crate::core::utils::{extract_black_move_count_from_board, extract_black_token_count_from_board, extract_white_move_count_from_board, extract_white_token_count_from_board, MILLS, NEIGHBORS};
+use crate::ai::score::WIN;
+use crate::ai::{Phase, PhaseType};
+
+/// Scores a board from whites point of view, positive values are good for white
+pub trait Evaluator: Send + Sync {
+    fn evaluate(&self, board: u64, phase: Phase) -> isize;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GamePhase {
+    Placement,
+    Moving,
+    Flying
+}
+impl GamePhase {
+    pub fn of(board: u64, phase: Phase) -> GamePhase {
+        if phase.phase == PhaseType::Set {
+            GamePhase::Placement
+        } else if extract_white_token_count_from_board(board) == 3 || extract_black_token_count_from_board(board) == 3 {
+            GamePhase::Flying
+        } else {
+            GamePhase::Moving
+        }
+    }
+}
+
+pub const FEATURE_COUNT: usize = 9;
+pub const FEATURE_NAMES: [&str; FEATURE_COUNT] = [
+    "pieces",
+    "mobility",
+    "closed_mills",
+    "open_mills",
+    "double_mills",
+    "blocked_pieces",
+    "pieces_in_hand",
+    "running_mills",
+    "flying_threats",
+];
+pub const PHASE_NAMES: [&str; 3] = ["placement", "moving", "flying"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PhaseWeights {
+    pub pieces: isize,
+    pub mobility: isize,
+    pub closed_mills: isize,
+    pub open_mills: isize,
+    pub double_mills: isize,
+    pub blocked_pieces: isize,
+    pub pieces_in_hand: isize,
+    pub running_mills: isize,
+    pub flying_threats: isize,
+}
+impl PhaseWeights {
+    pub fn to_array(&self) -> [isize; FEATURE_COUNT] {
+        [
+            self.pieces,
+            self.mobility,
+            self.closed_mills,
+            self.open_mills,
+            self.double_mills,
+            self.blocked_pieces,
+            self.pieces_in_hand,
+            self.running_mills,
+            self.flying_threats,
+        ]
+    }
+
+    pub fn from_array(values: [isize; FEATURE_COUNT]) -> Self {
+        PhaseWeights {
+            pieces: values[0],
+            mobility: values[1],
+            closed_mills: values[2],
+            open_mills: values[3],
+            double_mills: values[4],
+            blocked_pieces: values[5],
+            pieces_in_hand: values[6],
+            running_mills: values[7],
+            flying_threats: values[8],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Weights {
+    pub placement: PhaseWeights,
+    pub moving: PhaseWeights,
+    pub flying: PhaseWeights,
+}
+impl Weights {
+    pub fn for_phase(&self, game_phase: GamePhase) -> &PhaseWeights {
+        match game_phase {
+            GamePhase::Placement => &self.placement,
+            GamePhase::Moving => &self.moving,
+            GamePhase::Flying => &self.flying
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Weights, WeightsError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Weights::parse(&content),
+            Err(err) => Err(WeightsError::new(format!("Cannot read weights file {}: {}", path, err)))
+        }
+    }
+
+    /// Parses lines of `<phase>.<feature> = <value>`, `#` starts a comment
+    pub fn parse(content: &str) -> Result<Weights, WeightsError> {
+        let mut values: [[Option<isize>; FEATURE_COUNT]; 3] = [[None; FEATURE_COUNT]; 3];
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(WeightsError::new(format!("Missing '=' in line \"{}\"", line)))
+            };
+            let (phase_name, feature_name) = match key.split_once('.') {
+                Some(names) => names,
+                None => return Err(WeightsError::new(format!("Invalid key \"{}\"", key)))
+            };
+            let phase_index = match PHASE_NAMES.iter().position(|name| *name == phase_name) {
+                Some(index) => index,
+                None => return Err(WeightsError::new(format!("Unknown phase \"{}\"", phase_name)))
+            };
+            let feature_index = match FEATURE_NAMES.iter().position(|name| *name == feature_name) {
+                Some(index) => index,
+                None => return Err(WeightsError::new(format!("Unknown feature \"{}\"", feature_name)))
+            };
+            match value.parse() {
+                Ok(value) => values[phase_index][feature_index] = Some(value),
+                Err(_) => return Err(WeightsError::new(format!("Invalid value \"{}\" for {}", value, key)))
+            }
+        }
+
+        let mut phases = [PhaseWeights::from_array([0; FEATURE_COUNT]); 3];
+        for (phase_index, phase_values) in values.iter().enumerate() {
+            let mut array = [0; FEATURE_COUNT];
+            for (feature_index, value) in phase_values.iter().enumerate() {
+                match value {
+                    Some(value) => array[feature_index] = *value,
+                    None => return Err(WeightsError::new(format!("Missing weight {}.{}", PHASE_NAMES[phase_index], FEATURE_NAMES[feature_index])))
+                }
+            }
+            phases[phase_index] = PhaseWeights::from_array(array);
+        }
+
+        Ok(Weights {
+            placement: phases[0],
+            moving: phases[1],
+            flying: phases[2],
+        })
+    }
+}
+impl Default for Weights {
+    fn default() -> Self {
+        *EMBEDDED_WEIGHTS.get_or_init(|| Weights::parse(include_str!("weights.txt")).expect("embedded weights are invalid"))
+    }
+}
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (phase_name, phase_weights) in PHASE_NAMES.iter().zip([self.placement, self.moving, self.flying]) {
+            for (feature_name, value) in FEATURE_NAMES.iter().zip(phase_weights.to_array()) {
+                writeln!(f, "{}.{} = {}", phase_name, feature_name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static EMBEDDED_WEIGHTS: OnceLock<Weights> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct WeightsError {
+    pub message: String,
+}
+impl WeightsError {
+    pub fn new(message: String) -> WeightsError {
+        WeightsError {
+            message
+        }
+    }
+}
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Every feature is the value for white minus the value for black
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Features {
+    pub pieces: isize,
+    pub mobility: isize,
+    pub closed_mills: isize,
+    pub open_mills: isize,
+    pub double_mills: isize,
+    pub blocked_pieces: isize,
+    pub pieces_in_hand: isize,
+    pub running_mills: isize,
+    pub flying_threats: isize,
+}
+impl Features {
+    pub fn extract(board: u64, phase: Phase) -> Features {
+        let (white, black) = piece_masks(board);
+        let empty: u32 = !(white | black) & FULL_BOARD;
+
+        let (white_in_hand, black_in_hand) = if phase.phase == PhaseType::Set {
+            let left = 18_isize.saturating_sub(phase.step_counter as isize).max(0);
+            (left / 2, (left + 1) / 2)
+        } else {
+            (0, 0)
+        };
+        let white_flying = phase.phase == PhaseType::Move && white.count_ones() == 3;
+        let black_flying = phase.phase == PhaseType::Move && black.count_ones() == 3;
+
+        Features {
+            pieces: white.count_ones() as isize - black.count_ones() as isize,
+            mobility: extract_white_move_count_from_board(board) as isize - extract_black_move_count_from_board(board) as isize,
+            closed_mills: closed_mills(white) - closed_mills(black),
+            open_mills: open_mills(white, empty) - open_mills(black, empty),
+            double_mills: double_mills(white) - double_mills(black),
+            blocked_pieces: blocked_pieces(white, empty) - blocked_pieces(black, empty),
+            pieces_in_hand: white_in_hand - black_in_hand,
+            running_mills: running_mills(white, black, empty) - running_mills(black, white, empty),
+            flying_threats: if white_flying { open_mills(white, empty) } else { 0 }
+                - if black_flying { open_mills(black, empty) } else { 0 },
+        }
+    }
+
+    pub fn to_array(&self) -> [isize; FEATURE_COUNT] {
+        [
+            self.pieces,
+            self.mobility,
+            self.closed_mills,
+            self.open_mills,
+            self.double_mills,
+            self.blocked_pieces,
+            self.pieces_in_hand,
+            self.running_mills,
+            self.flying_threats,
+        ]
+    }
+
+    pub fn score(&self, weights: &PhaseWeights) -> isize {
+        self.pieces * weights.pieces
+            + self.mobility * weights.mobility
+            + self.closed_mills * weights.closed_mills
+            + self.open_mills * weights.open_mills
+            + self.double_mills * weights.double_mills
+            + self.blocked_pieces * weights.blocked_pieces
+            + self.pieces_in_hand * weights.pieces_in_hand
+            + self.running_mills * weights.running_mills
+            + self.flying_threats * weights.flying_threats
+    }
+}
+
+/// The original evaluation: piece difference and mobility difference
+#[derive(Clone, Copy, Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&self, board: u64, phase: Phase) -> isize {
+        if let Some(score) = terminal_score(board, phase) {
+            return score
+        }
+
+        let white_move_count = extract_white_move_count_from_board(board) as isize;
+        let black_move_count = extract_black_move_count_from_board(board) as isize;
+        let white_token_count = extract_white_token_count_from_board(board) as isize;
+        let black_token_count = extract_black_token_count_from_board(board) as isize;
+
+        (white_token_count - black_token_count) * 1000 + white_move_count - black_move_count
+    }
+}
+
+/// Phase aware evaluation on the feature set in [`Features`]
+#[derive(Clone, Copy, Default)]
+pub struct MuehleEvaluator {
+    pub weights: Weights,
+}
+impl MuehleEvaluator {
+    pub fn new(weights: Weights) -> Self {
+        MuehleEvaluator { weights }
+    }
+}
+
+impl Evaluator for MuehleEvaluator {
+    fn evaluate(&self, board: u64, phase: Phase) -> isize {
+        if let Some(score) = terminal_score(board, phase) {
+            return score
+        }
+
+        let weights = self.weights.for_phase(GamePhase::of(board, phase));
+        Features::extract(board, phase).score(weights)
+    }
+}
+
+/// [`WIN`] if white has won, `-WIN` if black has won. The search moves it to the distance from its root.
+pub fn terminal_score(board: u64, phase: Phase) -> Option<isize> {
+    if phase.phase != PhaseType::Move {
+        return None
+    }
+
+    let black_token_count = extract_black_token_count_from_board(board);
+    let white_token_count = extract_white_token_count_from_board(board);
+    if (extract_black_move_count_from_board(board) == 0 && black_token_count > 3) || black_token_count == 2 {
+        Some(WIN)
+    } else if (extract_white_move_count_from_board(board) == 0 && white_token_count > 3) || white_token_count == 2 {
+        Some(-WIN)
+    } else {
+        None
+    }
+}
+
+pub const FULL_BOARD: u32 = 0xFFFFFF;
+
+const fn mill_masks() -> [u32; 16] {
+    let mut masks = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        masks[i] = 1 << MILLS[i][0] | 1 << MILLS[i][1] | 1 << MILLS[i][2];
+        i += 1;
+    }
+    masks
+}
+pub const MILL_MASKS: [u32; 16] = mill_masks();
+
+const fn neighbor_masks() -> [u32; 24] {
+    let mut masks = [0; 24];
+    let mut position = 0;
+    while position < 24 {
+        let mut i = 0;
+        while i < 4 {
+            if NEIGHBORS[position][i] != 24 {
+                masks[position] |= 1 << NEIGHBORS[position][i];
+            }
+            i += 1;
+        }
+        position += 1;
+    }
+    masks
+}
+pub const NEIGHBOR_MASKS: [u32; 24] = neighbor_masks();
+
+/// Bit `position` is set for every white (first) and black (second) piece
+pub fn piece_masks(board: u64) -> (u32, u32) {
+    let mut white: u32 = 0;
+    let mut black: u32 = 0;
+    for position in 0..24 {
+        match get_token_at(board, position) {
+            0b11 => white |= 1 << position,
+            0b10 => black |= 1 << position,
+            _ => ()
+        }
+    }
+    (white, black)
+}
+
+/// Empty squares which would close a mill of `own`
+pub fn mill_gaps(own: u32, empty: u32) -> u32 {
+    MILL_MASKS.iter()
+        .filter(|mill| (own & **mill).count_ones() == 2 && (empty & **mill).count_ones() == 1)
+        .fold(0, |acc, mill| acc | (empty & mill))
+}
+
+/// Empty squares which would give `own` a new open mill
+pub fn threat_squares(own: u32, empty: u32) -> u32 {
+    MILL_MASKS.iter()
+        .filter(|mill| (own & **mill).count_ones() == 1 && (empty & **mill).count_ones() == 2)
+        .fold(0, |acc, mill| acc | (empty & mill))
+}
+
+fn closed_mills(own: u32) -> isize {
+    MILL_MASKS.iter().filter(|mill| own & **mill == **mill).count() as isize
+}
+
+fn open_mills(own: u32, empty: u32) -> isize {
+    MILL_MASKS.iter()
+        .filter(|mill| (own & **mill).count_ones() == 2 && (empty & **mill).count_ones() == 1)
+        .count() as isize
+}
+
+// pieces which are shared by two closed mills
+fn double_mills(own: u32) -> isize {
+    let mut in_one_mill: u32 = 0;
+    let mut in_two_mills: u32 = 0;
+    for mill in MILL_MASKS.iter().filter(|mill| own & **mill == **mill) {
+        in_two_mills |= in_one_mill & mill;
+        in_one_mill |= mill;
+    }
+    in_two_mills.count_ones() as isize
+}
+
+fn blocked_pieces(own: u32, empty: u32) -> isize {
+    (0..24)
+        .filter(|position| own & (1 << position) != 0 && NEIGHBOR_MASKS[*position] & empty == 0)
+        .count() as isize
+}
+
+// open mills whose gap can be filled by a piece leaving a closed mill,
+// while the opponent has no piece next to the gap to block it
+fn running_mills(own: u32, opponent: u32, empty: u32) -> isize {
+    let in_closed_mill: u32 = MILL_MASKS.iter()
+        .filter(|mill| own & **mill == **mill)
+        .fold(0, |acc, mill| acc | mill);
+
+    MILL_MASKS.iter()
+        .filter(|mill| (own & **mill).count_ones() == 2 && (empty & **mill).count_ones() == 1)
+        .filter(|mill| {
+            let gap = (empty & **mill).trailing_zeros() as usize;
+            let runners = NEIGHBOR_MASKS[gap] & in_closed_mill & !**mill;
+            runners != 0 && NEIGHBOR_MASKS[gap] & opponent == 0
+        })
+        .count() as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::score::WIN;
+    use crate::ai::{Phase, PhaseType};
+    use crate::ai::evaluation::{Evaluator, Features, GamePhase, MaterialEvaluator, MuehleEvaluator};
+    use crate::core::position::decode_positions;
+    use crate::core::utils::{insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+    fn board_from(encoded: &str) -> u64 {
+        insert_number_of_possible_moves_to_board(insert_token_count_to_board(decode_positions(encoded.to_string())))
+    }
+
+    #[test]
+    fn test_closed_and_open_mills() {
+        // white mill 7 0 1, white open mills 5 4 _ and 7 _ 5, black open mill _ 16 17
+        let board = board_from("WWEEWWEWEEEEEEEEBBEEEEEE");
+        let features = Features::extract(board, Phase::new(PhaseType::Move, 20));
+
+        assert_eq!(1, features.closed_mills);
+        assert_eq!(1, features.open_mills);
+        assert_eq!(3, features.pieces);
+    }
+
+    #[test]
+    fn test_double_mills() {
+        // 7 0 1 and 1 2 3 share the piece on 1
+        let board = board_from("WWWWEEEWEEBEEBEBBEEEEEEE");
+        let features = Features::extract(board, Phase::new(PhaseType::Move, 20));
+
+        assert_eq!(2, features.closed_mills);
+        assert_eq!(1, features.double_mills);
+    }
+
+    #[test]
+    fn test_running_mill() {
+        // closed mill 7 0 1, piece 0 can run to 8 closing 15 8 9
+        let board = board_from("WWEEEEEWEWEEEEEWEEEBBBBE");
+        let features = Features::extract(board, Phase::new(PhaseType::Move, 20));
+        assert_eq!(1, features.running_mills);
+
+        // a black piece on 16 can block the gap
+        let blocked = board_from("WWEEEEEWEWEEEEEWBEEBBBEE");
+        let features = Features::extract(blocked, Phase::new(PhaseType::Move, 20));
+        assert_eq!(0, features.running_mills);
+    }
+
+    #[test]
+    fn test_pieces_in_hand() {
+        let board = board_from("WEEEEEEEEEEEEEEEEEEEEEEE");
+        let features = Features::extract(board, Phase::new(PhaseType::Set, 1));
+
+        assert_eq!(-1, features.pieces_in_hand);
+        assert_eq!(1, features.pieces);
+        assert_eq!(GamePhase::Placement, GamePhase::of(board, Phase::new(PhaseType::Set, 1)));
+    }
+
+    #[test]
+    fn test_flying_phase() {
+        // white flies with two open mills
+        let board = board_from("WWEEEEEEEEEEEEEEEEEEBBBB");
+        let board = crate::core::position::set_token_at(board, 3, 0b11);
+        let board = insert_number_of_possible_moves_to_board(insert_token_count_to_board(board));
+        let phase = Phase::new(PhaseType::Move, 20);
+
+        assert_eq!(GamePhase::Flying, GamePhase::of(board, phase));
+        assert_eq!(2, Features::extract(board, phase).flying_threats);
+        assert!(MuehleEvaluator::default().evaluate(board, phase) > MaterialEvaluator.evaluate(board, phase));
+    }
+
+    #[test]
+    fn test_terminal_scores() {
+        let phase = Phase::new(PhaseType::Move, 20);
+        let board = board_from("WWWEEEEEEEEEEEEEEEEEEEBB");
+
+        assert_eq!(WIN, MuehleEvaluator::default().evaluate(board, phase));
+        assert_eq!(WIN, MaterialEvaluator.evaluate(board, phase));
+    }
+}
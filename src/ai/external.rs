@@ -0,0 +1,471 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use crate::ai::action::Action;
+use crate::ai::random::Rng;
+use crate::ai::timer;
+use crate::ai::tournament::{GameResult, MatchStats};
+use crate::ai::RootPosition;
+use crate::core::enums::State;
+use crate::core::game::Game;
+use crate::core::logic::compute_checked_step;
+use crate::core::position::{encode_positions, negate_token};
+
+/// Seconds an engine has to start and answer the handshake
+const HANDSHAKE_TIME: f64 = 10.0;
+
+#[derive(Debug)]
+pub struct ExternalError {
+    pub message: String,
+}
+impl ExternalError {
+    pub fn new(message: String) -> ExternalError {
+        ExternalError { message }
+    }
+}
+
+/// How the match manager talks to an engine
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EngineProtocol {
+    /// The protocol of the `engine` binary, see [`crate::ai::protocol`]
+    Mei,
+    /// One line `P|M W|B <board>` per move, `P` while the pieces are placed, answered by one line with the move
+    Legacy,
+}
+impl EngineProtocol {
+    pub fn parse(name: &str) -> Result<EngineProtocol, ExternalError> {
+        match name {
+            "mei" => Ok(EngineProtocol::Mei),
+            "legacy" => Ok(EngineProtocol::Legacy),
+            _ => Err(ExternalError::new(format!("Unknown protocol \"{}\", expected mei or legacy", name)))
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct EngineConfig {
+    /// Program and its arguments, separated by whitespace
+    pub command: String,
+    pub protocol: EngineProtocol,
+}
+
+/// Seconds on the clock of each player at the start and added after each of their moves
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeControl {
+    pub base: f64,
+    pub increment: f64,
+}
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl { base: 60.0, increment: 1.0 }
+    }
+}
+impl TimeControl {
+    /// Parses `<base>+<increment>` or only `<base>`, both in seconds
+    pub fn parse(text: &str) -> Result<TimeControl, ExternalError> {
+        let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+        match (base.parse::<f64>(), increment.parse::<f64>()) {
+            (Ok(base), Ok(increment)) if base > 0.0 && increment >= 0.0 => Ok(TimeControl { base, increment }),
+            _ => Err(ExternalError::new(format!("Invalid time control \"{}\", expected e.g. 60+1", text)))
+        }
+    }
+}
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}", self.base, self.increment)
+    }
+}
+
+/// A running engine process
+pub struct ExternalEngine {
+    pub name: String,
+    config: EngineConfig,
+    child: Child,
+    input: ChildStdin,
+    /// Lines of the engine, closed once it exits
+    output: Receiver<String>,
+}
+impl ExternalEngine {
+    pub fn start(config: &EngineConfig) -> Result<ExternalEngine, ExternalError> {
+        let mut words = config.command.split_whitespace();
+        let program = words.next().ok_or(ExternalError::new("Empty engine command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| ExternalError::new(format!("Cannot start \"{}\": {}", config.command, err)))?;
+
+        let input = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, output) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break
+                }
+            }
+        });
+
+        let name = program.rsplit(['/', '\\']).next().unwrap_or(program).to_string();
+        let mut engine = ExternalEngine { name, config: config.clone(), child, input, output };
+        if config.protocol == EngineProtocol::Mei {
+            engine.send("mei")?;
+            loop {
+                let line = engine.receive(HANDSHAKE_TIME)?.ok_or(ExternalError::new(format!("{} did not answer mei", engine.name)))?;
+                if let Some(name) = line.strip_prefix("id name ") {
+                    engine.name = name.trim().to_string();
+                } else if line.trim() == "meiok" {
+                    break
+                }
+            }
+        }
+        Ok(engine)
+    }
+
+    /// The same engine in a new process, e.g. after it lost on time and may still be thinking
+    pub fn restart(&mut self) -> Result<(), ExternalError> {
+        *self = ExternalEngine::start(&self.config)?;
+        Ok(())
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), ExternalError> {
+        writeln!(self.input, "{}", line)
+            .and_then(|_| self.input.flush())
+            .map_err(|_| ExternalError::new(format!("{} has exited", self.name)))
+    }
+
+    /// The next line within `seconds`, none if the engine did not answer in time
+    fn receive(&self, seconds: f64) -> Result<Option<String>, ExternalError> {
+        match self.output.recv_timeout(Duration::from_secs_f64(seconds.max(0.0))) {
+            Ok(line) => Ok(Some(line)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(ExternalError::new(format!("{} has exited", self.name)))
+        }
+    }
+
+    /// Tells the engine a new game starts and waits until it is ready
+    pub fn new_game(&mut self) -> Result<(), ExternalError> {
+        match self.config.protocol {
+            EngineProtocol::Mei => {
+                self.send("newgame")?;
+                self.send("isready")?;
+                // skips whatever is left of the last game
+                while self.receive(HANDSHAKE_TIME)?.ok_or(ExternalError::new(format!("{} is not ready", self.name)))?.trim() != "readyok" {}
+                Ok(())
+            },
+            EngineProtocol::Legacy => Ok(())
+        }
+    }
+
+    /// Asks for the move in `game`, which was reached by `moves` from the start. `clocks` holds the seconds
+    /// left of white and black. Returns none if no answer came before the clock of the side to move ran out.
+    pub fn request_move(&mut self, game: &Game, moves: &[Action], clocks: [f64; 2], increment: f64) -> Result<Option<String>, ExternalError> {
+        let side = if game.get_player_turn() == 0b11 { 0 } else { 1 };
+        let deadline = timer::time() + clocks[side];
+
+        match self.config.protocol {
+            EngineProtocol::Mei => {
+                let moves: Vec<String> = moves.iter().map(|action| action.to_string()).collect();
+                if moves.is_empty() {
+                    self.send("position startpos")?;
+                } else {
+                    self.send(&format!("position startpos moves {}", moves.join(" ")))?;
+                }
+                let milliseconds = |seconds: f64| (seconds * 1000.0).max(0.0) as u64;
+                self.send(&format!("go wtime {} btime {} winc {} binc {}",
+                    milliseconds(clocks[0]), milliseconds(clocks[1]), milliseconds(increment), milliseconds(increment)))?;
+
+                loop {
+                    let Some(line) = self.receive(deadline - timer::time())? else {
+                        self.send("stop")?;
+                        return Ok(None)
+                    };
+                    if let Some(action) = line.trim().strip_prefix("bestmove") {
+                        return Ok(Some(action.trim().to_string()))
+                    }
+                }
+            },
+            EngineProtocol::Legacy => {
+                let phase = if game.get_setup_pieces_left() > 0 { "P" } else { "M" };
+                let color = if side == 0 { "W" } else { "B" };
+                self.send(&format!("{} {} {}", phase, color, encode_positions(game.get_board())))?;
+
+                loop {
+                    let Some(line) = self.receive(deadline - timer::time())? else {
+                        return Ok(None)
+                    };
+                    if !line.trim().is_empty() {
+                        return Ok(Some(line.trim().to_string()))
+                    }
+                }
+            }
+        }
+    }
+}
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        if self.config.protocol == EngineProtocol::Mei {
+            let _ = self.send("quit");
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Termination {
+    /// A player has no pieces or moves left
+    Normal,
+    IllegalMove,
+    TimeForfeit,
+    /// The engine exited or closed its input
+    EngineError,
+    MoveLimit,
+}
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Termination::Normal => "normal",
+            Termination::IllegalMove => "illegal move",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::EngineError => "engine error",
+            Termination::MoveLimit => "move limit"
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    pub time_control: TimeControl,
+    /// The first moves were random and not chosen by the engines
+    pub opening_plies: usize,
+    pub moves: Vec<Action>,
+    /// Player who won, none for a draw
+    pub winner: Option<u8>,
+    pub termination: Termination,
+    /// What went wrong, e.g. the text of an illegal move
+    pub comment: String,
+}
+impl GameRecord {
+    pub fn result(&self) -> &'static str {
+        match self.winner {
+            Some(0b11) => "1-0",
+            Some(_) => "0-1",
+            None => "1/2-1/2"
+        }
+    }
+
+    /// Tag pairs followed by the numbered moves, similar to PGN
+    pub fn to_text(&self, round: usize) -> String {
+        let mut text = format!("[Round \"{}\"]\n[White \"{}\"]\n[Black \"{}\"]\n[TimeControl \"{}\"]\n[Opening \"{}\"]\n[Result \"{}\"]\n[Termination \"{}\"]\n",
+            round, self.white, self.black, self.time_control, self.opening_plies, self.result(), self.termination);
+        if !self.comment.is_empty() {
+            text += &format!("[Comment \"{}\"]\n", self.comment.replace('"', "'"));
+        }
+        text += "\n";
+
+        for (index, pair) in self.moves.chunks(2).enumerate() {
+            let moves: Vec<String> = pair.iter().map(|action| action.to_string()).collect();
+            text += &format!("{}. {} ", index + 1, moves.join(" "));
+        }
+        text + self.result() + "\n\n"
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MatchOptions {
+    pub games: usize,
+    /// Random plies at the start of every opening, each opening is played once with either color
+    pub opening_plies: usize,
+    /// Longer games are a draw
+    pub max_plies: usize,
+    pub time_control: TimeControl,
+    pub seed: u64,
+    /// File every game is appended to
+    pub output: Option<String>,
+}
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            games: 10,
+            opening_plies: 4,
+            max_plies: 200,
+            time_control: TimeControl::default(),
+            seed: 1,
+            output: None,
+        }
+    }
+}
+
+/// `plies` random legal moves from the start
+pub fn random_opening(plies: usize, seed: u64) -> Vec<Action> {
+    let mut rng = Rng::new(seed);
+    let mut game = Game::new();
+    let mut moves = Vec::with_capacity(plies);
+    for _ in 0..plies {
+        let root = RootPosition::of(&game);
+        let forward_boards = root.forward_boards();
+        if forward_boards.is_empty() || game.get_state() == State::Win {
+            break
+        }
+        let action = root.action(forward_boards[rng.below(forward_boards.len())]);
+        compute_checked_step(action, &mut game).expect("generated moves are legal");
+        moves.push(action);
+    }
+    moves
+}
+
+/// Plays one game after `opening` and referees it with the rules of `core`. An illegal move, running out
+/// of time or an engine error lose the game.
+pub fn play_external_game(white: &mut ExternalEngine, black: &mut ExternalEngine, opening: &[Action], options: &MatchOptions) -> GameRecord {
+    let mut record = GameRecord {
+        white: white.name.clone(),
+        black: black.name.clone(),
+        time_control: options.time_control,
+        opening_plies: opening.len(),
+        moves: Vec::new(),
+        winner: None,
+        termination: Termination::Normal,
+        comment: String::new(),
+    };
+    let mut game = Game::new();
+    for action in opening {
+        compute_checked_step(*action, &mut game).expect("openings are legal");
+        record.moves.push(*action);
+    }
+
+    let increment = options.time_control.increment;
+    let mut clocks = [options.time_control.base; 2];
+    while game.get_state() != State::Win {
+        if record.moves.len() >= options.max_plies {
+            record.termination = Termination::MoveLimit;
+            return record
+        }
+
+        let player = game.get_player_turn();
+        let side = if player == 0b11 { 0 } else { 1 };
+        let engine = if side == 0 { &mut *white } else { &mut *black };
+
+        let start_time = timer::time();
+        let reply = engine.request_move(&game, &record.moves, clocks, increment);
+        clocks[side] -= timer::time() - start_time;
+
+        let failure = match reply {
+            Err(err) => Some((Termination::EngineError, err.message)),
+            Ok(None) => Some((Termination::TimeForfeit, String::new())),
+            Ok(Some(_)) if clocks[side] < 0.0 => Some((Termination::TimeForfeit, String::new())),
+            Ok(Some(text)) => match text.parse::<Action>().and_then(|action| compute_checked_step(action, &mut game).map(|_| action)) {
+                Ok(action) => {
+                    record.moves.push(action);
+                    clocks[side] += increment;
+                    None
+                },
+                Err(err) => Some((Termination::IllegalMove, format!("\"{}\": {}", text, err.message)))
+            }
+        };
+        if let Some((termination, comment)) = failure {
+            record.winner = Some(negate_token(player));
+            record.termination = termination;
+            record.comment = comment;
+            return record
+        }
+    }
+
+    record.winner = Some(game.get_winner());
+    record
+}
+
+/// Plays `options.games` games between two engine processes, games `2n` and `2n + 1` share an opening and
+/// swap the colors. `progress` gets every game and the results of the first engine so far.
+pub fn run_match(first: &EngineConfig, second: &EngineConfig, options: &MatchOptions, mut progress: impl FnMut(usize, &GameRecord, &MatchStats)) -> Result<MatchStats, ExternalError> {
+    let mut engines = [ExternalEngine::start(first)?, ExternalEngine::start(second)?];
+    let mut output = match &options.output {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|err| ExternalError::new(format!("Cannot open {}: {}", path, err)))?),
+        None => None
+    };
+
+    let mut stats = MatchStats::default();
+    for index in 0..options.games {
+        let first_is_white = index % 2 == 0;
+        let opening = random_opening(options.opening_plies, options.seed.wrapping_mul(1_000_003).wrapping_add((index / 2) as u64));
+        for engine in engines.iter_mut() {
+            engine.new_game()?;
+        }
+
+        let [first_engine, second_engine] = &mut engines;
+        let record = if first_is_white {
+            play_external_game(first_engine, second_engine, &opening, options)
+        } else {
+            play_external_game(second_engine, first_engine, &opening, options)
+        };
+
+        let first_color = if first_is_white { 0b11 } else { 0b10 };
+        stats.add(match record.winner {
+            None => GameResult::Draw,
+            Some(winner) if winner == first_color => GameResult::Win,
+            Some(_) => GameResult::Loss
+        });
+        if let Some(file) = output.as_mut() {
+            file.write_all(record.to_text(index + 1).as_bytes())
+                .map_err(|err| ExternalError::new(format!("Cannot write the game: {}", err)))?;
+        }
+        progress(index + 1, &record, &stats);
+
+        // an engine which lost on time may still be thinking and one with an error may be gone
+        if matches!(record.termination, Termination::TimeForfeit | Termination::EngineError) {
+            let loser = if record.winner == Some(first_color) { 1 } else { 0 };
+            engines[loser].restart()?;
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::external::{play_external_game, random_opening, EngineConfig, EngineProtocol, ExternalEngine, MatchOptions, Termination, TimeControl};
+    use crate::core::enums::State;
+    use crate::core::game::Game;
+    use crate::core::logic::compute_checked_step;
+
+    #[test]
+    fn test_referee_rejects_illegal_moves() {
+        let mut game = Game::new();
+        for action in random_opening(6, 3) {
+            compute_checked_step(action, &mut game).unwrap();
+        }
+        assert_eq!(6, game.get_step_counter());
+
+        let occupied = (0..24).find(|position| game.get_token_at(*position) != 0).unwrap();
+        assert!(compute_checked_step(occupied.to_string().parse().unwrap(), &mut game).is_err());
+        assert!(compute_checked_step("1-2".parse().unwrap(), &mut Game::new()).is_err());
+        assert!(compute_checked_step("1x2".parse().unwrap(), &mut Game::new()).is_err());
+        assert_eq!(State::Setup, game.get_state());
+    }
+
+    #[test]
+    fn test_engine_answering_nonsense_loses() {
+        // cat answers with the position it was sent, which is no move
+        let config = EngineConfig { command: "cat".to_string(), protocol: EngineProtocol::Legacy };
+        let (mut white, mut black) = (ExternalEngine::start(&config).unwrap(), ExternalEngine::start(&config).unwrap());
+        let options = MatchOptions { time_control: TimeControl::parse("5").unwrap(), ..MatchOptions::default() };
+
+        let record = play_external_game(&mut white, &mut black, &random_opening(3, 1), &options);
+        assert_eq!(Some(0b11), record.winner);
+        assert_eq!(Termination::IllegalMove, record.termination);
+        assert!(record.comment.starts_with("\"P B "), "{}", record.comment);
+        assert!(record.to_text(1).contains("[Result \"1-0\"]"));
+        assert!(TimeControl::parse("1+x").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::ai::action::forward_step_boards;
+use crate::ai::evaluation::{mill_gaps, piece_masks, terminal_score, Evaluator, FULL_BOARD};
+use crate::ai::ordering::move_squares;
+use crate::ai::random::Rng;
+use crate::ai::timer;
+use crate::ai::{Phase, RootPosition};
+use crate::core::position::negate_token;
+
+/// How the moves of a playout are picked once it leaves the tree
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RolloutPolicy {
+    /// Every move is equally likely
+    Random,
+    /// Takes a piece whenever possible, otherwise blocks a mill of the opponent and only then plays randomly
+    MillAware,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MctsOptions {
+    /// Maximum number of playouts, the search also stops once `time_limit` seconds are over
+    pub playouts: usize,
+    pub time_limit: Option<f64>,
+    /// Weight of the exploration term of UCT
+    pub exploration: f64,
+    pub rollout_policy: RolloutPolicy,
+    /// Playouts which are still running after this many plies are scored by the evaluator
+    pub max_rollout_plies: usize,
+    pub seed: Option<u64>,
+}
+impl Default for MctsOptions {
+    fn default() -> Self {
+        MctsOptions {
+            playouts: 10_000,
+            time_limit: None,
+            exploration: std::f64::consts::SQRT_2,
+            rollout_policy: RolloutPolicy::MillAware,
+            max_rollout_plies: 60,
+            seed: None,
+        }
+    }
+}
+
+/// Evaluation which is worth about three quarters of a win in a cut off playout
+const EVALUATION_SCALE: f64 = 1000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MctsResult {
+    pub forward_board: u64,
+    /// Visits of the chosen root move
+    pub visits: u32,
+    /// Average result of the chosen move for the side to move, between 0 and 1
+    pub win_rate: f64,
+    pub playouts: usize,
+}
+
+struct Node {
+    board: u64,
+    /// Side to move in this node
+    token_type: u8,
+    phase: Phase,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<u64>,
+    visits: u32,
+    /// Sum of the results for the side which moved into this node
+    reward: f64,
+    /// Result for white if the game is over
+    terminal: Option<f64>,
+}
+impl Node {
+    fn new(board: u64, token_type: u8, phase: Phase, parent: Option<usize>) -> Self {
+        let terminal = terminal_result(board, phase);
+        let untried = if terminal.is_some() {
+            Vec::new()
+        } else {
+            forward_step_boards(&board, token_type, phase).collect()
+        };
+        Node { board, token_type, phase, parent, children: Vec::new(), untried, visits: 0, reward: 0.0, terminal }
+    }
+}
+
+/// Monte Carlo tree search with UCT selection, returns the most visited move of the root
+pub fn mcts(root: &RootPosition, options: &MctsOptions, evaluator: &dyn Evaluator) -> Option<MctsResult> {
+    mcts_until(root, options, evaluator, || false)
+}
+
+/// [`mcts`] which also ends once `stopped` returns true, e.g. after a `stop` from another thread
+pub fn mcts_until(root: &RootPosition, options: &MctsOptions, evaluator: &dyn Evaluator, stopped: impl Fn() -> bool) -> Option<MctsResult> {
+    let RootPosition { board, token_type, phase, .. } = *root;
+    let start_time = timer::time();
+    let mut rng = match options.seed {
+        Some(seed) => Rng::new(seed),
+        None => Rng::from_time(),
+    };
+
+    let mut nodes = vec![Node::new(board, token_type, phase, None)];
+    nodes[0].untried = root.forward_boards();
+    match nodes[0].untried.len() {
+        0 => return None,
+        1 => return Some(MctsResult { forward_board: nodes[0].untried[0], visits: 0, win_rate: 0.5, playouts: 0 }),
+        _ => ()
+    }
+
+    // playouts can not tell a win now from a win later
+    let win = if token_type == 0b11 { 1.0 } else { 0.0 };
+    let next_phase = advance(phase);
+    if let Some(forward_board) = nodes[0].untried.iter().find(|forward_board| terminal_result(**forward_board, next_phase) == Some(win)) {
+        return Some(MctsResult { forward_board: *forward_board, visits: 0, win_rate: 1.0, playouts: 0 })
+    }
+
+    let mut playouts = 0;
+    while playouts < options.playouts {
+        if options.time_limit.is_some_and(|limit| timer::time() - start_time > limit) || stopped() {
+            break
+        }
+
+        let mut index = select(&nodes, options.exploration);
+        if !nodes[index].untried.is_empty() {
+            index = expand(&mut nodes, index, &mut rng);
+        }
+        let node = &nodes[index];
+        let result = match node.terminal {
+            Some(result) => result,
+            None => rollout(node.board, node.token_type, node.phase, options, evaluator, &mut rng),
+        };
+        backpropagate(&mut nodes, index, result);
+        playouts += 1;
+    }
+
+    let best = nodes[0].children.iter()
+        .max_by_key(|child| nodes[**child].visits)
+        .copied()?;
+    let best = &nodes[best];
+    Some(MctsResult {
+        forward_board: best.board,
+        visits: best.visits,
+        win_rate: best.reward / best.visits.max(1) as f64,
+        playouts,
+    })
+}
+
+/// Walks down the fully expanded part of the tree
+fn select(nodes: &[Node], exploration: f64) -> usize {
+    let mut index = 0;
+    loop {
+        let node = &nodes[index];
+        if !node.untried.is_empty() || node.children.is_empty() {
+            return index
+        }
+
+        let log_visits = (node.visits.max(1) as f64).ln();
+        index = *node.children.iter()
+            .max_by(|a, b| uct(&nodes[**a], log_visits, exploration).total_cmp(&uct(&nodes[**b], log_visits, exploration)))
+            .unwrap();
+    }
+}
+
+fn uct(node: &Node, log_parent_visits: f64, exploration: f64) -> f64 {
+    if node.visits == 0 {
+        return f64::INFINITY
+    }
+    let visits = node.visits as f64;
+    node.reward / visits + exploration * (log_parent_visits / visits).sqrt()
+}
+
+fn expand(nodes: &mut Vec<Node>, index: usize, rng: &mut Rng) -> usize {
+    let untried = &mut nodes[index].untried;
+    let forward_board = untried.swap_remove(rng.below(untried.len()));
+    let node = &nodes[index];
+    let child = Node::new(forward_board, negate_token(node.token_type), advance(node.phase), Some(index));
+
+    nodes.push(child);
+    let child_index = nodes.len() - 1;
+    nodes[index].children.push(child_index);
+    child_index
+}
+
+fn backpropagate(nodes: &mut [Node], mut index: usize, white_result: f64) {
+    loop {
+        let node = &mut nodes[index];
+        node.visits += 1;
+        // the reward belongs to the side which moved into the node
+        node.reward += if node.token_type == 0b11 { 1.0 - white_result } else { white_result };
+        match node.parent {
+            Some(parent) => index = parent,
+            None => return
+        }
+    }
+}
+
+/// Plays the game out and returns the result for white
+fn rollout(mut board: u64, mut token_type: u8, mut phase: Phase, options: &MctsOptions, evaluator: &dyn Evaluator, rng: &mut Rng) -> f64 {
+    let mut moves = Vec::new();
+    for _ in 0..options.max_rollout_plies {
+        if let Some(result) = terminal_result(board, phase) {
+            return result
+        }
+
+        moves.clear();
+        moves.extend(forward_step_boards(&board, token_type, phase));
+        if moves.is_empty() {
+            return 0.5
+        }
+
+        board = match options.rollout_policy {
+            RolloutPolicy::Random => moves[rng.below(moves.len())],
+            RolloutPolicy::MillAware => mill_aware_move(board, &moves, token_type, rng),
+        };
+        token_type = negate_token(token_type);
+        phase = advance(phase);
+    }
+
+    terminal_result(board, phase)
+        .unwrap_or_else(|| 1.0 / (1.0 + (-evaluator.evaluate(board, phase) as f64 / EVALUATION_SCALE).exp()))
+}
+
+fn mill_aware_move(board: u64, moves: &[u64], token_type: u8, rng: &mut Rng) -> u64 {
+    let (white, black) = piece_masks(board);
+    let opponent = if token_type == 0b11 { black } else { white };
+    let opponent_gaps = mill_gaps(opponent, FULL_BOARD & !(white | black));
+
+    let mut blocking = None;
+    let mut blocking_count = 0;
+    let mut capturing = None;
+    let mut capturing_count = 0;
+    for forward_board in moves {
+        let (_, end, capture) = move_squares(board, *forward_board, token_type);
+        // reservoir sampling keeps the choice uniform among equally good moves
+        if capture {
+            capturing_count += 1;
+            if rng.below(capturing_count) == 0 {
+                capturing = Some(*forward_board);
+            }
+        } else if opponent_gaps & (1 << end) != 0 {
+            blocking_count += 1;
+            if rng.below(blocking_count) == 0 {
+                blocking = Some(*forward_board);
+            }
+        }
+    }
+
+    capturing.or(blocking).unwrap_or_else(|| moves[rng.below(moves.len())])
+}
+
+fn terminal_result(board: u64, phase: Phase) -> Option<f64> {
+    terminal_score(board, phase).map(|score| if score > 0 { 1.0 } else { 0.0 })
+}
+
+/// The step counter only matters for the phase change, long playouts must not overflow it
+fn advance(phase: Phase) -> Phase {
+    if phase.step_counter < u8::MAX {
+        phase.increased()
+    } else {
+        phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::evaluation::{terminal_score, MuehleEvaluator};
+    use crate::ai::mcts::{mcts, MctsOptions};
+    use crate::ai::{Phase, PhaseType, RootPosition};
+    use crate::core::position::decode_positions;
+    use crate::core::utils::{insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+    fn board_of(positions: &str) -> u64 {
+        insert_number_of_possible_moves_to_board(insert_token_count_to_board(decode_positions(positions.to_string())))
+    }
+
+    #[test]
+    fn test_finds_winning_mill() {
+        // no move wins at once, after 5-6 white closes either 15 14 13 with 6-14 or 5 4 3 again with 6-5
+        let board = board_of("EEEWWWEEEEBEBWEWBEEEEEEE");
+        let phase = Phase::new(PhaseType::Move, 40);
+        let options = MctsOptions { playouts: 2000, seed: Some(7), ..MctsOptions::default() };
+
+        let root = RootPosition::new(board, 0b11, phase);
+        assert!(root.forward_boards().iter().all(|&forward_board| terminal_score(forward_board, phase.increased()).is_none()));
+        let result = mcts(&root, &options, &MuehleEvaluator::default()).unwrap();
+        assert!(result.playouts > 0);
+        assert_eq!("5-6", root.action(result.forward_board).to_string());
+    }
+
+    #[test]
+    fn test_playout_limit() {
+        let board = board_of("EEEEEEEEEEEEEEEEEEEEEEEE");
+        let options = MctsOptions { playouts: 300, seed: Some(1), ..MctsOptions::default() };
+
+        let root = RootPosition::new(board, 0b11, Phase::new(PhaseType::Set, 0));
+        let result = mcts(&root, &options, &MuehleEvaluator::default()).unwrap();
+        assert_eq!(result.playouts, 300);
+        let again = mcts(&root, &options, &MuehleEvaluator::default()).unwrap();
+        assert_eq!(result, again);
+    }
+}
@@ -1,88 +1,338 @@
-use good_web_game::timer;
-use itertools::Itertools;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use crate::ai::action::forward_step_boards;
+use crate::ai::endgame::{EndgameDatabase, Outcome};
+use crate::ai::evaluation::{mill_gaps, piece_masks, terminal_score, Evaluator};
+use crate::ai::ordering::{move_squares, order_moves, MoveOrdering, OrderingTables};
+use crate::ai::timer;
+use crate::ai::transposition::{move_of, Bound, Entry, TranspositionTable};
 use crate::core::position::negate_token;
-use crate::core::utils::{extract_black_move_count_from_board, extract_black_token_count_from_board, extract_white_move_count_from_board, extract_white_token_count_from_board};
-use crate::ai::{Phase, PhaseType};
+use crate::ai::{score, Phase};
 
-pub fn minimax(board: u64, depth: usize, mut alpha: isize, mut beta: isize, maximizing_player: u8, phase: Phase, time: f64) -> Option<isize> {
-    if timer::time() - time > 0.980 {
-        return None;
-    }
-    
-    let black_token_count = extract_black_token_count_from_board(board);
-    let white_token_count = extract_white_token_count_from_board(board);
-    if phase.phase == PhaseType::Move {
-        if (extract_black_move_count_from_board(board) == 0 && black_token_count > 3) || black_token_count == 2 {
-            return Some(isize::MAX - phase.step_counter as isize)
-        } else if (extract_white_move_count_from_board(board) == 0 && white_token_count > 3) || white_token_count == 2 {
-            return Some(isize::MIN + phase.step_counter as isize)
+const TRANSPOSITION_TABLE_BITS: u32 = 18;
+/// Default for [`Search::quiescence_depth`]
+pub const QUIESCENCE_DEPTH: usize = 6;
+
+/// Everything a search needs besides the position, shared by all threads
+pub struct Search<'a> {
+    pub evaluator: &'a dyn Evaluator,
+    pub ordering: MoveOrdering,
+    pub transposition_table: TranspositionTable,
+    pub ordering_tables: OrderingTables,
+    /// How many mill closing plies are searched at most after the regular depth, 0 disables the quiescence search
+    pub quiescence_depth: usize,
+    /// Positions in the tables are not searched but scored exactly
+    pub endgame: Option<&'a EndgameDatabase>,
+    /// Threads of the lazy SMP search in [`iterative_deepening`](crate::ai::iterative_deepening), the default 1 searches
+    /// without helpers
+    pub threads: usize,
+    start_time: f64,
+    time_limit: Option<f64>,
+    stopped: AtomicBool,
+    nodes: AtomicU64,
+    evaluations: AtomicU64,
+}
+
+impl<'a> Search<'a> {
+    /// Without a time limit the search only stops at the given depth
+    pub fn new(evaluator: &'a dyn Evaluator, ordering: MoveOrdering, time_limit: Option<f64>) -> Self {
+        Search {
+            evaluator,
+            ordering,
+            transposition_table: TranspositionTable::new(TRANSPOSITION_TABLE_BITS),
+            ordering_tables: OrderingTables::new(),
+            quiescence_depth: QUIESCENCE_DEPTH,
+            endgame: None,
+            threads: 1,
+            start_time: timer::time(),
+            time_limit,
+            stopped: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+            evaluations: AtomicU64::new(0),
         }
     }
-    
-    if depth == 0 {
-        return Some(evaluate_action(board, phase));
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
     }
 
-    let forward_step_boards = forward_step_boards(&board, maximizing_player, phase)
-        .sorted_by(|board1, board2| {
-            let board1_eval = evaluate_action(*board1, phase);
-            let board2_eval = evaluate_action(*board2, phase);
-            if maximizing_player == 0b11 {
-                board2_eval.cmp(&board1_eval)
-            } else {
-                board1_eval.cmp(&board2_eval)
-            }
-    });
-    
-    if maximizing_player == 0b11 {
-        let mut max_eval = isize::MIN + phase.step_counter as isize;
-
-        for forward_board in forward_step_boards {
-            let eval = minimax(forward_board, depth - 1, alpha, beta, negate_token(maximizing_player), phase.increased(), time);
-            eval?;
-            max_eval = std::cmp::max(max_eval, eval.unwrap());
-            
-            alpha = std::cmp::max(alpha, eval.unwrap());
-            if beta <= alpha {
-                break;
+    /// Static evaluations at the leaves and for the move ordering
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations.load(Ordering::Relaxed)
+    }
+
+    fn evaluate(&self, board: u64, phase: Phase) -> isize {
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        self.evaluator.evaluate(board, phase)
+    }
+
+    /// Score for `token_type` to move if the position is in the endgame tables, `ply` plies below the root
+    fn probe_endgame(&self, board: u64, token_type: u8, phase: Phase, ply: usize) -> Option<isize> {
+        Some(match self.endgame?.probe(board, token_type, phase)? {
+            Outcome::Win(distance) => score::win_in(ply + distance as usize),
+            Outcome::Loss(distance) => score::loss_in(ply + distance as usize),
+            Outcome::Draw => 0
+        })
+    }
+
+    /// Makes every thread of the search give up, returns whether it was stopped already
+    pub fn stop(&self) -> bool {
+        self.stopped.swap(true, Ordering::Relaxed)
+    }
+
+    /// Whether the search was stopped or ran out of time
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    pub fn resume(&self) {
+        self.stopped.store(false, Ordering::Relaxed);
+    }
+
+    fn is_out_of_time(&self) -> bool {
+        if self.stopped.load(Ordering::Relaxed) {
+            return true
+        }
+
+        let nodes = self.nodes.fetch_add(1, Ordering::Relaxed);
+        if let Some(time_limit) = self.time_limit {
+            if nodes & 0xFF == 0 && timer::time() - self.start_time > time_limit {
+                self.stopped.store(true, Ordering::Relaxed);
+                return true
             }
         }
-        Some(max_eval)
+        false
+    }
+}
+
+/// One thread per core, the browser build has only one
+pub fn default_threads() -> usize {
+    if cfg!(target_arch = "wasm32") {
+        1
     } else {
-        let mut min_eval = isize::MAX - phase.step_counter as isize;
-        for forward_board in forward_step_boards {
-            let eval = minimax(forward_board, depth - 1, alpha, beta, negate_token(maximizing_player), phase.increased(), time);
-            eval?;
-            min_eval = std::cmp::min(min_eval, eval.unwrap());
-            
-            beta = std::cmp::min(beta, eval.unwrap());
-            if beta <= alpha {
-                break;
-            }
+        std::thread::available_parallelism().map_or(1, |threads| threads.get())
+    }
+}
+
+/// Negamax with principal variation search. Scores are from the view of `token_type`,
+/// the first move gets the full window and every other move a zero window which is only re-searched if it fails high.
+#[allow(clippy::too_many_arguments)]
+pub fn negamax(search: &Search, board: u64, depth: usize, mut alpha: isize, beta: isize, token_type: u8, phase: Phase, ply: usize) -> Option<isize> {
+    if search.is_out_of_time() {
+        return None;
+    }
+
+    let color: isize = if token_type == 0b11 { 1 } else { -1 };
+    if let Some(score) = terminal_score(board, phase) {
+        return Some(score::from_ply(color * score, ply))
+    }
+    if let Some(score) = search.probe_endgame(board, token_type, phase, ply) {
+        return Some(score)
+    }
+
+    if depth == 0 {
+        return quiescence(search, board, alpha, beta, token_type, phase, search.quiescence_depth, ply);
+    }
+
+    let key = TranspositionTable::key(board, token_type, phase);
+    let entry = search.transposition_table.probe(key);
+    // an entry searched deeper is at least as good, a shallower one still gives its move to the ordering
+    if let Some(entry) = entry.filter(|entry| entry.depth as usize >= depth) {
+        let score = score::from_ply(entry.score, ply);
+        match entry.bound {
+            Bound::Exact => return Some(score),
+            Bound::Lower if score >= beta => return Some(score),
+            Bound::Upper if score <= alpha => return Some(score),
+            _ => ()
+        }
+    }
+    let hash_move = entry.map(|entry| entry.best_move);
+    let mut forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+    let ordering_evaluations = order_moves(search.ordering, &search.ordering_tables, search.evaluator, board, &mut forward_boards, token_type, phase, ply, hash_move);
+    search.evaluations.fetch_add(ordering_evaluations, Ordering::Relaxed);
+
+    let alpha_start = alpha;
+    let mut best_board = None;
+    let mut best_score = score::loss_in(ply);
+    for (index, forward_board) in forward_boards.into_iter().enumerate() {
+        let mut score = if index == 0 {
+            -negamax(search, forward_board, depth - 1, -beta, -alpha, negate_token(token_type), phase.increased(), ply + 1)?
+        } else {
+            -negamax(search, forward_board, depth - 1, -alpha - 1, -alpha, negate_token(token_type), phase.increased(), ply + 1)?
+        };
+        if index > 0 && score > alpha && score < beta {
+            score = -negamax(search, forward_board, depth - 1, -beta, -alpha, negate_token(token_type), phase.increased(), ply + 1)?;
+        }
+
+        if best_board.is_none() || score > best_score {
+            best_board = Some(forward_board);
+            best_score = score;
+        }
+        alpha = std::cmp::max(alpha, score);
+        if alpha >= beta {
+            search.ordering_tables.record_cutoff(board, forward_board, token_type, depth, ply);
+            break;
         }
-        Some(min_eval)
     }
+
+    if let Some(best_board) = best_board {
+        let bound = if best_score <= alpha_start {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        search.transposition_table.store(key, Entry { best_move: move_of(board, best_board), depth: depth as u8, score: score::to_ply(best_score, ply), bound });
+    }
+    Some(best_score)
 }
 
-fn evaluate_action(positions: u64, phase: Phase) -> isize {
-    let mut score: isize = 0;
-    let black_move_count = extract_black_move_count_from_board(positions);
-    let white_move_count = extract_white_move_count_from_board(positions);
-    let black_token_count = extract_black_token_count_from_board(positions);
-    let white_token_count = extract_white_token_count_from_board(positions);
-
-    if phase.phase == PhaseType::Move {
-        if (black_move_count == 0 && black_token_count > 3) || black_token_count == 2 {
-            return isize::MAX - phase.step_counter as isize
-        } else if (white_move_count == 0 && white_token_count > 3) || white_token_count == 2 {
-            return isize::MIN + phase.step_counter as isize
+/// Keeps searching moves which close a mill until the position is quiet or `depth` runs out,
+/// so the search does not stop right before a capture. The side to move may always stand pat.
+#[allow(clippy::too_many_arguments)]
+fn quiescence(search: &Search, board: u64, mut alpha: isize, beta: isize, token_type: u8, phase: Phase, depth: usize, ply: usize) -> Option<isize> {
+    if search.is_out_of_time() {
+        return None;
+    }
+
+    let color: isize = if token_type == 0b11 { 1 } else { -1 };
+    if let Some(score) = terminal_score(board, phase) {
+        return Some(score::from_ply(color * score, ply))
+    }
+    if let Some(score) = search.probe_endgame(board, token_type, phase, ply) {
+        return Some(score)
+    }
+
+    let stand_pat = color * search.evaluate(board, phase);
+    if depth == 0 || stand_pat >= beta {
+        return Some(stand_pat)
+    }
+
+    // every mill closing move ends in one of the gaps
+    let (white, black) = piece_masks(board);
+    let own = if token_type == 0b11 { white } else { black };
+    if mill_gaps(own, !(white | black) & 0xFFFFFF) == 0 {
+        return Some(stand_pat)
+    }
+
+    let mut best_score = stand_pat;
+    alpha = std::cmp::max(alpha, stand_pat);
+    let captures = forward_step_boards(&board, token_type, phase)
+        .filter(|forward_board| move_squares(board, *forward_board, token_type).2);
+    for forward_board in captures {
+        let score = -quiescence(search, forward_board, -beta, -alpha, negate_token(token_type), phase.increased(), depth - 1, ply + 1)?;
+
+        best_score = std::cmp::max(best_score, score);
+        alpha = std::cmp::max(alpha, score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    Some(best_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::action::forward_step_boards;
+    use crate::ai::benchmark::BENCHMARK_POSITIONS;
+    use crate::ai::evaluation::{terminal_score, Evaluator, MuehleEvaluator};
+    use crate::ai::minimax::{negamax, Search};
+    use crate::ai::ordering::MoveOrdering;
+    use crate::ai::score::Score;
+    use crate::ai::{iterative_deepening, score, Phase, PhaseType};
+    use crate::core::position::{decode_positions, negate_token};
+    use crate::core::utils::{insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+    // plain minimax from the view of white without any pruning
+    fn reference_minimax(evaluator: &dyn Evaluator, board: u64, depth: usize, token_type: u8, phase: Phase, ply: usize) -> isize {
+        if let Some(score) = terminal_score(board, phase) {
+            return score::from_ply(score, ply)
+        }
+        if depth == 0 {
+            return evaluator.evaluate(board, phase)
+        }
+
+        let scores = forward_step_boards(&board, token_type, phase)
+            .map(|forward_board| reference_minimax(evaluator, forward_board, depth - 1, negate_token(token_type), phase.increased(), ply + 1));
+        if token_type == 0b11 { scores.max().unwrap() } else { scores.min().unwrap() }
+    }
+
+    #[test]
+    fn test_same_score_as_plain_minimax() {
+        let evaluator = MuehleEvaluator::default();
+        for position in BENCHMARK_POSITIONS.iter().skip(1) {
+            let phase = Phase::new(position.phase, position.step_counter);
+            let expected = reference_minimax(&evaluator, position.board(), 3, position.token_type, phase, 0);
+            let mut search = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+            search.quiescence_depth = 0;
+            let result = iterative_deepening(&search, position.board(), position.token_type, phase, 3).unwrap();
+
+            assert_eq!(expected, result.score, "{}", position.name);
+        }
+    }
+
+    #[test]
+    fn test_quiescence_sees_pending_mill() {
+        let evaluator = MuehleEvaluator::default();
+        // black to move can close the mill 7 0 1 by moving from 6 to 7
+        let board = insert_number_of_possible_moves_to_board(insert_token_count_to_board(
+            decode_positions("BBEEWEBEEEWEEWEEEEWEEEBE".to_string())));
+        let phase = Phase::new(PhaseType::Move, 30);
+
+        let mut search = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+        search.quiescence_depth = 0;
+        let horizon_score = negamax(&search, board, 0, -isize::MAX, isize::MAX, 0b10, phase, 0).unwrap();
+        search.quiescence_depth = 2;
+        let quiescence_score = negamax(&search, board, 0, -isize::MAX, isize::MAX, 0b10, phase, 0).unwrap();
+
+        assert!(quiescence_score > horizon_score + 500, "{} <= {}", quiescence_score, horizon_score);
+    }
+
+    #[test]
+    fn test_prefers_fastest_win() {
+        let evaluator = MuehleEvaluator::default();
+        // white can win at once by flying into 7, every other move wins later
+        let board = insert_number_of_possible_moves_to_board(insert_token_count_to_board(
+            decode_positions("WWEEEEEEEEWEEBEEEEEBEEBE".to_string())));
+        let phase = Phase::new(PhaseType::Move, 18);
+
+        let search = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+        let result = iterative_deepening(&search, board, 0b11, phase, 4).unwrap();
+        assert_eq!(Score::Win(1), Score::from_raw(result.score));
+        assert_eq!(Some(score::WIN), terminal_score(result.forward_board, phase.increased()));
+    }
+
+    #[test]
+    fn test_deeper_entries_cut_off() {
+        let evaluator = MuehleEvaluator::default();
+        let position = &BENCHMARK_POSITIONS[3];
+        let phase = Phase::new(position.phase, position.step_counter);
+        let search = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+
+        let deep = negamax(&search, position.board(), 4, -isize::MAX, isize::MAX, position.token_type, phase, 0).unwrap();
+        let nodes = search.nodes();
+        // the exact entry of depth 4 answers the shallower search without looking at any move
+        let shallow = negamax(&search, position.board(), 2, -isize::MAX, isize::MAX, position.token_type, phase, 0).unwrap();
+        assert_eq!(deep, shallow);
+        assert_eq!(nodes + 1, search.nodes());
+    }
+
+    #[test]
+    fn test_helper_threads_keep_the_score() {
+        let evaluator = MuehleEvaluator::default();
+        for position in BENCHMARK_POSITIONS.iter().skip(1) {
+            let phase = Phase::new(position.phase, position.step_counter);
+            let mut single = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+            single.threads = 1;
+            let mut parallel = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+            parallel.threads = 3;
+
+            let expected = iterative_deepening(&single, position.board(), position.token_type, phase, 4).unwrap();
+            let result = iterative_deepening(&parallel, position.board(), position.token_type, phase, 4).unwrap();
+            assert_eq!(expected.score, result.score, "{}", position.name);
+            // the search can be used again afterwards
+            assert!(iterative_deepening(&parallel, position.board(), position.token_type, phase, 2).is_some());
         }
     }
-    
-    score += (white_token_count as isize - black_token_count as isize) * 1000;
-    score += white_move_count as isize - black_move_count as isize;
-    
-    score
 }
@@ -1,80 +1,365 @@
-use action::Action;
 use rayon::prelude::*;
-use good_web_game::timer;
 
-use crate::core::game::Game;
-use crate::core::enums::{State, Difficulty};
+use crate::core::game::{Game, Turn};
+use crate::core::enums::State;
 use crate::core::utils::{get_action_from_board, insert_number_of_possible_moves_to_board, insert_token_count_to_board};
-use crate::ai::action::forward_step_boards;
-use crate::ai::minimax::minimax;
+use crate::ai::action::{capture_boards, forward_step_boards, Action};
+use crate::ai::book::{BookSelection, OpeningBook};
+use crate::ai::endgame::EndgameDatabase;
+use crate::ai::engine::EngineMove;
+use crate::ai::evaluation::{Evaluator, MuehleEvaluator};
+use crate::ai::minimax::{default_threads, negamax, Search};
+use crate::ai::multipv::multi_pv;
+use crate::ai::ordering::MoveOrdering;
+use crate::ai::random::Rng;
+use crate::ai::score::Score;
+use crate::ai::skill::{blunder_scores, softmax_choice, Skill, MAX_SKILL_LEVEL};
 use crate::core::position::negate_token;
 
 pub mod action;
-mod minimax;
-
-pub fn compute_step(game: &Game, difficulty: Difficulty) -> Option<Action> {
-    let setup_pieces_left: u8 = game.get_setup_pieces_left();
-    
-    let phase = match game.get_state() {
-        State::Setup => Phase::new(PhaseType::Set, 18 - setup_pieces_left),
-        _ => Phase::new(PhaseType::Move, 20)
-    };
-    
-    let token_type = game.get_player_turn();
-
-    let mut board = game.get_board();
-    board = insert_token_count_to_board(board);
-    board = insert_number_of_possible_moves_to_board(board);
-
-
-    let now = timer::time();
-    
-    let mut depth = 0;
-    let maximum_depth = match difficulty {
-        Difficulty::Off => 1,
-        Difficulty::Easy => 1,
-        Difficulty::Medium => 6,
-        Difficulty::Hard => 50
+pub mod analysis;
+pub mod benchmark;
+pub mod book;
+pub mod endgame;
+pub mod engine;
+pub mod evaluation;
+pub mod external;
+pub mod mcts;
+pub mod minimax;
+pub mod multipv;
+pub mod ordering;
+pub mod protocol;
+pub mod random;
+pub mod score;
+pub mod skill;
+pub mod symmetry;
+pub mod timer;
+pub mod tournament;
+pub mod transposition;
+pub mod tuning;
+
+pub fn compute_step(game: &Game, skill: Skill) -> Option<EngineMove> {
+    compute_step_with_evaluator(game, skill, &MuehleEvaluator::default(), &mut Rng::from_time())
+}
+
+/// The position the computer has to move in, taken from any state of a game
+#[derive(Clone, Copy)]
+pub struct RootPosition {
+    /// With header
+    pub board: u64,
+    pub token_type: u8,
+    /// Phase of the move to play, with a pending capture the phase of the move which closed the mill
+    pub phase: Phase,
+    /// The mill is closed already and only a piece has to be taken
+    pub pending_capture: bool,
+}
+impl RootPosition {
+    pub fn new(board: u64, token_type: u8, phase: Phase) -> RootPosition {
+        RootPosition { board, token_type, phase, pending_capture: false }
+    }
+
+    pub fn of(game: &Game) -> RootPosition {
+        let turn = Turn {
+            board: game.get_board(),
+            player_turn: game.get_player_turn(),
+            setup_pieces_left: game.get_setup_pieces_left(),
+            step_counter: game.get_step_counter(),
+        };
+        RootPosition::at(&turn, game.get_state() == State::Take)
+    }
+
+    /// The position at the start of a turn from the history of a game
+    pub fn of_turn(turn: &Turn) -> RootPosition {
+        RootPosition::at(turn, false)
+    }
+
+    fn at(turn: &Turn, pending_capture: bool) -> RootPosition {
+        let setup_pieces_left = turn.setup_pieces_left;
+
+        // the step counter of a phase stops at 255, only the setup needs it to be exact
+        let phase = if setup_pieces_left == 0 {
+            Phase::new(PhaseType::Move, turn.step_counter.min(u8::MAX as usize) as u8)
+        } else if pending_capture {
+            // the piece which closed the mill is counted already
+            Phase::new(PhaseType::Set, 17 - setup_pieces_left)
+        } else {
+            Phase::new(PhaseType::Set, 18 - setup_pieces_left)
+        };
+
+        let mut board = turn.board;
+        board = insert_token_count_to_board(board);
+        board = insert_number_of_possible_moves_to_board(board);
+        RootPosition { board, token_type: turn.player_turn, phase, pending_capture }
+    }
+
+    /// Boards after every legal action of the side to move
+    pub fn forward_boards(&self) -> Vec<u64> {
+        if self.pending_capture {
+            capture_boards(self.board, self.token_type).collect()
+        } else {
+            forward_step_boards(&self.board, self.token_type, self.phase).collect()
+        }
+    }
+
+    /// Action which leads to `forward_board`, only the taken piece if the mill was closed already
+    pub fn action(&self, forward_board: u64) -> Action {
+        get_action_from_board(self.board, forward_board, self.token_type)
+    }
+
+    /// Action which leads to the position of `next`, none if no legal move does
+    pub fn action_to(&self, next: &Turn) -> Option<Action> {
+        let positions_mask: u64 = (1 << 48) - 1;
+        let forward_board = self.forward_boards().into_iter()
+            .find(|forward_board| forward_board & positions_mask == next.board & positions_mask)?;
+        Some(self.action(forward_board))
+    }
+}
+
+pub fn compute_step_with_evaluator(game: &Game, skill: Skill, evaluator: &dyn Evaluator, rng: &mut Rng) -> Option<EngineMove> {
+    let root = RootPosition::of(game);
+    let chosen = choose_move(&root, skill, evaluator, SearchLimits::default(), rng)?;
+    Some(EngineMove::of_chosen(&root, &chosen))
+}
+
+/// Seconds the computer thinks at full strength
+pub const MOVE_TIME: f64 = 0.980;
+
+/// What the search at full strength may use
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SearchLimits {
+    pub time_limit: f64,
+    pub threads: usize,
+}
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits { time_limit: MOVE_TIME, threads: default_threads() }
+    }
+}
+
+/// A move picked by [`choose_move`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChosenMove {
+    pub forward_board: u64,
+    /// For the side which plays the move, none for book moves
+    pub score: Option<Score>,
+    /// Depth of the search, the finished iterations at full strength, none for book moves and blunders
+    pub depth: Option<usize>,
+    /// Positions the search visited
+    pub nodes: u64,
+}
+
+/// The move the computer plays at `skill`, full strength searches within `limits`
+pub fn choose_move(root: &RootPosition, skill: Skill, evaluator: &dyn Evaluator, limits: SearchLimits, rng: &mut Rng) -> Option<ChosenMove> {
+    // loading the tables the first time does not count towards the time of the search
+    let endgame = EndgameDatabase::shared();
+
+    // the weaker levels always search as deep as their level
+    let time_limit = Some(limits.time_limit).filter(|_| skill.is_full_strength());
+    let mut search = Search::new(evaluator, MoveOrdering::Heuristic, time_limit);
+    search.endgame = endgame.filter(|_| skill.knows_endgame());
+    search.threads = limits.threads;
+    choose_searched_move(&search, root, skill, None, rng)
+}
+
+/// [`choose_move`] with the search of the caller, which may be stopped from another thread. `depth` caps the
+/// depth of the weaker levels and is the last iteration at full strength.
+pub fn choose_searched_move(search: &Search, root: &RootPosition, skill: Skill, depth: Option<usize>, rng: &mut Rng) -> Option<ChosenMove> {
+    let RootPosition { board, token_type, phase, .. } = *root;
+
+    let book_selection = match skill.level() {
+        _ if root.pending_capture => BookSelection::Off,
+        MAX_SKILL_LEVEL => BookSelection::Best,
+        7.. => BookSelection::WeightedRandom,
+        _ => BookSelection::Off
     };
-    
-    let mut best_action_total = None;
-    let mut best_score_total = if token_type == 0b11 { isize::MIN } else { isize::MAX };
-    let mut last_depth_time_elapsed: f64 = 0.0;
-    let mut _actions_with_scores: Vec<(u64, Option<isize>)> = Vec::with_capacity(500);
-
-    'outer_loop: loop {
-        if depth == maximum_depth {
-            break;
+    if let Some(forward_board) = OpeningBook::shared().choose(board, token_type, phase, book_selection, rng) {
+        return Some(ChosenMove { forward_board, score: None, depth: None, nodes: 0 });
+    }
+
+    let forward_boards = root.forward_boards();
+    if !skill.is_full_strength() {
+        // now and then the weaker levels overlook the reply
+        let (scored, depth) = if rng.next_f64() < skill.blunder_chance() {
+            (blunder_scores(&forward_boards, token_type, phase, search.evaluator), None)
+        } else {
+            let depth = depth.map_or(skill.depth(), |depth| depth.min(skill.depth()));
+            (score_root_moves(search, &forward_boards, token_type, phase, depth), Some(depth))
+        };
+        let forward_board = softmax_choice(&scored, skill.temperature(), rng)?;
+        let score = scored.iter().find(|(scored_board, _)| *scored_board == forward_board).map(|(_, score)| Score::from_raw(*score));
+        return Some(ChosenMove { forward_board, score, depth, nodes: search.nodes() });
+    }
+
+    let result = search_moves(search, &forward_boards, token_type, phase, depth.unwrap_or(50))?;
+    let color: isize = if token_type == 0b11 { 1 } else { -1 };
+    Some(ChosenMove { forward_board: result.forward_board, score: Some(Score::from_raw(color * result.score)), depth: Some(result.depth), nodes: search.nodes() })
+}
+
+/// Time a hint may take, it is computed while the player waits
+const HINT_TIME: f64 = 0.250;
+
+/// The best action for the side to move after a short search, to show it to a human player
+pub fn compute_hint(game: &Game) -> Option<Action> {
+    let root = RootPosition::of(game);
+    let endgame = EndgameDatabase::shared();
+
+    let evaluator = MuehleEvaluator::default();
+    let mut search = Search::new(&evaluator, MoveOrdering::Heuristic, Some(HINT_TIME));
+    search.endgame = endgame;
+
+    let line = multi_pv(&search, &root, 50, 1).into_iter().next()?;
+    Some(root.action(line.forward_board))
+}
+
+/// Half width of the first aspiration window around the score of the previous iteration
+const ASPIRATION_WINDOW: isize = 50;
+
+/// Outcome of the deepest finished iteration
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SearchResult {
+    pub forward_board: u64,
+    /// From the view of white
+    pub score: isize,
+    /// Number of finished iterations
+    pub depth: usize,
+}
+
+/// Searches with depth `0..maximum_depth` below the root, each iteration inside an aspiration window around
+/// the previous score. Returns the result of the last iteration which finished in time.
+///
+/// With more than one [`Search::threads`] this is a lazy SMP search: helper threads search the same position
+/// at staggered depths and in another move order, and only share the transposition and ordering tables.
+/// Whatever they store lets the main thread skip work. Its result is the one returned. The helpers are jobs
+/// of the global rayon pool, so at most as many threads as it has search at once.
+pub fn iterative_deepening(search: &Search, board: u64, token_type: u8, phase: Phase, maximum_depth: usize) -> Option<SearchResult> {
+    let forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+    search_moves(search, &forward_boards, token_type, phase, maximum_depth)
+}
+
+/// [`iterative_deepening`] over the given successors of the root, e.g. the boards of [`RootPosition::forward_boards`]
+pub fn search_moves(search: &Search, forward_boards: &[u64], token_type: u8, phase: Phase, maximum_depth: usize) -> Option<SearchResult> {
+    if search.threads <= 1 {
+        return deepen(search, forward_boards, token_type, phase, maximum_depth, 0)
+    }
+
+    let (result, was_stopped) = rayon::scope(|scope| {
+        for helper in 1..search.threads {
+            scope.spawn(move |_| {
+                deepen(search, forward_boards, token_type, phase, maximum_depth, helper);
+            });
         }
+        let result = deepen(search, forward_boards, token_type, phase, maximum_depth, 0);
+        // the helpers are of no use anymore once the main thread is done
+        (result, search.stop())
+    });
+    if !was_stopped {
+        search.resume();
+    }
+    result
+}
 
-        let mut best_action = None;
-        let mut best_score = if token_type == 0b11 { isize::MIN } else { isize::MAX };
-        _actions_with_scores = forward_step_boards(&board, token_type, phase).par_bridge().map(|forward_board| {
-            (forward_board, minimax(forward_board, depth, isize::MIN, isize::MAX, negate_token(token_type), phase.increased(), now))
-        }).collect();
+// iterative deepening of a single thread, `helper` 0 is the main thread
+fn deepen(search: &Search, forward_boards: &[u64], token_type: u8, phase: Phase, maximum_depth: usize, helper: usize) -> Option<SearchResult> {
+    let color: isize = if token_type == 0b11 { 1 } else { -1 };
+    let mut forward_boards = forward_boards.to_vec();
+    if forward_boards.is_empty() {
+        return None
+    }
+    let length = forward_boards.len();
+    forward_boards.rotate_left(helper % length);
 
-        for action_with_score in _actions_with_scores.into_iter() {
-            if action_with_score.1.is_none() {
-                break 'outer_loop;
-            }
+    let mut result: Option<SearchResult> = None;
+    let mut previous_score: Option<isize> = None;
+    for depth in (helper % 2)..maximum_depth {
+        let mut window = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous_score {
+            Some(score) if !score::is_decisive(score) => (score - window, score + window),
+            _ => (-isize::MAX, isize::MAX)
+        };
+
+        let (best_index, score) = loop {
+            let (best_index, score) = match search_root(search, &forward_boards, token_type, phase, depth, alpha, beta) {
+                Some(root_result) => root_result,
+                None => return result
+            };
 
-            if token_type == 0b11 && action_with_score.1.unwrap() >= best_score {
-                best_action = Some(get_action_from_board(board, action_with_score.0, token_type));
-                best_score = action_with_score.1.unwrap();
-            } else if token_type == 0b10 && action_with_score.1.unwrap() <= best_score {
-                best_action = Some(get_action_from_board(board, action_with_score.0, token_type));
-                best_score = action_with_score.1.unwrap();
+            if score <= alpha && alpha > -isize::MAX {
+                window = window.saturating_mul(4);
+                alpha = score.saturating_sub(window).max(-isize::MAX);
+            } else if score >= beta && beta < isize::MAX {
+                window = window.saturating_mul(4);
+                beta = score.saturating_add(window);
+            } else {
+                break (best_index, score)
             }
+        };
+
+        // the best move is searched first in the next iteration
+        let best_board = forward_boards.remove(best_index);
+        forward_boards.insert(0, best_board);
+        previous_score = Some(score);
+        result = Some(SearchResult { forward_board: best_board, score: color * score, depth: depth + 1 });
+    }
+    result
+}
+
+/// Principal variation search over the successors of the root, `depth` plies below each of them. The first
+/// successor is searched with the window, all others with a zero window and again if they fail high.
+/// Returns the index of the best successor and its fail soft score from the view of `token_type`,
+/// `None` if the search ran out of time.
+pub fn search_root(search: &Search, forward_boards: &[u64], token_type: u8, phase: Phase, depth: usize, mut alpha: isize, beta: isize) -> Option<(usize, isize)> {
+    let child_score = |forward_board: u64, alpha: isize, beta: isize| {
+        negamax(search, forward_board, depth, -beta, -alpha, negate_token(token_type), phase.increased(), 1).map(|score| -score)
+    };
+
+    let mut best_index = 0;
+    let mut best_score = child_score(*forward_boards.first()?, alpha, beta)?;
+    alpha = alpha.max(best_score);
+
+    for (index, forward_board) in forward_boards.iter().enumerate().skip(1) {
+        if alpha >= beta {
+            break
+        }
+
+        let mut score = child_score(*forward_board, alpha, alpha + 1)?;
+        if score > alpha && score < beta {
+            score = child_score(*forward_board, alpha, beta)?;
         }
 
-        best_action_total = best_action;
-        best_score_total = best_score;
-        last_depth_time_elapsed = timer::time() - now;
-        depth += 1;
+        if score > best_score {
+            best_index = index;
+            best_score = score;
+        }
+        alpha = alpha.max(score);
     }
+    Some((best_index, best_score))
+}
+
+/// Every successor with its exact score for `token_type` from a full window search `depth` plies below it.
+/// A stopped search keeps the scores of its last finished iteration, none if it stopped during the first.
+pub fn score_root_moves(search: &Search, forward_boards: &[u64], token_type: u8, phase: Phase, depth: usize) -> Vec<(u64, isize)> {
+    let mut scored = Vec::new();
+    // the shallower iterations fill the tables for the move ordering
+    for iteration_depth in 0..=depth {
+        let iteration: Option<Vec<(u64, isize)>> = forward_boards.par_iter()
+            .map(|forward_board| {
+                let score = negamax(search, *forward_board, iteration_depth, -isize::MAX, isize::MAX, negate_token(token_type), phase.increased(), 1)?;
+                Some((*forward_board, -score))
+            })
+            .collect();
+        match iteration {
+            Some(iteration) => scored = iteration,
+            None => break
+        }
+    }
+    scored
+}
 
-    println!("-> Execution time {:.3?} \n-> best score {} \n-> depth: {}\n", last_depth_time_elapsed, best_score_total, depth);
-    best_action_total
+/// Searches all successors `depth` plies deep without a time limit and returns the best one with its score
+pub fn search_fixed_depth(board: u64, token_type: u8, phase: Phase, depth: usize, evaluator: &dyn Evaluator) -> Option<(u64, isize)> {
+    let search = Search::new(evaluator, MoveOrdering::Heuristic, None);
+    iterative_deepening(&search, board, token_type, phase, depth + 1)
+        .map(|result| (result.forward_board, result.score))
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -101,7 +386,7 @@ impl Phase {
             } else {
                 self.phase
             },
-            self.step_counter + 1
+            self.step_counter.saturating_add(1)
         )
     }
 }
\ No newline at end of file
@@ -0,0 +1,192 @@
+use rayon::prelude::*;
+
+use crate::ai::action::{forward_step_boards, Action};
+use crate::ai::evaluation::terminal_score;
+use crate::ai::minimax::{negamax, Search};
+use crate::ai::transposition::{move_of, TranspositionTable};
+use crate::ai::{Phase, RootPosition};
+use crate::core::position::negate_token;
+use crate::core::utils::get_action_from_board;
+
+/// One of the best root moves
+#[derive(Clone, PartialEq, Debug)]
+pub struct PvLine {
+    pub forward_board: u64,
+    /// From the view of white
+    pub score: isize,
+    /// Boards after each move of the principal variation, starting with `forward_board`
+    pub principal_variation: Vec<u64>,
+}
+impl PvLine {
+    /// The moves of the principal variation, `token_type` plays the first one
+    pub fn actions(&self, board: u64, token_type: u8) -> Vec<Action> {
+        let mut before = board;
+        let mut token_type = token_type;
+        self.principal_variation.iter()
+            .map(|after| {
+                let action = get_action_from_board(before, *after, token_type);
+                before = *after;
+                token_type = negate_token(token_type);
+                action
+            })
+            .collect()
+    }
+}
+
+/// Iterative deepening which keeps the `count` best root moves apart. Returns them best first
+/// with the scores and principal variations of the last iteration which finished in time.
+pub fn multi_pv(search: &Search, root: &RootPosition, maximum_depth: usize, count: usize) -> Vec<PvLine> {
+    multi_pv_with_progress(search, root, maximum_depth, count, |_, _| {})
+}
+
+/// [`multi_pv`] which calls `progress` with the depth and the lines after every finished iteration
+pub fn multi_pv_with_progress(search: &Search, root: &RootPosition, maximum_depth: usize, count: usize, mut progress: impl FnMut(usize, &[PvLine])) -> Vec<PvLine> {
+    let RootPosition { token_type, phase, .. } = *root;
+    let color: isize = if token_type == 0b11 { 1 } else { -1 };
+    let mut forward_boards = root.forward_boards();
+    let count = count.min(forward_boards.len());
+
+    let mut lines = Vec::new();
+    for depth in 0..maximum_depth {
+        let scored = match search_multi_pv_root(search, &forward_boards, token_type, phase, depth, count) {
+            Some(scored) => scored,
+            None => break
+        };
+
+        forward_boards = scored.iter().map(|(forward_board, _)| *forward_board).collect();
+        lines = scored.into_iter()
+            .take(count)
+            .map(|(forward_board, score)| PvLine {
+                forward_board,
+                score: color * score,
+                principal_variation: principal_variation(search, forward_board, negate_token(token_type), phase.increased(), depth),
+            })
+            .collect();
+        progress(depth + 1, &lines);
+    }
+    lines
+}
+
+/// Scores the root moves so that the first `count` are exact and the best, sorted by score for `token_type`.
+/// The `count` first moves get a full window, every later one a zero window at the worst of the best
+/// moves so far and a full window again if it gets into them.
+fn search_multi_pv_root(search: &Search, forward_boards: &[u64], token_type: u8, phase: Phase, depth: usize, count: usize) -> Option<Vec<(u64, isize)>> {
+    let child_score = |forward_board: u64, alpha: isize, beta: isize| {
+        negamax(search, forward_board, depth, -beta, -alpha, negate_token(token_type), phase.increased(), 1).map(|score| -score)
+    };
+
+    let mut best: Vec<(u64, isize)> = forward_boards[..count].par_iter()
+        .map(|forward_board| child_score(*forward_board, -isize::MAX, isize::MAX).map(|score| (*forward_board, score)))
+        .collect::<Option<_>>()?;
+    best.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    let threshold = best.last().map_or(-isize::MAX, |(_, score)| *score);
+    let scout_scores: Vec<Option<isize>> = forward_boards[count..].par_iter()
+        .map(|forward_board| child_score(*forward_board, threshold, threshold + 1))
+        .collect();
+
+    let mut rest = Vec::new();
+    for (forward_board, scout_score) in forward_boards[count..].iter().zip(scout_scores) {
+        let score = scout_score?;
+        if score <= threshold {
+            rest.push((*forward_board, score));
+            continue
+        }
+
+        // failing high only gives a lower bound
+        let score = child_score(*forward_board, -isize::MAX, isize::MAX)?;
+        if score <= best.last().map_or(-isize::MAX, |(_, score)| *score) {
+            rest.push((*forward_board, score));
+            continue
+        }
+        let index = best.partition_point(|(_, other)| *other >= score);
+        best.insert(index, (*forward_board, score));
+        if best.len() > count {
+            rest.push(best.pop().unwrap());
+        }
+    }
+
+    rest.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    best.extend(rest);
+    Some(best)
+}
+
+/// Follows the best moves stored in the transposition table, at most `depth` moves after `forward_board`
+pub fn principal_variation(search: &Search, forward_board: u64, token_type: u8, phase: Phase, depth: usize) -> Vec<u64> {
+    let mut variation = vec![forward_board];
+    let (mut board, mut token_type, mut phase) = (forward_board, token_type, phase);
+
+    while variation.len() <= depth && terminal_score(board, phase).is_none() {
+        let best_move = match search.transposition_table.probe(TranspositionTable::key(board, token_type, phase)) {
+            Some(entry) => entry.best_move,
+            None => break
+        };
+        let next = forward_step_boards(&board, token_type, phase).find(|next| move_of(board, *next) == best_move);
+        board = match next {
+            Some(next) => next,
+            None => break
+        };
+
+        variation.push(board);
+        token_type = negate_token(token_type);
+        phase = phase.increased();
+    }
+    variation
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::action::forward_step_boards;
+    use crate::ai::benchmark::BENCHMARK_POSITIONS;
+    use crate::ai::evaluation::MuehleEvaluator;
+    use crate::ai::minimax::Search;
+    use crate::ai::multipv::multi_pv;
+    use crate::ai::ordering::MoveOrdering;
+    use crate::ai::{compute_hint, score_root_moves, Phase, RootPosition};
+    use crate::core::enums::State;
+    use crate::core::game::Game;
+    use crate::core::position::{decode_positions, negate_token};
+
+    #[test]
+    fn test_best_moves_with_exact_scores() {
+        let evaluator = MuehleEvaluator::default();
+        for position in BENCHMARK_POSITIONS.iter().skip(1) {
+            let (board, token_type) = (position.board(), position.token_type);
+            let phase = Phase::new(position.phase, position.step_counter);
+            let color: isize = if token_type == 0b11 { 1 } else { -1 };
+
+            let search = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+            let lines = multi_pv(&search, &RootPosition::new(board, token_type, phase), 3, 3);
+
+            let forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+            let mut expected: Vec<isize> = score_root_moves(&Search::new(&evaluator, MoveOrdering::Heuristic, None), &forward_boards, token_type, phase, 2)
+                .into_iter()
+                .map(|(_, score)| color * score)
+                .collect();
+            expected.sort_by_key(|score| std::cmp::Reverse(color * score));
+            let scores: Vec<isize> = lines.iter().map(|line| line.score).collect();
+            assert_eq!(expected[..3], scores[..], "{}", position.name);
+
+            for line in lines.iter() {
+                assert_eq!(line.forward_board, line.principal_variation[0]);
+                let (mut before, mut token_type, mut phase) = (board, token_type, phase);
+                for after in line.principal_variation.iter() {
+                    assert!(forward_step_boards(&before, token_type, phase).any(|next| next == *after), "{}", position.name);
+                    (before, token_type, phase) = (*after, negate_token(token_type), phase.increased());
+                }
+                assert_eq!(line.principal_variation.len(), line.actions(board, position.token_type).len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_hint_closes_mill() {
+        // closing the mill 7 0 1 is the only way to stop black from closing 23 16 17
+        let board = decode_positions("WWBEEEEEEWEEEEEEBBEEEEEE".to_string());
+        let game = Game::from_state(board, 0b11, State::Setup, 12, 6).unwrap();
+
+        let hint = compute_hint(&game).unwrap();
+        assert_eq!((None, 7), (hint.start_position, hint.end_position));
+        assert!([Some(16), Some(17)].contains(&hint.beatable_position), "{:?}", hint.beatable_position);
+    }
+}
@@ -0,0 +1,220 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::ai::evaluation::{mill_gaps, piece_masks, threat_squares, Evaluator, NEIGHBOR_MASKS};
+use crate::ai::transposition::move_of;
+use crate::ai::{Phase, PhaseType};
+use crate::core::position::{get_token_at, negate_token};
+
+pub const MAX_PLY: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveOrdering {
+    /// Generation order
+    None,
+    /// Sorts every successor by its static evaluation
+    StaticEvaluation,
+    /// Hash move, captures, blocks and threats, then the killer moves (in the move phase), the history table and the mobility of the square
+    Heuristic,
+    /// Like the heuristic ordering without the killer moves, to measure what they prune
+    History
+}
+
+/// Killer moves per ply and the history table, shared by all threads of a search
+pub struct OrderingTables {
+    killers: Vec<[AtomicU64; 2]>,
+    // indexed by start * 25 + end, start 24 is a placement and end 24 a capture only
+    history: Vec<AtomicU32>,
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderingTables {
+    pub fn new() -> Self {
+        OrderingTables {
+            killers: (0..MAX_PLY).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+            history: (0..25 * 25).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    /// Remembers a quiet move which caused a beta cutoff
+    pub fn record_cutoff(&self, board: u64, forward_board: u64, token_type: u8, depth: usize, ply: usize) {
+        let (start, end, capture) = move_squares(board, forward_board, token_type);
+        if capture {
+            return
+        }
+
+        if ply < MAX_PLY {
+            let killer = move_of(board, forward_board);
+            let killers = &self.killers[ply];
+            if killers[0].load(Ordering::Relaxed) != killer {
+                killers[1].store(killers[0].load(Ordering::Relaxed), Ordering::Relaxed);
+                killers[0].store(killer, Ordering::Relaxed);
+            }
+        }
+
+        let bonus = (depth * depth) as u32;
+        let entry = &self.history[start * 25 + end];
+        // halve everything before the counters overflow
+        if entry.fetch_add(bonus, Ordering::Relaxed) > u32::MAX / 2 {
+            for value in self.history.iter() {
+                value.store(value.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn killers(&self, ply: usize) -> [u64; 2] {
+        if ply >= MAX_PLY {
+            return [0, 0]
+        }
+        [self.killers[ply][0].load(Ordering::Relaxed), self.killers[ply][1].load(Ordering::Relaxed)]
+    }
+
+    fn history(&self, start: usize, end: usize) -> u32 {
+        self.history[start * 25 + end].load(Ordering::Relaxed)
+    }
+}
+
+/// Sorts the successors, the most promising first. Returns how many static evaluations were needed.
+#[allow(clippy::too_many_arguments)]
+pub fn order_moves(ordering: MoveOrdering, tables: &OrderingTables, evaluator: &dyn Evaluator, board: u64, forward_boards: &mut [u64], token_type: u8, phase: Phase, ply: usize, hash_move: Option<u64>) -> u64 {
+    let mut evaluations = 0;
+    match ordering {
+        MoveOrdering::None => (),
+        MoveOrdering::StaticEvaluation => {
+            forward_boards.sort_by(|board1, board2| {
+                let board1_eval = evaluator.evaluate(*board1, phase);
+                let board2_eval = evaluator.evaluate(*board2, phase);
+                evaluations += 2;
+                if token_type == 0b11 {
+                    board2_eval.cmp(&board1_eval)
+                } else {
+                    board1_eval.cmp(&board2_eval)
+                }
+            });
+        },
+        MoveOrdering::Heuristic | MoveOrdering::History => {
+            let (white, black) = piece_masks(board);
+            let (own, opponent) = if token_type == 0b11 { (white, black) } else { (black, white) };
+            // a placement or a move against a flying opponent rarely refutes the sibling positions as well
+            let use_killers = ordering == MoveOrdering::Heuristic && phase.phase == PhaseType::Move && opponent.count_ones() > 3;
+            let killers = if use_killers { tables.killers(ply) } else { [0, 0] };
+            let empty = !(white | black) & 0xFFFFFF;
+            let opponent_gaps = mill_gaps(opponent, empty);
+            let own_threats = threat_squares(own, empty);
+
+            forward_boards.sort_by_cached_key(|forward_board| {
+                let current_move = move_of(board, *forward_board);
+                let (start, end, capture) = move_squares(board, *forward_board, token_type);
+
+                let class = if Some(current_move) == hash_move {
+                    4
+                } else if capture {
+                    3
+                } else if opponent_gaps >> end & 1 != 0 {
+                    2
+                } else if own_threats >> end & 1 != 0 {
+                    1
+                } else {
+                    0
+                };
+                let killer = if current_move == killers[0] {
+                    2
+                } else if current_move == killers[1] {
+                    1
+                } else {
+                    0
+                };
+                let history = if capture { 0 } else { tables.history(start, end) };
+                let mobility = NEIGHBOR_MASKS.get(end).map_or(0, |neighbors| neighbors.count_ones());
+                Reverse((class, killer, history, mobility))
+            });
+        }
+    }
+    evaluations
+}
+
+/// Start (24 for a placement) and end (24 if only a piece was taken) of a move and if a piece was taken
+pub fn move_squares(board: u64, forward_board: u64, token_type: u8) -> (usize, usize, bool) {
+    let mut difference = move_of(board, forward_board);
+    let (mut start, mut end, mut capture) = (24, 24, false);
+
+    while difference != 0 {
+        let position = 23 - difference.trailing_zeros() as usize / 2;
+        difference &= !(0b11 << (46 - position * 2));
+
+        let token = get_token_at(board, position);
+        if token == 0b00 {
+            end = position;
+        } else if token == token_type {
+            start = position;
+        } else if token == negate_token(token_type) {
+            capture = true;
+        }
+    }
+    (start, end, capture)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::evaluation::MaterialEvaluator;
+    use crate::ai::ordering::{move_squares, order_moves, MoveOrdering, OrderingTables};
+    use crate::ai::transposition::move_of;
+    use crate::ai::{Phase, PhaseType};
+    use crate::core::position::decode_positions;
+
+    #[test]
+    fn test_move_squares() {
+        let board = decode_positions("WWEEEEEEEEEEEEEEEEEEEEBB".to_string());
+        let moved = decode_positions("WEWEEEEEEEEEEEEEEEEEEEBB".to_string());
+        let placed_and_taken = decode_positions("WWEEEEEEEEEEEEEEEEEEEWEB".to_string());
+        let taken = decode_positions("WWEEEEEEEEEEEEEEEEEEEEEB".to_string());
+
+        assert_eq!((1, 2, false), move_squares(board, moved, 0b11));
+        assert_eq!((24, 21, true), move_squares(board, placed_and_taken, 0b11));
+        assert_eq!((24, 24, true), move_squares(board, taken, 0b11));
+    }
+
+    #[test]
+    fn test_heuristic_order() {
+        let tables = OrderingTables::new();
+        let board = decode_positions("WWEEEEEEEEEEEEEEEEEEEEBB".to_string());
+        let quiet = decode_positions("WWEEEEEEEEEEEEEEEEWEEEBB".to_string());
+        let killer = decode_positions("WWEEEEEEEEEEEEEEEEEWEEBB".to_string());
+        let capture = decode_positions("WWEEEEEEEEEEEEEEEEEEEWEB".to_string());
+        let hash = decode_positions("WWEEEEEEEEEEEEEEEEEEEEBB".to_string()) | 0b11 << 40;
+
+        tables.record_cutoff(board, killer, 0b11, 2, 3);
+        let mut forward_boards = [quiet, killer, capture, hash];
+        order_moves(MoveOrdering::Heuristic, &tables, &MaterialEvaluator, board, &mut forward_boards, 0b11, Phase::new(PhaseType::Set, 4), 3, Some(move_of(board, hash)));
+
+        assert_eq!([hash, capture, killer, quiet], forward_boards);
+    }
+
+    #[test]
+    fn test_killers_ahead_of_history() {
+        let tables = OrderingTables::new();
+        let board = decode_positions("WWEEEEEEEEEEEEEEEEEEBBBB".to_string());
+        let history = decode_positions("WEEEEEEEEWEEEEEEEEEEBBBB".to_string());
+        let killer = decode_positions("EWEEEEEWEEEEEEEEEEEEBBBB".to_string());
+        let phase = Phase::new(PhaseType::Move, 30);
+
+        tables.record_cutoff(board, history, 0b11, 5, 7);
+        tables.record_cutoff(board, killer, 0b11, 2, 3);
+        let mut forward_boards = [history, killer];
+        order_moves(MoveOrdering::Heuristic, &tables, &MaterialEvaluator, board, &mut forward_boards, 0b11, phase, 3, None);
+        assert_eq!([killer, history], forward_boards);
+
+        // against a flying opponent only the history counts
+        let board = decode_positions("WWEEEEEEEEEEEEEEEEEEEBBB".to_string());
+        let history = decode_positions("WEEEEEEEEWEEEEEEEEEEEBBB".to_string());
+        let killer = decode_positions("EWEEEEEWEEEEEEEEEEEEEBBB".to_string());
+        let mut forward_boards = [killer, history];
+        order_moves(MoveOrdering::Heuristic, &tables, &MaterialEvaluator, board, &mut forward_boards, 0b11, phase, 3, None);
+        assert_eq!([history, killer], forward_boards);
+    }
+}
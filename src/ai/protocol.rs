@@ -0,0 +1,384 @@
+//! Text protocol of the engine binary, modelled on UCI. Every command is one line on stdin and every
+//! answer one line on stdout:
+//!
+//! - `mei`: the engine answers with `id name ...`, `id author ...`, one `option ...` line per option and `meiok`
+//! - `isready`: answered with `readyok`, also while searching
+//! - `setoption name <name> value <value>`
+//! - `newgame`: goes back to the start position
+//! - `position startpos [moves <move>...]` or `position board <24 x W|B|E> <w|b> <plies played> [moves <move>...]`
+//! - `go [depth <n>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [infinite]`: the engine
+//!   prints `info depth <n> multipv <i> score <cp x|win n|loss n> nodes <n> time <ms> pv <move>...` after every
+//!   iteration and finally `bestmove <move>`, or `bestmove none` if the game is over. `depth` also caps the
+//!   search of the lower levels, the mcts engine rejects it. After `infinite` the `bestmove` waits for `stop`.
+//! - `stop`: ends the search, which still prints its `bestmove`
+//! - `quit`
+//!
+//! Moves are written like [`Action`](crate::ai::action::Action): `7` places, `3-4` moves and `3-4x10` also
+//! takes the piece on 10. The positions are numbered as in `core::position`.
+
+use std::thread;
+use std::time::Duration;
+
+use crate::ai::action::Action;
+use crate::ai::endgame::EndgameDatabase;
+use crate::ai::engine::{EngineKind, MctsEngine};
+use crate::ai::evaluation::{terminal_score, Evaluator};
+use crate::ai::mcts::mcts_until;
+use crate::ai::minimax::{default_threads, Search};
+use crate::ai::multipv::{multi_pv_with_progress, PvLine};
+use crate::ai::ordering::MoveOrdering;
+use crate::ai::random::Rng;
+use crate::ai::score::Score;
+use crate::ai::skill::{Skill, MAX_SKILL_LEVEL, MIN_SKILL_LEVEL};
+use crate::ai::timer;
+use crate::ai::{choose_searched_move, RootPosition, MOVE_TIME};
+use crate::core::game::Turn;
+use crate::core::position::{decode_positions, negate_token};
+
+pub const ENGINE_NAME: &str = "Muehle Agent";
+pub const ENGINE_AUTHOR: &str = "Purpurax";
+
+/// Deepest iteration of a search without depth limit
+const MAX_DEPTH: usize = 50;
+
+#[derive(Debug)]
+pub struct ProtocolError {
+    pub message: String,
+}
+impl ProtocolError {
+    pub fn new(message: String) -> ProtocolError {
+        ProtocolError { message }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct GoLimits {
+    pub depth: Option<usize>,
+    /// All times in milliseconds
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub infinite: bool,
+}
+impl GoLimits {
+    /// Seconds `token_type` may think, none if only `stop` or the depth ends the search
+    pub fn time_limit(&self, token_type: u8) -> Option<f64> {
+        if self.infinite {
+            return None
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(movetime as f64 / 1000.0)
+        }
+
+        let (time, increment) = if token_type == 0b11 { (self.wtime, self.winc) } else { (self.btime, self.binc) };
+        let time = time? as f64;
+        let increment = increment.unwrap_or(0) as f64;
+        // a game lasts about 30 more moves, but never more than half of the clock goes into one
+        Some((time / 30.0 + increment * 0.8).min(time / 2.0) / 1000.0)
+    }
+}
+
+pub enum Command {
+    Mei,
+    IsReady,
+    NewGame,
+    SetOption { name: String, value: String },
+    Position(RootPosition),
+    Go(GoLimits),
+    Stop,
+    Quit,
+}
+
+pub fn parse_command(line: &str) -> Result<Command, ProtocolError> {
+    let mut words = line.split_whitespace();
+    let command = words.next().ok_or(ProtocolError::new("Empty command".to_string()))?;
+    let arguments: Vec<&str> = words.collect();
+
+    match command {
+        "mei" => Ok(Command::Mei),
+        "isready" => Ok(Command::IsReady),
+        "newgame" => Ok(Command::NewGame),
+        "setoption" => parse_setoption(&arguments),
+        "position" => parse_position(&arguments).map(Command::Position),
+        "go" => parse_go(&arguments).map(Command::Go),
+        "stop" => Ok(Command::Stop),
+        "quit" => Ok(Command::Quit),
+        _ => Err(ProtocolError::new(format!("Unknown command \"{}\"", command)))
+    }
+}
+
+fn parse_setoption(arguments: &[&str]) -> Result<Command, ProtocolError> {
+    let value_index = arguments.iter().position(|word| *word == "value");
+    match (arguments.first(), value_index) {
+        (Some(&"name"), Some(value_index)) if value_index > 1 => Ok(Command::SetOption {
+            name: arguments[1..value_index].join(" "),
+            value: arguments[value_index + 1..].join(" "),
+        }),
+        _ => Err(ProtocolError::new("Expected setoption name <name> value <value>".to_string()))
+    }
+}
+
+pub fn parse_position(arguments: &[&str]) -> Result<RootPosition, ProtocolError> {
+    let (turn, rest) = match arguments.first() {
+        Some(&"startpos") => (Turn { board: 0, player_turn: 0b11, setup_pieces_left: 18, step_counter: 0 }, &arguments[1..]),
+        Some(&"board") if arguments.len() >= 4 => {
+            let positions = arguments[1];
+            if positions.len() != 24 || !positions.chars().all(|char| "WBE".contains(char)) {
+                return Err(ProtocolError::new(format!("Invalid board \"{}\", expected 24 of W, B and E", positions)))
+            }
+            let player_turn = match arguments[2] {
+                "w" => 0b11,
+                "b" => 0b10,
+                other => return Err(ProtocolError::new(format!("Invalid side to move \"{}\"", other)))
+            };
+            let plies: usize = arguments[3].parse()
+                .map_err(|_| ProtocolError::new(format!("Invalid number of plies \"{}\"", arguments[3])))?;
+            let turn = Turn {
+                board: decode_positions(positions.to_string()),
+                player_turn,
+                setup_pieces_left: 18usize.saturating_sub(plies) as u8,
+                step_counter: plies,
+            };
+            (turn, &arguments[4..])
+        },
+        _ => return Err(ProtocolError::new("Expected position startpos or position board <board> <w|b> <plies>".to_string()))
+    };
+
+    let mut root = RootPosition::of_turn(&turn);
+    match rest.split_first() {
+        None => {},
+        Some((&"moves", moves)) => for text in moves {
+            root = apply_move(&root, text)?;
+        },
+        Some((other, _)) => return Err(ProtocolError::new(format!("Expected moves, not \"{}\"", other)))
+    }
+    Ok(root)
+}
+
+/// The position after the move `text`, which has to be legal
+pub fn apply_move(root: &RootPosition, text: &str) -> Result<RootPosition, ProtocolError> {
+    let action: Action = text.parse().map_err(|err: crate::core::enums::FieldError| ProtocolError::new(err.message))?;
+    let forward_board = root.forward_boards().into_iter()
+        .find(|forward_board| root.action(*forward_board) == action)
+        .ok_or(ProtocolError::new(format!("Illegal move \"{}\"", text)))?;
+    Ok(RootPosition::new(forward_board, negate_token(root.token_type), root.phase.increased()))
+}
+
+fn parse_go(arguments: &[&str]) -> Result<GoLimits, ProtocolError> {
+    let mut limits = GoLimits::default();
+    let mut words = arguments.iter();
+    while let Some(word) = words.next() {
+        if *word == "infinite" {
+            limits.infinite = true;
+            continue
+        }
+        let value = words.next()
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or(ProtocolError::new(format!("Missing or invalid value for {}", word)))?;
+        match *word {
+            "depth" => limits.depth = Some(value as usize),
+            "movetime" => limits.movetime = Some(value),
+            "wtime" => limits.wtime = Some(value),
+            "btime" => limits.btime = Some(value),
+            "winc" => limits.winc = Some(value),
+            "binc" => limits.binc = Some(value),
+            _ => return Err(ProtocolError::new(format!("Unknown limit \"{}\"", word)))
+        }
+    }
+    Ok(limits)
+}
+
+/// Settings changed by `setoption`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EngineOptions {
+    pub engine: EngineKind,
+    pub skill: Skill,
+    /// Best moves reported in the `info` lines
+    pub multi_pv: usize,
+}
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions { engine: EngineKind::Minimax, skill: Skill::new(MAX_SKILL_LEVEL), multi_pv: 1 }
+    }
+}
+impl EngineOptions {
+    /// The `option` lines of the handshake
+    pub fn describe() -> Vec<String> {
+        vec![
+            "option name Engine type combo default minimax var minimax var mcts".to_string(),
+            format!("option name Level type spin default {} min {} max {}", MAX_SKILL_LEVEL, MIN_SKILL_LEVEL, MAX_SKILL_LEVEL),
+            "option name MultiPV type spin default 1 min 1 max 24".to_string(),
+        ]
+    }
+
+    /// Rejects the limits of a `go` which the engine cannot keep to
+    pub fn check(&self, limits: &GoLimits) -> Result<(), ProtocolError> {
+        if self.engine == EngineKind::Mcts && limits.depth.is_some() {
+            return Err(ProtocolError::new("The mcts engine searches no depth, use movetime, the clock or infinite".to_string()))
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ProtocolError> {
+        let invalid = || ProtocolError::new(format!("Invalid value \"{}\" for option {}", value, name));
+        match name.to_lowercase().as_str() {
+            "engine" => self.engine = match value {
+                "minimax" => EngineKind::Minimax,
+                "mcts" => EngineKind::Mcts,
+                _ => return Err(invalid())
+            },
+            "level" => self.skill = Skill::new(value.parse().map_err(|_| invalid())?),
+            "multipv" => self.multi_pv = value.parse::<usize>().ok().filter(|count| (1..=24).contains(count)).ok_or_else(invalid)?,
+            _ => return Err(ProtocolError::new(format!("Unknown option \"{}\"", name)))
+        }
+        Ok(())
+    }
+}
+
+/// The search for a `go`, `stop` on another thread ends it early
+pub fn new_search<'a>(evaluator: &'a dyn Evaluator, root: &RootPosition, options: &EngineOptions, limits: &GoLimits) -> Search<'a> {
+    let mut search = Search::new(evaluator, MoveOrdering::Heuristic, limits.time_limit(root.token_type));
+    search.endgame = EndgameDatabase::shared().filter(|_| options.skill.knows_endgame());
+    search.threads = default_threads();
+    search
+}
+
+/// Searches `root` and returns the `bestmove` line, `emit` gets the `info` lines as they come.
+/// After `go infinite` the line is only returned once `search` is stopped.
+pub fn go(search: &Search, root: &RootPosition, options: &EngineOptions, limits: &GoLimits, emit: &dyn Fn(String)) -> String {
+    let start_time = timer::time();
+    if terminal_score(root.board, root.phase).is_some() || root.forward_boards().is_empty() {
+        return "bestmove none".to_string()
+    }
+    let time_limit = limits.time_limit(root.token_type);
+
+    let forward_board = match options.engine {
+        EngineKind::Mcts => {
+            let mut mcts_options = MctsEngine::options(options.skill);
+            mcts_options.time_limit = time_limit.or(Some(MOVE_TIME).filter(|_| !limits.infinite));
+            let result = mcts_until(root, &mcts_options, search.evaluator, || search.is_stopped());
+            if let Some(result) = result {
+                emit(format!("info playouts {} winrate {:.3} time {}", result.playouts, result.win_rate, elapsed_ms(start_time)));
+            }
+            result.map(|result| result.forward_board)
+        },
+        EngineKind::Minimax if !options.skill.is_full_strength() => {
+            let chosen = choose_searched_move(search, root, options.skill, limits.depth, &mut Rng::from_time());
+            if let Some(score) = chosen.and_then(|chosen| chosen.score) {
+                emit(format!("info score {} nodes {} time {}", protocol_score(score), search.nodes(), elapsed_ms(start_time)));
+            }
+            chosen.map(|chosen| chosen.forward_board)
+        },
+        EngineKind::Minimax => {
+            let color: isize = if root.token_type == 0b11 { 1 } else { -1 };
+            let lines = multi_pv_with_progress(search, root, limits.depth.unwrap_or(MAX_DEPTH), options.multi_pv, |depth, lines: &[PvLine]| {
+                for (index, line) in lines.iter().enumerate() {
+                    let moves: Vec<String> = line.actions(root.board, root.token_type).iter().map(|action| action.to_string()).collect();
+                    emit(format!("info depth {} multipv {} score {} nodes {} time {} pv {}",
+                        depth, index + 1, protocol_score(Score::from_raw(color * line.score)), search.nodes(), elapsed_ms(start_time), moves.join(" ")));
+                }
+            });
+            lines.first().map(|line| line.forward_board)
+        }
+    };
+    // stopped before the first iteration finished
+    let forward_board = forward_board.or(root.forward_boards().first().copied());
+
+    while limits.infinite && !search.is_stopped() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    match forward_board {
+        Some(forward_board) => format!("bestmove {}", root.action(forward_board)),
+        None => "bestmove none".to_string()
+    }
+}
+
+/// Score for the side to move, e.g. `cp 120` or `win 5`
+pub fn protocol_score(score: Score) -> String {
+    match score {
+        Score::Heuristic(value) => format!("cp {}", value),
+        Score::Win(plies) => format!("win {}", plies),
+        Score::Loss(plies) => format!("loss {}", plies)
+    }
+}
+
+fn elapsed_ms(start_time: f64) -> u64 {
+    ((timer::time() - start_time) * 1000.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::ai::evaluation::MuehleEvaluator;
+    use crate::ai::protocol::{apply_move, go, new_search, parse_command, Command, EngineOptions, GoLimits};
+
+    fn position_of(line: &str) -> crate::ai::RootPosition {
+        match parse_command(line) {
+            Ok(Command::Position(root)) => root,
+            _ => panic!("{} is no position", line)
+        }
+    }
+
+    #[test]
+    fn test_position_and_go() {
+        let root = position_of("position startpos moves 0 8 1 9");
+        assert!(apply_move(&root, "7x8").is_ok());
+        assert!(apply_move(&root, "7").is_err());
+        assert!(apply_move(&root, "0").is_err());
+        assert!(apply_move(&root, "10").is_ok());
+        assert!(parse_command("position startpos moves 0 0").is_err());
+        assert!(parse_command("position board WWEEEEEEBBEEEEEEEEEEEEEE w 4 moves 7x8").is_ok());
+        assert!(matches!(parse_command("go depth 3 wtime 1000"), Ok(Command::Go(GoLimits { depth: Some(3), wtime: Some(1000), .. }))));
+        assert!(matches!(parse_command("setoption name MultiPV value 2"), Ok(Command::SetOption { name, value }) if name == "MultiPV" && value == "2"));
+
+        let mut options = EngineOptions::default();
+        options.set("MultiPV", "2").unwrap();
+        assert!(options.set("Level", "x").is_err());
+
+        let evaluator = MuehleEvaluator::default();
+        let limits = GoLimits { depth: Some(2), ..GoLimits::default() };
+        let search = new_search(&evaluator, &root, &options, &limits);
+        let info = Mutex::new(Vec::new());
+        let bestmove = go(&search, &root, &options, &limits, &|line| info.lock().unwrap().push(line));
+
+        assert!(bestmove == "bestmove 7x8" || bestmove == "bestmove 7x9", "{}", bestmove);
+        let info = info.into_inner().unwrap();
+        assert_eq!(4, info.len());
+        assert!(info[3].starts_with("info depth 2 multipv 2 score "), "{}", info[3]);
+    }
+
+    #[test]
+    fn test_stop_every_engine() {
+        let root = position_of("position startpos moves 0 8 1 9");
+        let evaluator = MuehleEvaluator::default();
+        let limits = GoLimits { infinite: true, ..GoLimits::default() };
+
+        for (engine, level) in [("minimax", "20"), ("minimax", "10"), ("mcts", "20")] {
+            let mut options = EngineOptions::default();
+            options.set("Engine", engine).unwrap();
+            options.set("Level", level).unwrap();
+            let search = new_search(&evaluator, &root, &options, &limits);
+
+            let bestmove = thread::scope(|scope| {
+                let handle = scope.spawn(|| go(&search, &root, &options, &limits, &|_| {}));
+                thread::sleep(Duration::from_millis(200));
+                // the best move of an infinite search waits for the stop
+                assert!(!handle.is_finished(), "{} level {}", engine, level);
+                search.stop();
+                handle.join().unwrap()
+            });
+            assert!(bestmove.starts_with("bestmove "), "{}", bestmove);
+            assert_ne!("bestmove none", bestmove);
+        }
+
+        let mut options = EngineOptions::default();
+        options.set("Engine", "mcts").unwrap();
+        assert!(options.check(&GoLimits { depth: Some(3), ..GoLimits::default() }).is_err());
+        assert!(options.check(&limits).is_ok());
+    }
+}
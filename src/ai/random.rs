@@ -0,0 +1,55 @@
+use crate::ai::timer;
+
+/// Small xorshift64* generator, good enough for picking moves and openings
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // the state must never be zero
+        Rng { state: (seed ^ 0x9E3779B97F4A7C15) | 1 }
+    }
+
+    pub fn from_time() -> Self {
+        Rng::new((timer::time() * 1_000_000.0) as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, upper)`, `upper` has to be greater than zero
+    pub fn below(&mut self, upper: usize) -> usize {
+        (self.next_u64() % upper as u64) as usize
+    }
+
+    /// Picks an index with a probability proportional to its weight
+    pub fn weighted_index(&mut self, weights: &[f64]) -> Option<usize> {
+        let total: f64 = weights.iter().filter(|weight| **weight > 0.0).sum();
+        if total <= 0.0 {
+            return None
+        }
+
+        let mut target = self.next_f64() * total;
+        for (index, weight) in weights.iter().enumerate() {
+            if *weight <= 0.0 {
+                continue
+            }
+            if target < *weight {
+                return Some(index)
+            }
+            target -= weight;
+        }
+        weights.iter().rposition(|weight| *weight > 0.0)
+    }
+}
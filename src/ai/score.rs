@@ -0,0 +1,134 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+
+use serde::Serialize;
+
+/// Raw score of a position which is won right now. The search stores wins as `WIN - plies`
+/// with the plies counted from the root, so a faster win is always worth more.
+pub const WIN: isize = isize::MAX;
+/// Longest distance to a win or loss the raw scores can hold
+const MAX_PLIES: isize = 1 << 16;
+
+/// Raw score of a win `plies` plies after the root
+pub fn win_in(plies: usize) -> isize {
+    WIN - plies as isize
+}
+
+/// Raw score of a loss `plies` plies after the root
+pub fn loss_in(plies: usize) -> isize {
+    -win_in(plies)
+}
+
+pub fn is_decisive(raw: isize) -> bool {
+    raw.abs() > WIN - MAX_PLIES
+}
+
+/// Moves a win or loss which is `plies` plies away from a position found `ply` plies below the root,
+/// heuristic scores are kept
+pub fn from_ply(raw: isize, ply: usize) -> isize {
+    if !is_decisive(raw) {
+        raw
+    } else if raw > 0 {
+        raw - ply as isize
+    } else {
+        raw + ply as isize
+    }
+}
+
+/// Inverse of [`from_ply`], counts a win or loss from the position `ply` plies below the root
+pub fn to_ply(raw: isize, ply: usize) -> isize {
+    if !is_decisive(raw) {
+        raw
+    } else if raw > 0 {
+        raw + ply as isize
+    } else {
+        raw - ply as isize
+    }
+}
+
+/// A score of the search from the view of one side, with wins and losses apart from the heuristic values
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub enum Score {
+    Heuristic(isize),
+    /// Wins after this many plies at the latest
+    Win(usize),
+    /// Loses after this many plies at the earliest
+    Loss(usize),
+}
+impl Score {
+    pub fn from_raw(raw: isize) -> Score {
+        if !is_decisive(raw) {
+            Score::Heuristic(raw)
+        } else if raw > 0 {
+            Score::Win((WIN - raw) as usize)
+        } else {
+            Score::Loss((WIN + raw) as usize)
+        }
+    }
+
+    pub fn to_raw(self) -> isize {
+        match self {
+            Score::Heuristic(value) => value,
+            Score::Win(plies) => win_in(plies),
+            Score::Loss(plies) => loss_in(plies)
+        }
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_raw().cmp(&other.to_raw())
+    }
+}
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The same score from the view of the opponent
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        match self {
+            Score::Heuristic(value) => Score::Heuristic(-value),
+            Score::Win(plies) => Score::Loss(plies),
+            Score::Loss(plies) => Score::Win(plies)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Score::Heuristic(value) => write!(f, "{:+}", value),
+            Score::Win(plies) => write!(f, "win in {}", plies),
+            Score::Loss(plies) => write!(f, "loss in {}", plies)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::score::{from_ply, win_in, Score, WIN};
+
+    #[test]
+    fn test_raw_round_trip_and_order() {
+        let scores = [Score::Loss(1), Score::Loss(9), Score::Heuristic(-3000), Score::Heuristic(0), Score::Heuristic(1000), Score::Win(5), Score::Win(1)];
+        for pair in scores.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} {:?}", pair[0], pair[1]);
+        }
+        for score in scores {
+            assert_eq!(score, Score::from_raw(score.to_raw()));
+            assert_eq!(-score, Score::from_raw(-score.to_raw()));
+        }
+
+        assert_eq!(Score::Win(3), Score::from_raw(from_ply(WIN, 3)));
+        assert_eq!(Score::Loss(7), Score::from_raw(from_ply(-win_in(2), 5)));
+        assert_eq!(Score::Heuristic(250), Score::from_raw(from_ply(250, 5)));
+        assert_eq!("win in 5", Score::Win(5).to_string());
+        assert_eq!("-120", Score::Heuristic(-120).to_string());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::ai::evaluation::Evaluator;
+use crate::ai::random::Rng;
+use crate::ai::{score, Phase};
+use crate::core::enums::Difficulty;
+
+pub const MIN_SKILL_LEVEL: u8 = 1;
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Rating of level 1, every level is about this much stronger than the one below
+const BASE_ELO: u32 = 600;
+const ELO_PER_LEVEL: u32 = 100;
+
+/// Softmax temperature of level 1 in evaluation units, a piece is worth about 1000
+const MAX_TEMPERATURE: f64 = 400.0;
+/// Chance of level 1 to overlook the reply of the opponent
+const MAX_BLUNDER_CHANCE: f64 = 0.3;
+
+/// Playing strength from 1 (beginner) to 20 (full strength). Weaker levels search shallower, pick among
+/// the root moves by a softmax over their scores and now and then overlook an open mill of the opponent.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Skill {
+    level: u8,
+}
+impl Skill {
+    pub fn new(level: u8) -> Skill {
+        Skill { level: level.clamp(MIN_SKILL_LEVEL, MAX_SKILL_LEVEL) }
+    }
+
+    pub fn from_elo(elo: u32) -> Skill {
+        let level = (elo.saturating_sub(BASE_ELO) + ELO_PER_LEVEL / 2) / ELO_PER_LEVEL + 1;
+        Skill::new(level.min(MAX_SKILL_LEVEL as u32) as u8)
+    }
+
+    pub fn level(self) -> u8 {
+        self.level
+    }
+
+    pub fn elo(self) -> u32 {
+        BASE_ELO + (self.level - 1) as u32 * ELO_PER_LEVEL
+    }
+
+    /// Plays without any randomness and uses the whole time of the search
+    pub fn is_full_strength(self) -> bool {
+        self.level == MAX_SKILL_LEVEL
+    }
+
+    /// Plies searched below each root move
+    pub fn depth(self) -> usize {
+        match self.level {
+            1..=3 => 0,
+            4..=6 => 1,
+            7..=9 => 2,
+            10..=12 => 3,
+            13..=15 => 4,
+            16..=18 => 5,
+            _ => 6
+        }
+    }
+
+    /// Whether the search uses the endgame tables, the lower levels play the endgame by themselves
+    pub fn knows_endgame(self) -> bool {
+        self.depth() >= 5
+    }
+
+    pub fn temperature(self) -> f64 {
+        MAX_TEMPERATURE * self.weakness()
+    }
+
+    pub fn blunder_chance(self) -> f64 {
+        MAX_BLUNDER_CHANCE * self.weakness()
+    }
+
+    // 1 for level 1 down to 0 for full strength, falling quickly at first
+    fn weakness(self) -> f64 {
+        let weakness = (MAX_SKILL_LEVEL - self.level) as f64 / (MAX_SKILL_LEVEL - MIN_SKILL_LEVEL) as f64;
+        weakness * weakness
+    }
+}
+
+impl From<Difficulty> for Skill {
+    fn from(difficulty: Difficulty) -> Skill {
+        match difficulty {
+            Difficulty::Off | Difficulty::Easy => Skill::new(3),
+            Difficulty::Medium => Skill::new(9),
+            Difficulty::Hard => Skill::new(MAX_SKILL_LEVEL)
+        }
+    }
+}
+
+/// Picks one of the scored root moves by a softmax over the scores from the view of the side to move
+pub fn softmax_choice(scored: &[(u64, isize)], temperature: f64, rng: &mut Rng) -> Option<u64> {
+    let best = scored.iter().max_by_key(|(_, score)| *score)?;
+    if temperature <= 0.0 {
+        return Some(best.0)
+    }
+    rng.weighted_index(&softmax_weights(scored, best.1, temperature)).map(|index| scored[index].0)
+}
+
+// relative chances of the scored moves, 1 for the best score
+fn softmax_weights(scored: &[(u64, isize)], best: isize, temperature: f64) -> Vec<f64> {
+    scored.iter()
+        .map(|(_, score)| ((*score as f64 - best as f64) / temperature).exp())
+        .collect()
+}
+
+/// Scores of the root moves by a player who only looks at the board after their own move
+/// and so misses the open mills of the opponent
+pub fn blunder_scores(forward_boards: &[u64], token_type: u8, phase: Phase, evaluator: &dyn Evaluator) -> Vec<(u64, isize)> {
+    let color: isize = if token_type == 0b11 { 1 } else { -1 };
+    forward_boards.iter()
+        .map(|forward_board| (*forward_board, score::from_ply(color * evaluator.evaluate(*forward_board, phase.increased()), 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::action::forward_step_boards;
+    use crate::ai::benchmark::BENCHMARK_POSITIONS;
+    use crate::ai::evaluation::MuehleEvaluator;
+    use crate::ai::minimax::Search;
+    use crate::ai::ordering::MoveOrdering;
+    use crate::ai::random::Rng;
+    use crate::ai::skill::{softmax_choice, softmax_weights, Skill, MAX_SKILL_LEVEL, MIN_SKILL_LEVEL};
+    use crate::ai::{score_root_moves, Phase};
+
+    #[test]
+    fn test_levels_are_ordered() {
+        for level in MIN_SKILL_LEVEL..MAX_SKILL_LEVEL {
+            let (weaker, stronger) = (Skill::new(level), Skill::new(level + 1));
+            assert!(weaker.depth() <= stronger.depth());
+            assert!(weaker.temperature() > stronger.temperature());
+            assert!(weaker.blunder_chance() > stronger.blunder_chance());
+            assert_eq!(weaker, Skill::from_elo(weaker.elo()));
+            assert!(weaker.elo() < stronger.elo());
+        }
+        assert_eq!(0.0, Skill::new(MAX_SKILL_LEVEL).temperature());
+        assert_eq!(Skill::new(1), Skill::from_elo(0));
+        assert_eq!(Skill::new(MAX_SKILL_LEVEL), Skill::from_elo(4000));
+    }
+
+    #[test]
+    fn test_higher_levels_search_more_or_choose_better() {
+        let evaluator = MuehleEvaluator::default();
+        for position in BENCHMARK_POSITIONS.iter().filter(|position| ["late setup", "middlegame", "endgame"].contains(&position.name)) {
+            let phase = Phase::new(position.phase, position.step_counter);
+            let forward_boards: Vec<u64> = forward_step_boards(&position.board(), position.token_type, phase).collect();
+            // nodes and root scores of the search at every depth a level uses
+            let searches: Vec<(u64, Vec<(u64, isize)>)> = (0..=Skill::new(MAX_SKILL_LEVEL).depth())
+                .map(|depth| {
+                    let search = Search::new(&evaluator, MoveOrdering::Heuristic, None);
+                    let scored = score_root_moves(&search, &forward_boards, position.token_type, phase, depth);
+                    (search.nodes(), scored)
+                })
+                .collect();
+            // chance of the level to play the move its own search scores best
+            let best_move_chance = |skill: Skill| {
+                let scored = &searches[skill.depth()].1;
+                let best = scored.iter().map(|(_, score)| *score).max().unwrap();
+                let weights = softmax_weights(scored, best, skill.temperature().max(f64::MIN_POSITIVE));
+                (1.0 - skill.blunder_chance()) * scored.iter().zip(&weights).filter(|((_, score), _)| *score == best).map(|(_, weight)| weight).sum::<f64>() / weights.iter().sum::<f64>()
+            };
+
+            for level in MIN_SKILL_LEVEL..MAX_SKILL_LEVEL {
+                let (weaker, stronger) = (Skill::new(level), Skill::new(level + 1));
+                // a deeper search sees more, at the same depth the stronger level picks its best move more often
+                if weaker.depth() < stronger.depth() {
+                    assert!(searches[weaker.depth()].0 < searches[stronger.depth()].0, "{} level {}", position.name, level);
+                } else {
+                    assert!(best_move_chance(weaker) < best_move_chance(stronger), "{} level {}", position.name, level);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_softmax_prefers_better_moves() {
+        let scored = [(1, 0), (2, -400), (3, -2000)];
+        assert_eq!(Some(1), softmax_choice(&scored, 0.0, &mut Rng::new(3)));
+
+        let mut rng = Rng::new(3);
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[softmax_choice(&scored, 400.0, &mut rng).unwrap() as usize - 1] += 1;
+        }
+        assert!(counts[0] > counts[1] && counts[1] > counts[2], "{:?}", counts);
+        assert!(counts[2] < 20, "{:?}", counts);
+    }
+}
@@ -0,0 +1,75 @@
+// position 8 * ring + k, k counted clockwise from the top middle of the ring:
+// rotations by a quarter turn, the mirror image and swapping the inner with the outer ring keep all mills
+const fn symmetries() -> [[usize; 24]; 16] {
+    let mut symmetries = [[0; 24]; 16];
+    let mut symmetry = 0;
+    while symmetry < 16 {
+        let (rotation, mirrored, swapped) = (symmetry % 4, symmetry / 4 % 2 == 1, symmetry / 8 == 1);
+        let mut position = 0;
+        while position < 24 {
+            let (mut ring, mut k) = (position / 8, position % 8);
+            if mirrored {
+                k = (8 - k) % 8;
+            }
+            k = (k + 2 * rotation) % 8;
+            if swapped {
+                ring = 2 - ring;
+            }
+            symmetries[symmetry][position] = 8 * ring + k;
+            position += 1;
+        }
+        symmetry += 1;
+    }
+    symmetries
+}
+pub const SYMMETRIES: [[usize; 24]; 16] = symmetries();
+
+/// Moves every set bit to its image under the symmetry
+pub fn transform(mut mask: u32, symmetry: usize) -> u32 {
+    let mut transformed = 0;
+    while mask != 0 {
+        transformed |= 1 << SYMMETRIES[symmetry][mask.trailing_zeros() as usize];
+        mask &= mask - 1;
+    }
+    transformed
+}
+
+/// The position which the symmetry moves onto `position`
+pub fn inverse(position: usize, symmetry: usize) -> usize {
+    SYMMETRIES[symmetry].iter().position(|image| *image == position).unwrap()
+}
+
+/// Smallest pair of white and black masks among all symmetric positions and the symmetry which leads to it
+pub fn canonical(white: u32, black: u32) -> (u32, u32, usize) {
+    (0..16).map(|symmetry| (transform(white, symmetry), transform(black, symmetry), symmetry))
+        .min()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::evaluation::MILL_MASKS;
+    use crate::ai::symmetry::{canonical, inverse, transform, SYMMETRIES};
+
+    #[test]
+    fn test_symmetries_keep_mills() {
+        for symmetry in 0..16 {
+            for mill in MILL_MASKS.iter() {
+                assert!(MILL_MASKS.contains(&transform(*mill, symmetry)), "{} {:b}", symmetry, mill);
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        let (white, black) = (0b1, 0b100);
+        let (canonical_white, canonical_black, symmetry) = canonical(white, black);
+
+        assert_eq!((canonical_white, canonical_black), (transform(white, symmetry), transform(black, symmetry)));
+        for other in 0..16 {
+            let (other_white, other_black, _) = canonical(transform(white, other), transform(black, other));
+            assert_eq!((canonical_white, canonical_black), (other_white, other_black));
+        }
+        assert_eq!(5, inverse(SYMMETRIES[symmetry][5], symmetry));
+    }
+}
@@ -0,0 +1,20 @@
+//! Wall clock of the searches, without the timer of good-web-game so that the `wasm-api` build does not need
+//! the JavaScript loader of the canvas
+
+/// Seconds since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+pub fn time() -> f64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+/// Seconds since the Unix epoch
+#[cfg(all(target_arch = "wasm32", feature = "wasm-api"))]
+pub fn time() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+/// Seconds of the clock of the JavaScript loader of the canvas
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm-api")))]
+pub fn time() -> f64 {
+    good_web_game::timer::time()
+}
@@ -0,0 +1,320 @@
+use rayon::prelude::*;
+
+use crate::ai::action::forward_step_boards;
+use crate::ai::engine::{EngineKind, MctsEngine};
+use crate::ai::evaluation::{terminal_score, MuehleEvaluator, Weights};
+use crate::ai::mcts::mcts;
+use crate::ai::random::Rng;
+use crate::ai::skill::{Skill, MAX_SKILL_LEVEL};
+use crate::ai::{choose_move, Phase, PhaseType, RootPosition, SearchLimits};
+use crate::core::position::negate_token;
+
+#[derive(Debug)]
+pub struct TournamentError {
+    pub message: String,
+}
+impl TournamentError {
+    pub fn new(message: String) -> TournamentError {
+        TournamentError { message }
+    }
+}
+
+/// One side of a match
+#[derive(Clone, Copy)]
+pub struct PlayerConfig {
+    pub engine: EngineKind,
+    pub skill: Skill,
+    pub weights: Weights,
+    /// Seconds per move at full strength, also the most a weaker level may think
+    pub time_limit: f64,
+}
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            engine: EngineKind::Minimax,
+            skill: Skill::new(MAX_SKILL_LEVEL),
+            weights: Weights::default(),
+            time_limit: 0.1,
+        }
+    }
+}
+impl PlayerConfig {
+    /// Parses `key=value` pairs separated by commas, e.g. `engine=mcts,level=12,time=0.2,weights=tuned.txt`
+    pub fn parse(description: &str) -> Result<PlayerConfig, TournamentError> {
+        let mut config = PlayerConfig::default();
+        for pair in description.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (key, value) = pair.split_once('=')
+                .ok_or(TournamentError::new(format!("Missing '=' in \"{}\"", pair)))?;
+            let invalid = || TournamentError::new(format!("Invalid value \"{}\" for {}", value, key));
+
+            match key.trim() {
+                "engine" => config.engine = match value {
+                    "minimax" => EngineKind::Minimax,
+                    "mcts" => EngineKind::Mcts,
+                    _ => return Err(invalid())
+                },
+                "level" => config.skill = Skill::new(value.parse().map_err(|_| invalid())?),
+                "elo" => config.skill = Skill::from_elo(value.parse().map_err(|_| invalid())?),
+                "time" => config.time_limit = value.parse().map_err(|_| invalid())?,
+                "weights" => config.weights = Weights::from_file(value).map_err(|err| TournamentError::new(err.message))?,
+                _ => return Err(TournamentError::new(format!("Unknown key \"{}\"", key)))
+            }
+        }
+        Ok(config)
+    }
+
+    fn choose(&self, root: &RootPosition, rng: &mut Rng) -> Option<u64> {
+        let evaluator = MuehleEvaluator::new(self.weights);
+        match self.engine {
+            EngineKind::Minimax => {
+                // the games already run in parallel
+                let limits = SearchLimits { time_limit: self.time_limit, threads: 1 };
+                choose_move(root, self.skill, &evaluator, limits, rng).map(|chosen| chosen.forward_board)
+            }
+            EngineKind::Mcts => {
+                let mut options = MctsEngine::options(self.skill);
+                options.time_limit = Some(self.time_limit);
+                options.seed = Some(rng.next_u64());
+                mcts(root, &options, &evaluator).map(|result| result.forward_board)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameResult {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Wins, draws and losses of the first player
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct MatchStats {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+impl MatchStats {
+    pub fn add(&mut self, result: GameResult) {
+        match result {
+            GameResult::Win => self.wins += 1,
+            GameResult::Draw => self.draws += 1,
+            GameResult::Loss => self.losses += 1,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, a draw is half a point
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /// Variance of the points of a single game
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games().max(1) as f64;
+        (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2)) / games
+    }
+
+    /// Elo difference of the first player and the half width of its 95% confidence interval
+    pub fn elo(&self) -> (f64, f64) {
+        let score = self.score();
+        let margin = 1.96 * (self.variance() / self.games().max(1) as f64).sqrt();
+        let elo = elo_of_score(score);
+        let error = (elo_of_score(score + margin) - elo_of_score(score - margin)) / 2.0;
+        (elo, error)
+    }
+
+    /// Log likelihood ratio of the hypothesis that the first player is `elo1` stronger against it being
+    /// `elo0` stronger, by the normal approximation of the trinomial distribution
+    pub fn log_likelihood_ratio(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.games() == 0 {
+            return 0.0
+        }
+        // a clean sweep has no variance at all, one more win and loss keep the first games from deciding alone
+        let variance = MatchStats { wins: self.wins + 1, draws: self.draws, losses: self.losses + 1 }.variance();
+        let (score0, score1) = (score_of_elo(elo0), score_of_elo(elo1));
+        let score = self.score();
+        self.games() as f64 / (2.0 * variance) * ((score - score0).powi(2) - (score - score1).powi(2))
+    }
+}
+
+pub fn elo_of_score(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+pub fn score_of_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Sequential probability ratio test between an Elo difference of `elo0` and one of `elo1`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Chance to accept `elo1` although `elo0` is true
+    pub alpha: f64,
+    /// Chance to accept `elo0` although `elo1` is true
+    pub beta: f64,
+}
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt { elo0: 0.0, elo1: 20.0, alpha: 0.05, beta: 0.05 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SprtDecision {
+    /// The first player is not `elo0` stronger
+    AcceptH0,
+    /// The first player is `elo1` stronger
+    AcceptH1,
+    Continue,
+}
+
+impl Sprt {
+    /// Lower and upper bound of the log likelihood ratio
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn decide(&self, stats: &MatchStats) -> SprtDecision {
+        let llr = stats.log_likelihood_ratio(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            SprtDecision::AcceptH0
+        } else if llr >= upper {
+            SprtDecision::AcceptH1
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TournamentOptions {
+    pub games: usize,
+    /// Random plies at the start of every opening, each opening is played once with either color
+    pub opening_plies: usize,
+    /// Games longer than this are counted as draw
+    pub max_plies: usize,
+    /// Stops as soon as the test decides
+    pub sprt: Option<Sprt>,
+    pub seed: u64,
+}
+impl Default for TournamentOptions {
+    fn default() -> Self {
+        TournamentOptions {
+            games: 100,
+            opening_plies: 4,
+            max_plies: 200,
+            sprt: None,
+            seed: 1,
+        }
+    }
+}
+
+/// Plays `options.games` games of `first` against `second` in parallel batches, calling `progress` after each batch.
+/// Games `2n` and `2n + 1` share an opening and swap the colors.
+pub fn run_tournament(options: &TournamentOptions, first: &PlayerConfig, second: &PlayerConfig, mut progress: impl FnMut(&MatchStats)) -> MatchStats {
+    let batch_size = (2 * rayon::current_num_threads()).max(2);
+    let mut stats = MatchStats::default();
+
+    let mut game_index = 0;
+    while game_index < options.games {
+        let batch_end = (game_index + batch_size).min(options.games);
+        let results: Vec<GameResult> = (game_index..batch_end).into_par_iter()
+            .map(|index| {
+                let opening_seed = options.seed.wrapping_mul(1_000_003).wrapping_add((index / 2) as u64);
+                play_game(first, second, index % 2 == 0, opening_seed, options)
+            })
+            .collect();
+        results.into_iter().for_each(|result| stats.add(result));
+        game_index = batch_end;
+
+        progress(&stats);
+        if options.sprt.is_some_and(|sprt| sprt.decide(&stats) != SprtDecision::Continue) {
+            break
+        }
+    }
+    stats
+}
+
+/// Plays one game and returns the result of `first`
+pub fn play_game(first: &PlayerConfig, second: &PlayerConfig, first_is_white: bool, opening_seed: u64, options: &TournamentOptions) -> GameResult {
+    let mut opening_rng = Rng::new(opening_seed);
+    let mut rng = Rng::new(opening_seed ^ if first_is_white { 0 } else { 0x5DEE_CE66 });
+    let mut board: u64 = 0;
+    let mut token_type: u8 = 0b11;
+    let mut phase = Phase::new(PhaseType::Set, 0);
+
+    for ply in 0..options.max_plies {
+        let white_result = if let Some(score) = terminal_score(board, phase) {
+            Some(score > 0)
+        } else {
+            let forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+            if forward_boards.is_empty() {
+                Some(token_type != 0b11)
+            } else {
+                board = if ply < options.opening_plies {
+                    forward_boards[opening_rng.below(forward_boards.len())]
+                } else {
+                    let player = if (token_type == 0b11) == first_is_white { first } else { second };
+                    match player.choose(&RootPosition::new(board, token_type, phase), &mut rng) {
+                        Some(forward_board) => forward_board,
+                        None => forward_boards[rng.below(forward_boards.len())]
+                    }
+                };
+                None
+            }
+        };
+
+        if let Some(white_won) = white_result {
+            return if white_won == first_is_white { GameResult::Win } else { GameResult::Loss }
+        }
+        token_type = negate_token(token_type);
+        phase = phase.increased();
+    }
+    GameResult::Draw
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::skill::Skill;
+    use crate::ai::tournament::{elo_of_score, run_tournament, MatchStats, PlayerConfig, Sprt, SprtDecision, TournamentOptions};
+
+    #[test]
+    fn test_elo_and_sprt() {
+        assert!((elo_of_score(0.75) - 190.85).abs() < 0.01);
+        assert_eq!(0.0, elo_of_score(0.5));
+
+        let even = MatchStats { wins: 400, draws: 200, losses: 400 };
+        let (elo, error) = even.elo();
+        assert_eq!(0.0, elo);
+        assert!(error > 10.0 && error < 25.0, "{}", error);
+        assert_eq!(SprtDecision::AcceptH0, Sprt { elo0: 0.0, elo1: 50.0, ..Sprt::default() }.decide(&even));
+
+        let strong = MatchStats { wins: 300, draws: 20, losses: 80 };
+        assert_eq!(SprtDecision::AcceptH1, Sprt::default().decide(&strong));
+        assert_eq!(SprtDecision::Continue, Sprt::default().decide(&MatchStats { wins: 3, draws: 1, losses: 2 }));
+        assert_eq!(SprtDecision::AcceptH1, Sprt::default().decide(&MatchStats { wins: 30, draws: 0, losses: 0 }));
+    }
+
+    #[test]
+    fn test_tournament_plays_all_games() {
+        let weak = PlayerConfig { skill: Skill::new(1), ..PlayerConfig::default() };
+        let options = TournamentOptions { games: 4, max_plies: 60, ..TournamentOptions::default() };
+
+        let mut batches = 0;
+        let stats = run_tournament(&options, &weak, &weak, |_| batches += 1);
+        assert_eq!(4, stats.games());
+        assert!(batches >= 1);
+        assert!(PlayerConfig::parse("engine=mcts,level=5,time=0.5").is_ok_and(|config| config.skill == Skill::new(5)));
+        assert!(PlayerConfig::parse("level").is_err());
+    }
+}
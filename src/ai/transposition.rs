@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::ai::{Phase, PhaseType};
+
+const MOVE_MASK: u64 = 0xFFFFFFFFFFFF;
+
+/// How the stored score relates to the real score of the position
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound {
+    Exact,
+    /// The search failed high, the real score is at least as good
+    Lower,
+    /// The search failed low, the real score is at most as good
+    Upper
+}
+
+/// What the table remembers about a searched position
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entry {
+    /// Xor of the board before and after the best move, see [`move_of`]
+    pub best_move: u64,
+    pub depth: u8,
+    /// Wins and losses are counted from the position itself, not from the root
+    pub score: isize,
+    pub bound: Bound,
+}
+
+/// Fixed size hash table which can be shared between threads without locks.
+/// Every slot stores the key xor'ed with the data, so torn writes are detected on probe.
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 3]>,
+}
+
+impl TranspositionTable {
+    /// Creates a table with `2^size_bits` entries
+    pub fn new(size_bits: u32) -> Self {
+        TranspositionTable {
+            entries: (0..1usize << size_bits).map(|_| [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+        }
+    }
+
+    /// The board alone is not enough: during the setup the same board can have different pieces in hand
+    pub fn key(board: u64, token_type: u8, phase: Phase) -> u64 {
+        let step = if phase.phase == PhaseType::Set { phase.step_counter as u64 } else { 0xFF };
+        mix(board) ^ mix((token_type as u64) << 8 | step)
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = &self.entries[key as usize & (self.entries.len() - 1)];
+        let checked = slot[0].load(Ordering::Relaxed);
+        let data = slot[1].load(Ordering::Relaxed);
+        let score = slot[2].load(Ordering::Relaxed);
+        if checked ^ data ^ score != key || data == 0 {
+            return None
+        }
+
+        Some(Entry {
+            best_move: data & MOVE_MASK,
+            depth: (data >> 48) as u8,
+            score: score as i64 as isize,
+            bound: match data >> 56 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper
+            },
+        })
+    }
+
+    pub fn store(&self, key: u64, entry: Entry) {
+        let slot = &self.entries[key as usize & (self.entries.len() - 1)];
+        if let Some(existing) = self.probe(key) {
+            if existing.depth > entry.depth {
+                return
+            }
+        }
+
+        let bound: u64 = match entry.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2
+        };
+        let data = (entry.best_move & MOVE_MASK) | (entry.depth as u64) << 48 | bound << 56;
+        let score = entry.score as i64 as u64;
+        slot[0].store(key ^ data ^ score, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+        slot[2].store(score, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.entries.iter() {
+            slot[0].store(0, Ordering::Relaxed);
+            slot[1].store(0, Ordering::Relaxed);
+            slot[2].store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Identifies a move independent of the rest of the board, the same move in another position gives the same value
+pub fn move_of(board: u64, forward_board: u64) -> u64 {
+    (board ^ forward_board) & MOVE_MASK
+}
+
+// splitmix64 finalizer
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D049BB133111EB);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::score::win_in;
+    use crate::ai::transposition::{move_of, Bound, Entry, TranspositionTable};
+    use crate::ai::{Phase, PhaseType};
+    use crate::core::position::decode_positions;
+
+    #[test]
+    fn test_store_and_probe() {
+        let table = TranspositionTable::new(8);
+        let board = decode_positions("WEEEEEEEEEEEEEEEEEEEEEEB".to_string());
+        let key = TranspositionTable::key(board, 0b11, Phase::new(PhaseType::Set, 2));
+
+        assert_eq!(None, table.probe(key));
+        let entry = Entry { best_move: 0b1100, depth: 3, score: -win_in(4), bound: Bound::Upper };
+        table.store(key, entry);
+        assert_eq!(Some(entry), table.probe(key));
+
+        // shallower results do not replace deeper ones
+        table.store(key, Entry { best_move: 0b11, depth: 1, score: 20, bound: Bound::Exact });
+        assert_eq!(Some(entry), table.probe(key));
+
+        table.clear();
+        assert_eq!(None, table.probe(key));
+    }
+
+    #[test]
+    fn test_key_depends_on_pieces_in_hand() {
+        let board = decode_positions("WEEEEEEEEEEEEEEEEEEEEEEB".to_string());
+
+        assert_ne!(TranspositionTable::key(board, 0b11, Phase::new(PhaseType::Set, 2)), TranspositionTable::key(board, 0b11, Phase::new(PhaseType::Set, 4)));
+        assert_ne!(TranspositionTable::key(board, 0b11, Phase::new(PhaseType::Move, 20)), TranspositionTable::key(board, 0b10, Phase::new(PhaseType::Move, 20)));
+        assert_eq!(TranspositionTable::key(board, 0b11, Phase::new(PhaseType::Move, 20)), TranspositionTable::key(board, 0b11, Phase::new(PhaseType::Move, 30)));
+    }
+
+    #[test]
+    fn test_move_of() {
+        let board1 = decode_positions("WEEEEEEEEEEEEEEEEEEEEEEB".to_string());
+        let board2 = decode_positions("EWEEEEEEEEEEEEEEEEEEEEEB".to_string());
+        let board3 = decode_positions("WEEEEEEEEEEEEEEEEEEEEEEE".to_string());
+        let board4 = decode_positions("EWEEEEEEEEEEEEEEEEEEEEEE".to_string());
+
+        assert_eq!(move_of(board1, board2), move_of(board3, board4));
+    }
+}
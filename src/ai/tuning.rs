@@ -0,0 +1,326 @@
+use rayon::prelude::*;
+
+use crate::ai::action::forward_step_boards;
+use crate::ai::evaluation::{terminal_score, Evaluator, Features, GamePhase, PhaseWeights, Weights, WeightsError, FEATURE_COUNT};
+use crate::ai::random::Rng;
+use crate::ai::{search_fixed_depth, Phase, PhaseType};
+use crate::core::position::{decode_positions, encode_positions, negate_token};
+use crate::core::utils::{insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+/// A position with the expected result for white: 1.0 win, 0.5 draw, 0.0 loss
+#[derive(Clone, Copy)]
+pub struct LabelledPosition {
+    pub board: u64,
+    pub phase: Phase,
+    pub result: f64,
+}
+
+impl LabelledPosition {
+    pub fn new(board: u64, phase: Phase, result: f64) -> Self {
+        LabelledPosition { board, phase, result }
+    }
+
+    /// `<positions> <S|M> <step counter> <result>`, for example `WEEEEEEEEEEEEEEEEEEEEEEB S 2 0.5`
+    pub fn to_line(&self) -> String {
+        let phase = if self.phase.phase == PhaseType::Set { "S" } else { "M" };
+        format!("{} {} {} {}", encode_positions(self.board), phase, self.phase.step_counter, self.result)
+    }
+
+    pub fn parse_line(line: &str) -> Result<LabelledPosition, WeightsError> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 4 || parts[0].len() != 24 || parts[0].chars().any(|c| !"WBE".contains(c)) {
+            return Err(WeightsError::new(format!("Invalid position line \"{}\"", line)));
+        }
+
+        let phase_type = match parts[1] {
+            "S" => PhaseType::Set,
+            "M" => PhaseType::Move,
+            other => return Err(WeightsError::new(format!("Unknown phase \"{}\"", other)))
+        };
+        let step_counter: u8 = match parts[2].parse() {
+            Ok(value) => value,
+            Err(_) => return Err(WeightsError::new(format!("Invalid step counter \"{}\"", parts[2])))
+        };
+        let result: f64 = match parts[3].parse() {
+            Ok(value) if (0.0..=1.0).contains(&value) => value,
+            _ => return Err(WeightsError::new(format!("Invalid result \"{}\"", parts[3])))
+        };
+
+        let board = decode_positions(parts[0].to_string());
+        let board = insert_number_of_possible_moves_to_board(insert_token_count_to_board(board));
+        Ok(LabelledPosition::new(board, Phase::new(phase_type, step_counter), result))
+    }
+}
+
+pub fn load_positions(path: &str) -> Result<Vec<LabelledPosition>, WeightsError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => return Err(WeightsError::new(format!("Cannot read positions file {}: {}", path, err)))
+    };
+    content.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(LabelledPosition::parse_line)
+        .collect()
+}
+
+pub fn save_positions(path: &str, positions: &[LabelledPosition]) -> std::io::Result<()> {
+    let content: String = positions.iter().map(|position| position.to_line() + "\n").collect();
+    std::fs::write(path, content)
+}
+
+pub struct SelfPlayOptions {
+    pub games: usize,
+    pub depth: usize,
+    /// Plies at the start of every game which are played randomly
+    pub random_plies: usize,
+    /// Games longer than this are counted as draw
+    pub max_plies: usize,
+    pub seed: u64,
+}
+
+impl Default for SelfPlayOptions {
+    fn default() -> Self {
+        SelfPlayOptions {
+            games: 200,
+            depth: 2,
+            random_plies: 6,
+            max_plies: 200,
+            seed: 1,
+        }
+    }
+}
+
+/// Plays games of the engine against itself and labels every position with the final result
+pub fn generate_self_play(options: &SelfPlayOptions, evaluator: &dyn Evaluator) -> Vec<LabelledPosition> {
+    (0..options.games).into_par_iter()
+        .flat_map_iter(|game_index| {
+            let mut rng = Rng::new(options.seed.wrapping_mul(1_000_003).wrapping_add(game_index as u64));
+            play_self_play_game(&mut rng, options, evaluator)
+        })
+        .collect()
+}
+
+fn play_self_play_game(rng: &mut Rng, options: &SelfPlayOptions, evaluator: &dyn Evaluator) -> Vec<LabelledPosition> {
+    let mut board: u64 = 0;
+    let mut phase = Phase::new(PhaseType::Set, 0);
+    let mut token_type: u8 = 0b11;
+    let mut visited: Vec<(u64, Phase)> = vec![];
+
+    let result = loop {
+        if let Some(score) = terminal_score(board, phase) {
+            break if score > 0 { 1.0 } else { 0.0 }
+        }
+        if phase.step_counter as usize >= options.max_plies {
+            break 0.5
+        }
+
+        let forward_boards: Vec<u64> = forward_step_boards(&board, token_type, phase).collect();
+        if forward_boards.is_empty() {
+            break if token_type == 0b11 { 0.0 } else { 1.0 }
+        }
+
+        board = if (phase.step_counter as usize) < options.random_plies {
+            forward_boards[rng.below(forward_boards.len())]
+        } else {
+            visited.push((board, phase));
+            match search_fixed_depth(board, token_type, phase, options.depth, evaluator) {
+                Some((forward_board, _)) => forward_board,
+                None => forward_boards[rng.below(forward_boards.len())]
+            }
+        };
+        phase = phase.increased();
+        token_type = negate_token(token_type);
+    };
+
+    visited.into_iter()
+        .map(|(board, phase)| LabelledPosition::new(board, phase, result))
+        .collect()
+}
+
+/// Replaces the labels with the result of a deeper search, mapped through the sigmoid with `scaling`
+pub fn label_by_search(positions: &mut [LabelledPosition], depth: usize, scaling: f64, evaluator: &dyn Evaluator) {
+    positions.par_iter_mut().for_each(|position| {
+        let token_type = if position.phase.step_counter % 2 == 0 { 0b11 } else { 0b10 };
+        if let Some((_, score)) = search_fixed_depth(position.board, token_type, position.phase, depth, evaluator) {
+            position.result = sigmoid(scaling * score as f64);
+        }
+    });
+}
+
+pub struct TuningOptions {
+    pub iterations: usize,
+    pub learning_rate: f64,
+}
+
+impl Default for TuningOptions {
+    fn default() -> Self {
+        TuningOptions {
+            iterations: 2000,
+            learning_rate: 2.0,
+        }
+    }
+}
+
+struct Sample {
+    phase_index: usize,
+    features: [f64; FEATURE_COUNT],
+    result: f64,
+}
+
+fn samples_of(positions: &[LabelledPosition]) -> Vec<Sample> {
+    positions.iter()
+        .filter(|position| terminal_score(position.board, position.phase).is_none())
+        .map(|position| Sample {
+            phase_index: match GamePhase::of(position.board, position.phase) {
+                GamePhase::Placement => 0,
+                GamePhase::Moving => 1,
+                GamePhase::Flying => 2
+            },
+            features: Features::extract(position.board, position.phase).to_array().map(|value| value as f64),
+            result: position.result,
+        })
+        .collect()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn phase_parameters(weights: &Weights) -> [[f64; FEATURE_COUNT]; 3] {
+    [weights.placement, weights.moving, weights.flying].map(|phase_weights| phase_weights.to_array().map(|value| value as f64))
+}
+
+fn sample_error(samples: &[Sample], parameters: &[[f64; FEATURE_COUNT]; 3], scaling: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0
+    }
+
+    samples.par_iter()
+        .map(|sample| {
+            let eval: f64 = sample.features.iter().zip(parameters[sample.phase_index]).map(|(feature, weight)| feature * weight).sum();
+            (sigmoid(scaling * eval) - sample.result).powi(2)
+        })
+        .sum::<f64>() / samples.len() as f64
+}
+
+pub fn mean_squared_error(positions: &[LabelledPosition], weights: &Weights, scaling: f64) -> f64 {
+    sample_error(&samples_of(positions), &phase_parameters(weights), scaling)
+}
+
+/// Finds the sigmoid scaling which fits the given weights best, searched on a log scale
+pub fn fit_scaling(positions: &[LabelledPosition], weights: &Weights) -> f64 {
+    let samples = samples_of(positions);
+    let parameters = phase_parameters(weights);
+    let error_at = |exponent: f64| sample_error(&samples, &parameters, 10f64.powf(exponent));
+
+    let (mut low, mut high) = (-7.0, -1.0);
+    for _ in 0..60 {
+        let left = low + (high - low) / 3.0;
+        let right = high - (high - low) / 3.0;
+        if error_at(left) < error_at(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+    10f64.powf((low + high) / 2.0)
+}
+
+/// Texel style tuning: minimises the squared error between the sigmoid of the evaluation
+/// and the labels with Adam. The piece weights stay fixed so that the scale is kept.
+pub fn tune_weights(positions: &[LabelledPosition], initial: &Weights, scaling: f64, options: &TuningOptions) -> Weights {
+    let samples = samples_of(positions);
+    let mut parameters = phase_parameters(initial);
+    if samples.is_empty() {
+        return *initial
+    }
+
+    let mut first_moment = [[0.0; FEATURE_COUNT]; 3];
+    let mut second_moment = [[0.0; FEATURE_COUNT]; 3];
+    let (beta1, beta2, epsilon) = (0.9, 0.999, 1e-8);
+
+    for iteration in 1..=options.iterations {
+        let gradient = samples.par_iter()
+            .fold(|| [[0.0; FEATURE_COUNT]; 3], |mut gradient, sample| {
+                let eval: f64 = sample.features.iter().zip(parameters[sample.phase_index]).map(|(feature, weight)| feature * weight).sum();
+                let prediction = sigmoid(scaling * eval);
+                let factor = 2.0 * (prediction - sample.result) * prediction * (1.0 - prediction) * scaling;
+                for (value, feature) in gradient[sample.phase_index].iter_mut().zip(sample.features) {
+                    *value += factor * feature;
+                }
+                gradient
+            })
+            .reduce(|| [[0.0; FEATURE_COUNT]; 3], |mut a, b| {
+                for (row_a, row_b) in a.iter_mut().zip(b) {
+                    for (value_a, value_b) in row_a.iter_mut().zip(row_b) {
+                        *value_a += value_b;
+                    }
+                }
+                a
+            });
+
+        for phase_index in 0..3 {
+            // index 0 are the pieces, which anchor the scale
+            for feature_index in 1..FEATURE_COUNT {
+                let g = gradient[phase_index][feature_index] / samples.len() as f64;
+                first_moment[phase_index][feature_index] = beta1 * first_moment[phase_index][feature_index] + (1.0 - beta1) * g;
+                second_moment[phase_index][feature_index] = beta2 * second_moment[phase_index][feature_index] + (1.0 - beta2) * g * g;
+                let corrected_first = first_moment[phase_index][feature_index] / (1.0 - beta1.powi(iteration as i32));
+                let corrected_second = second_moment[phase_index][feature_index] / (1.0 - beta2.powi(iteration as i32));
+                parameters[phase_index][feature_index] -= options.learning_rate * corrected_first / (corrected_second.sqrt() + epsilon);
+            }
+        }
+    }
+
+    let rounded = parameters.map(|phase_parameters| PhaseWeights::from_array(phase_parameters.map(|value| value.round() as isize)));
+    Weights {
+        placement: rounded[0],
+        moving: rounded[1],
+        flying: rounded[2],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::evaluation::{MuehleEvaluator, Weights};
+    use crate::ai::tuning::{fit_scaling, generate_self_play, mean_squared_error, tune_weights, LabelledPosition, SelfPlayOptions, TuningOptions};
+
+    #[test]
+    fn test_weights_round_trip() {
+        let weights = Weights::default();
+        assert_eq!(weights, Weights::parse(&weights.to_string()).unwrap());
+
+        assert!(Weights::parse("placement.pieces = 1000").is_err());
+        assert!(Weights::parse(&(weights.to_string() + "moving.unknown = 3\n")).is_err());
+        assert_eq!("Missing weight placement.mobility", Weights::parse("placement.pieces = 1000").unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_position_line_round_trip() {
+        let line = "WEEEEEEEEEEWEEEEEEEEEEEB S 3 0.5";
+        let position = LabelledPosition::parse_line(line).unwrap();
+        assert_eq!(line, position.to_line());
+
+        assert!(LabelledPosition::parse_line("WEEE S 3 0.5").is_err());
+        assert!(LabelledPosition::parse_line("WEEEEEEEEEEWEEEEEEEEEEEB S 3 1.5").is_err());
+    }
+
+    #[test]
+    fn test_tuning_does_not_increase_error() {
+        let options = SelfPlayOptions {
+            games: 4,
+            depth: 0,
+            max_plies: 60,
+            ..SelfPlayOptions::default()
+        };
+        let positions = generate_self_play(&options, &MuehleEvaluator::default());
+        assert!(!positions.is_empty());
+
+        let initial = Weights::default();
+        let scaling = fit_scaling(&positions, &initial);
+        let tuned = tune_weights(&positions, &initial, scaling, &TuningOptions { iterations: 50, learning_rate: 1.0 });
+
+        assert_eq!(initial.moving.pieces, tuned.moving.pieces);
+        assert!(mean_squared_error(&positions, &tuned, scaling) <= mean_squared_error(&positions, &initial, scaling) + 1e-9);
+    }
+}
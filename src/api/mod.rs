@@ -0,0 +1,189 @@
+//! What the interfaces to other programs share: the state of a game as plain data, checked moves and the
+//! moves of the engines. The HTTP API in `server`, the JavaScript bindings in [`wasm`] and the Python
+//! module in [`python`] are built on it.
+
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm-api")]
+pub mod wasm;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::action::Action;
+use crate::ai::analysis::player_name;
+use crate::ai::engine::{EngineKind, EngineMove, MctsEngine};
+use crate::ai::evaluation::MuehleEvaluator;
+use crate::ai::mcts::mcts;
+use crate::ai::random::Rng;
+use crate::ai::skill::Skill;
+use crate::ai::{choose_move, RootPosition, SearchLimits};
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::core::logic::compute_checked_step;
+use crate::core::position::{decode_positions, encode_positions};
+use crate::core::utils::get_number_of_tokens;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Pieces {
+    pub white: u8,
+    pub black: u8,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GameView {
+    pub id: u64,
+    /// 24 of `W`, `B` and `E`
+    pub board: String,
+    /// Side to move, in the `take` state the side which closed the mill
+    pub player: String,
+    pub state: String,
+    pub winner: Option<String>,
+    pub plies: usize,
+    pub in_hand: Pieces,
+    pub pieces: Pieces,
+    pub moves: Vec<String>,
+}
+impl GameView {
+    pub fn of(id: u64, game: &Game) -> GameView {
+        let setup_pieces_left = game.get_setup_pieces_left();
+        let moves = game.get_history().windows(2)
+            .filter_map(|turns| RootPosition::of_turn(&turns[0]).action_to(&turns[1]))
+            .map(|action| action.to_string())
+            .collect();
+
+        GameView {
+            id,
+            board: encode_positions(game.get_board()),
+            player: player_name(game.get_player_turn()).to_lowercase(),
+            state: game.get_state().to_str().to_lowercase(),
+            winner: Some(game.get_winner())
+                .filter(|_| game.get_state() == State::Win)
+                .map(|winner| player_name(winner).to_lowercase()),
+            plies: game.get_step_counter(),
+            in_hand: Pieces { white: setup_pieces_left / 2, black: setup_pieces_left - setup_pieces_left / 2 },
+            pieces: Pieces {
+                white: get_number_of_tokens(game.get_board(), 0b11),
+                black: get_number_of_tokens(game.get_board(), 0b10),
+            },
+            moves,
+        }
+    }
+
+    /// The game at this state, without the moves which led to it
+    pub fn to_game(&self) -> Result<Game, FieldError> {
+        if self.board.len() != 24 || self.board.chars().any(|token| !"WBE".contains(token)) {
+            return Err(FieldError::new(format!("Invalid board \"{}\", expected 24 of W, B and E", self.board)));
+        }
+        let player = match self.player.as_str() {
+            "white" => 0b11,
+            "black" => 0b10,
+            player => return Err(FieldError::new(format!("Unknown player \"{}\", expected white or black", player)))
+        };
+        let state = match self.state.as_str() {
+            "setup" => State::Setup,
+            "normal" => State::Normal,
+            "take" => State::Take,
+            "win" => State::Win,
+            state => return Err(FieldError::new(format!("Unknown state \"{}\", expected setup, normal, take or win", state)))
+        };
+        // the game only knows the pieces in hand of both players together, white places first
+        let Pieces { white, black } = self.in_hand;
+        let setup_pieces_left = white.checked_add(black)
+            .filter(|_| black == white || black == white + 1)
+            .ok_or(FieldError::new(format!("{} pieces in hand of white and {} of black cannot be left after alternate placing", white, black)))?;
+        Game::from_state(decode_positions(self.board.clone()), player, state, setup_pieces_left, self.plies)
+    }
+}
+
+/// The move `engine` plays at `skill`, full strength searches for `time_limit` seconds
+pub fn engine_move(root: &RootPosition, engine: EngineKind, skill: Skill, time_limit: f64) -> Option<EngineMove> {
+    let evaluator = MuehleEvaluator::default();
+    match engine {
+        EngineKind::Minimax => {
+            let limits = SearchLimits { time_limit, ..SearchLimits::default() };
+            let chosen = choose_move(root, skill, &evaluator, limits, &mut Rng::from_time())?;
+            Some(EngineMove::of_chosen(root, &chosen))
+        },
+        EngineKind::Mcts => {
+            let mut options = MctsEngine::options(skill);
+            if skill.is_full_strength() {
+                options.time_limit = Some(time_limit);
+            }
+            let result = mcts(root, &options, &evaluator)?;
+            Some(EngineMove::of_mcts(root, &result))
+        }
+    }
+}
+
+/// Plays `action` if it is legal, an illegal action leaves the game as it was
+pub fn play_checked(game: &mut Game, action: Action) -> Result<(), FieldError> {
+    // a game set up with a pending capture has no turn to go back to before the capture is played
+    let Some(ply) = game.get_history().len().checked_sub(1) else {
+        let (board, player, state) = (game.get_board(), game.get_player_turn(), game.get_state());
+        let (setup_pieces_left, plies) = (game.get_setup_pieces_left(), game.get_step_counter());
+        return compute_checked_step(action, game).or_else(|err| {
+            *game = Game::from_state(board, player, state, setup_pieces_left, plies)?;
+            Err(err)
+        });
+    };
+    compute_checked_step(action, game).or_else(|err| {
+        game.rewind(ply)?;
+        Err(err)
+    })
+}
+
+/// Legal actions of the side to move
+pub fn legal_actions(game: &Game) -> Vec<Action> {
+    if game.get_state() == State::Win {
+        return Vec::new();
+    }
+    let root = RootPosition::of(game);
+    root.forward_boards().into_iter()
+        .map(|forward_board| root.action(forward_board))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{play_checked, GameView, Pieces};
+    use crate::core::enums::State;
+    use crate::core::game::Game;
+
+    fn view(player: &str, state: &str, white: u8, black: u8) -> GameView {
+        let mut view = GameView::of(0, &Game::new());
+        view.board = "EWEBBEEWEEEEEEEEEEEEEEEE".to_string();
+        view.player = player.to_string();
+        view.state = state.to_string();
+        view.in_hand = Pieces { white, black };
+        view
+    }
+
+    #[test]
+    fn test_invalid_views_are_rejected() {
+        assert!(view("white", "setup", 7, 7).to_game().is_ok());
+        assert!(view("white", "", 7, 7).to_game().is_err());
+        assert!(view("white", "Setup", 7, 7).to_game().is_err());
+        assert!(view("red", "setup", 7, 7).to_game().is_err());
+        assert!(view("white", "setup", 200, 100).to_game().is_err());
+        assert!(view("white", "setup", 200, 200).to_game().is_err());
+        // 14 in hand are always split 7 and 7
+        assert!(view("white", "setup", 6, 8).to_game().is_err());
+    }
+
+    #[test]
+    fn test_play_after_a_pending_capture() {
+        let mut game = view("white", "take", 7, 7).to_game().unwrap();
+        assert!(game.get_history().is_empty());
+
+        // the position 0 is empty, so the capture is illegal and the game stays as it was
+        assert!(play_checked(&mut game, "1x0".parse().unwrap()).is_err());
+        assert_eq!(State::Take, game.get_state());
+        assert_eq!(GameView::of(0, &game).board, "EWEBBEEWEEEEEEEEEEEEEEEE");
+
+        play_checked(&mut game, "1x3".parse().unwrap()).unwrap();
+        assert_eq!("EWEEBEEWEEEEEEEEEEEEEEEE", GameView::of(0, &game).board);
+        play_checked(&mut game, "0".parse().unwrap()).unwrap();
+        assert_eq!("BWEEBEEWEEEEEEEEEEEEEEEE", GameView::of(0, &game).board);
+    }
+}
@@ -0,0 +1,369 @@
+//! Python module of the rules and the engine, built with the `python` feature, e.g. by `maturin develop`.
+//! Boards are numpy tensors from the view of the side to move, and [`GameBatch`] steps many games at once
+//! on all cores without holding the GIL.
+//!
+//! ```python
+//! import muehle_agent
+//!
+//! batch = muehle_agent.GameBatch(256, seed=7)
+//! while not batch.done().all():
+//!     boards = batch.tensors()          # float32, shape (256, 3, 24)
+//!     moves = batch.compute_steps(level=5)
+//! ```
+
+use numpy::ndarray::{Array1, Array2, Array3};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArray3};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::ai::action::Action;
+use crate::ai::analysis::player_name;
+use crate::ai::evaluation::MuehleEvaluator;
+use crate::ai::random::Rng;
+use crate::ai::skill::{Skill, MAX_SKILL_LEVEL, MIN_SKILL_LEVEL};
+use crate::ai::{choose_move, RootPosition, SearchLimits, MOVE_TIME};
+use crate::api::{legal_actions, play_checked, GameView};
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::core::logic::compute_computer_step;
+use crate::core::position::{encode_positions, get_token_at, negate_token};
+
+/// Planes of a board tensor: pieces of the side to move, pieces of the opponent and empty points
+const PLANES: usize = 3;
+
+/// Board of `game` as `PLANES` planes of the 24 points, from the view of the side to move
+fn board_planes(game: &Game) -> [f32; PLANES * 24] {
+    let own = game.get_player_turn();
+    let mut planes = [0.0; PLANES * 24];
+    for position in 0..24 {
+        let plane = match get_token_at(game.get_board(), position) {
+            token if token == own => 0,
+            token if token == negate_token(own) => 1,
+            _ => 2
+        };
+        planes[plane * 24 + position] = 1.0;
+    }
+    planes
+}
+
+/// Pieces left to place of the side to move and of the opponent
+fn in_hand(game: &Game) -> [u8; 2] {
+    let in_hand = GameView::of(0, game).in_hand;
+    if game.get_player_turn() == 0b11 { [in_hand.white, in_hand.black] } else { [in_hand.black, in_hand.white] }
+}
+
+/// 1 for white, -1 for black and 0 for nobody
+fn sign(token: Option<u8>) -> i8 {
+    match token {
+        Some(0b11) => 1,
+        Some(0b10) => -1,
+        _ => 0
+    }
+}
+
+fn winner(game: &Game) -> Option<u8> {
+    Some(game.get_winner()).filter(|_| game.get_state() == State::Win)
+}
+
+fn skill(level: u8) -> PyResult<Skill> {
+    if !(MIN_SKILL_LEVEL..=MAX_SKILL_LEVEL).contains(&level) {
+        return Err(PyValueError::new_err(format!("The level has to be from {} to {}", MIN_SKILL_LEVEL, MAX_SKILL_LEVEL)));
+    }
+    Ok(Skill::new(level))
+}
+
+fn value_error(err: FieldError) -> PyErr {
+    PyValueError::new_err(err.message)
+}
+
+/// The move of the engine in `game` with a search on one thread, as the games of a batch are searched in parallel
+fn engine_action(game: &Game, skill: Skill, time_limit: f64, rng: &mut Rng) -> Option<Action> {
+    let root = RootPosition::of(game);
+    let limits = SearchLimits { time_limit, threads: 1 };
+    let chosen = choose_move(&root, skill, &MuehleEvaluator::default(), limits, rng)?;
+    Some(root.action(chosen.forward_board))
+}
+
+/// One game, `core::game::Game` for Python
+#[pyclass(name = "Game")]
+pub struct PyGame {
+    game: Game,
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    pub fn new() -> PyGame {
+        PyGame { game: Game::new() }
+    }
+
+    /// The game at the position string `board` of 24 `W`, `B` and `E` with `player` to move
+    #[staticmethod]
+    #[pyo3(signature = (board, player, in_hand_white = 0, in_hand_black = 0))]
+    pub fn from_position(board: &str, player: &str, in_hand_white: u8, in_hand_black: u8) -> PyResult<PyGame> {
+        if player != "white" && player != "black" {
+            return Err(PyValueError::new_err(format!("Unknown player \"{}\", expected white or black", player)));
+        }
+        let mut view = GameView::of(0, &Game::new());
+        view.board = board.to_string();
+        view.player = player.to_string();
+        view.state = if in_hand_white > 0 || in_hand_black > 0 { "setup" } else { "normal" }.to_string();
+        view.in_hand.white = in_hand_white;
+        view.in_hand.black = in_hand_black;
+        Ok(PyGame { game: view.to_game().map_err(value_error)? })
+    }
+
+    /// Position string of the board, 24 of `W`, `B` and `E`
+    pub fn position(&self) -> String {
+        encode_positions(self.game.get_board())
+    }
+
+    /// Side to move, in the take state the side which closed the mill
+    #[getter]
+    pub fn player(&self) -> String {
+        player_name(self.game.get_player_turn()).to_lowercase()
+    }
+
+    #[getter]
+    pub fn state(&self) -> String {
+        self.game.get_state().to_str().to_lowercase()
+    }
+
+    #[getter]
+    pub fn winner(&self) -> Option<String> {
+        winner(&self.game).map(|winner| player_name(winner).to_lowercase())
+    }
+
+    #[getter]
+    pub fn plies(&self) -> usize {
+        self.game.get_step_counter()
+    }
+
+    /// Legal moves of the side to move, e.g. `["3-4", "3-4x10"]`
+    pub fn legal_moves(&self) -> Vec<String> {
+        legal_actions(&self.game).iter().map(|action| action.to_string()).collect()
+    }
+
+    /// Plays `action`, e.g. `3-4x10`, an illegal move raises a `ValueError` and leaves the game as it was
+    pub fn play(&mut self, action: &str) -> PyResult<()> {
+        if self.game.get_state() == State::Win {
+            return Err(PyValueError::new_err("The game is already over"));
+        }
+        play_checked(&mut self.game, action.parse().map_err(value_error)?).map_err(value_error)
+    }
+
+    /// The move of `ai::compute_step` without playing it, at full strength the search takes `time_limit` seconds
+    #[pyo3(signature = (level = MAX_SKILL_LEVEL, time_limit = MOVE_TIME))]
+    pub fn compute_step(&self, py: Python<'_>, level: u8, time_limit: f64) -> PyResult<Option<String>> {
+        let skill = skill(level)?;
+        if self.game.get_state() == State::Win {
+            return Ok(None);
+        }
+        let action = py.detach(|| engine_action(&self.game, skill, time_limit, &mut Rng::from_time()));
+        Ok(action.map(|action| action.to_string()))
+    }
+
+    /// Board as float32 tensor of shape (3, 24): pieces of the side to move, of the opponent and empty points
+    pub fn tensor<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        Array2::from_shape_vec((PLANES, 24), board_planes(&self.game).to_vec())
+            .expect("the planes have this shape")
+            .into_pyarray(py)
+    }
+
+    /// Pieces left to place of the side to move and of the opponent
+    pub fn in_hand(&self) -> (u8, u8) {
+        let [own, other] = in_hand(&self.game);
+        (own, other)
+    }
+}
+
+impl Default for PyGame {
+    fn default() -> Self {
+        PyGame::new()
+    }
+}
+
+/// Many games which are stepped together, index `i` of every array belongs to game `i`
+#[pyclass]
+pub struct GameBatch {
+    games: Vec<Game>,
+    rng: Rng,
+}
+
+impl GameBatch {
+    /// Plays the move of each game, none skips it. If one is illegal all games stay as they were.
+    pub fn step_games(&mut self, actions: &[Option<Action>]) -> Result<(), FieldError> {
+        if actions.len() != self.games.len() {
+            return Err(FieldError::new(format!("Expected {} moves, got {}", self.games.len(), actions.len())));
+        }
+        let plies: Vec<usize> = self.games.iter().map(|game| game.get_history().len() - 1).collect();
+        let results: Vec<Result<(), FieldError>> = self.games.par_iter_mut().zip(actions)
+            .map(|(game, action)| match action {
+                None => Ok(()),
+                Some(_) if game.get_state() == State::Win => Err(FieldError::new("The game is already over".to_string())),
+                Some(action) => play_checked(game, *action)
+            })
+            .collect();
+
+        let Some((index, err)) = results.into_iter().enumerate().find_map(|(index, result)| Some(index).zip(result.err())) else {
+            return Ok(())
+        };
+        for (game, ply) in self.games.iter_mut().zip(plies) {
+            game.rewind(ply)?;
+        }
+        Err(FieldError::new(format!("Game {}: {}", index, err.message)))
+    }
+
+    /// Lets the engine play a move in every game which is not over, each game is searched on its own core
+    pub fn compute_engine_steps(&mut self, skill: Skill, time_limit: f64) -> Vec<Option<Action>> {
+        let seeds: Vec<u64> = self.games.iter().map(|_| self.rng.next_u64()).collect();
+        self.games.par_iter_mut().zip(seeds)
+            .map(|(game, seed)| {
+                if game.get_state() == State::Win {
+                    return None;
+                }
+                let action = engine_action(game, skill, time_limit, &mut Rng::new(seed))?;
+                compute_computer_step(action, game).ok()?;
+                Some(action)
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl GameBatch {
+    /// `size` new games, the engine moves are random with the same `seed`
+    #[new]
+    #[pyo3(signature = (size, seed = None))]
+    pub fn new(size: usize, seed: Option<u64>) -> GameBatch {
+        GameBatch {
+            games: (0..size).map(|_| Game::new()).collect(),
+            rng: seed.map_or_else(Rng::from_time, Rng::new),
+        }
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Starts game `index` again, without an index all games
+    #[pyo3(signature = (index = None))]
+    pub fn reset(&mut self, index: Option<usize>) -> PyResult<()> {
+        match index {
+            None => self.games.iter_mut().for_each(|game| *game = Game::new()),
+            Some(index) => *self.games.get_mut(index).ok_or(PyValueError::new_err(format!("There is no game {}", index)))? = Game::new()
+        }
+        Ok(())
+    }
+
+    pub fn game(&self, index: usize) -> PyResult<PyGame> {
+        let game = self.games.get(index).ok_or(PyValueError::new_err(format!("There is no game {}", index)))?;
+        let view = GameView::of(0, game);
+        Ok(PyGame { game: view.to_game().map_err(value_error)? })
+    }
+
+    pub fn positions(&self) -> Vec<String> {
+        self.games.iter().map(|game| encode_positions(game.get_board())).collect()
+    }
+
+    pub fn legal_moves(&self, py: Python<'_>) -> Vec<Vec<String>> {
+        py.detach(|| self.games.par_iter()
+            .map(|game| legal_actions(game).iter().map(|action| action.to_string()).collect())
+            .collect())
+    }
+
+    /// Boards as float32 tensor of shape (n, 3, 24), the planes of [`PyGame::tensor`]
+    pub fn tensors<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray3<f32>> {
+        let data: Vec<f32> = self.games.iter().flat_map(board_planes).collect();
+        Array3::from_shape_vec((self.games.len(), PLANES, 24), data)
+            .expect("the planes have this shape")
+            .into_pyarray(py)
+    }
+
+    /// Pieces left to place as uint8 array of shape (n, 2), of the side to move and of the opponent
+    pub fn in_hand<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<u8>> {
+        let data: Vec<u8> = self.games.iter().flat_map(in_hand).collect();
+        Array2::from_shape_vec((self.games.len(), 2), data)
+            .expect("two counts per game")
+            .into_pyarray(py)
+    }
+
+    /// Side to move, 1 for white and -1 for black
+    pub fn players<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<i8>> {
+        Array1::from_iter(self.games.iter().map(|game| sign(Some(game.get_player_turn())))).into_pyarray(py)
+    }
+
+    /// 1 if white has won, -1 if black has won and 0 while the game goes on
+    pub fn winners<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<i8>> {
+        Array1::from_iter(self.games.iter().map(|game| sign(winner(game)))).into_pyarray(py)
+    }
+
+    pub fn done<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<bool>> {
+        Array1::from_iter(self.games.iter().map(|game| game.get_state() == State::Win)).into_pyarray(py)
+    }
+
+    pub fn plies<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u64>> {
+        Array1::from_iter(self.games.iter().map(|game| game.get_step_counter() as u64)).into_pyarray(py)
+    }
+
+    /// Plays one move in each game, `None` skips a game. An illegal move raises a `ValueError` and leaves
+    /// all games as they were.
+    pub fn step(&mut self, py: Python<'_>, moves: Vec<Option<String>>) -> PyResult<()> {
+        let actions = moves.iter()
+            .map(|text| text.as_deref().map(str::parse).transpose())
+            .collect::<Result<Vec<Option<Action>>, FieldError>>()
+            .map_err(value_error)?;
+        py.detach(|| self.step_games(&actions)).map_err(value_error)
+    }
+
+    /// Lets the engine play in every game which is not over and returns the moves, `None` for finished games
+    #[pyo3(signature = (level = MAX_SKILL_LEVEL, time_limit = MOVE_TIME))]
+    pub fn compute_steps(&mut self, py: Python<'_>, level: u8, time_limit: f64) -> PyResult<Vec<Option<String>>> {
+        let skill = skill(level)?;
+        let actions = py.detach(|| self.compute_engine_steps(skill, time_limit));
+        Ok(actions.into_iter().map(|action| action.map(|action| action.to_string())).collect())
+    }
+}
+
+#[pymodule]
+fn muehle_agent(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<GameBatch>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::skill::Skill;
+    use crate::api::legal_actions;
+    use crate::api::python::{board_planes, GameBatch};
+    use crate::core::game::Game;
+
+    #[test]
+    fn test_board_planes_of_the_side_to_move() {
+        let mut batch = GameBatch::new(2, Some(1));
+        batch.step_games(&[Some("3".parse().unwrap()), None]).unwrap();
+
+        // black to move sees the white piece on 3 as the piece of its opponent
+        let planes = board_planes(&batch.games[0]);
+        assert_eq!(1.0, planes[24 + 3]);
+        assert_eq!(23.0, planes[48..].iter().sum::<f32>());
+        assert_eq!(24.0, board_planes(&Game::new())[48..].iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_batch_step() {
+        let mut batch = GameBatch::new(3, Some(1));
+        let moves = batch.compute_engine_steps(Skill::new(3), 0.05);
+        assert!(moves.iter().all(|action| action.is_some()));
+        assert!(batch.games.iter().all(|game| game.get_step_counter() == 1));
+
+        // the illegal move in game 2 takes back the legal one in game 0
+        let legal = legal_actions(&batch.games[0])[0];
+        let err = batch.step_games(&[Some(legal), None, moves[2]]).unwrap_err();
+        assert!(err.message.starts_with("Game 2"));
+        assert!(batch.games.iter().all(|game| game.get_step_counter() == 1));
+        assert!(batch.step_games(&[None]).is_err());
+    }
+}
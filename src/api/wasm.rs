@@ -0,0 +1,199 @@
+//! JavaScript bindings of the rules and the engines, built with the `wasm-api` feature. `wasm-bindgen` writes
+//! the TypeScript typings of [`MuehleGame`] next to the generated glue, the types below are added to them.
+//!
+//! ```js
+//! const game = new MuehleGame();
+//! game.play("3");
+//! const reply = game.bestMove(0.5);   // e.g. "7", searched for at most half a second
+//! game.play(reply);
+//! game.view().board;                  // e.g. "EEEWEEEBEEEEEEEEEEEEEEEE"
+//! ```
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::ai::engine::EngineKind;
+use crate::ai::skill::{Skill, MAX_SKILL_LEVEL, MIN_SKILL_LEVEL};
+use crate::ai::RootPosition;
+use crate::api::{engine_move, legal_actions, play_checked, GameView, Pieces};
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::core::position::encode_positions;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+export type Player = "white" | "black";
+
+export interface Pieces {
+    white: number;
+    black: number;
+}
+
+/** State of a game, the same as the one of the HTTP API */
+export interface GameView {
+    /** Always 0 outside of the HTTP API */
+    id: number;
+    /** 24 of W, B and E */
+    board: string;
+    /** Side to move, in the take state the side which closed the mill */
+    player: Player;
+    state: "setup" | "normal" | "take" | "win";
+    winner: Player | null;
+    plies: number;
+    in_hand: Pieces;
+    pieces: Pieces;
+    /** Moves which led to this state, e.g. "3-4x10" */
+    moves: string[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "GameView")]
+    pub type JsGameView;
+}
+
+/// A game with the moves played in it
+#[wasm_bindgen]
+pub struct MuehleGame {
+    game: Game,
+}
+
+#[wasm_bindgen]
+impl MuehleGame {
+    /// A new game, white places the first piece
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> MuehleGame {
+        MuehleGame { game: Game::new() }
+    }
+
+    /// The game at the position string `board` of 24 `W`, `B` and `E` with `player` to move, in the setup
+    /// phase while pieces are left in hand
+    #[wasm_bindgen(js_name = fromPosition)]
+    pub fn from_position(board: &str, player: &str, in_hand_white: u8, in_hand_black: u8) -> Result<MuehleGame, JsError> {
+        let view = GameView {
+            id: 0,
+            board: board.to_string(),
+            player: player.to_string(),
+            state: if in_hand_white > 0 || in_hand_black > 0 { "setup" } else { "normal" }.to_string(),
+            winner: None,
+            plies: 0,
+            in_hand: Pieces { white: in_hand_white, black: in_hand_black },
+            // counted from the board by the game
+            pieces: Pieces { white: 0, black: 0 },
+            moves: Vec::new(),
+        };
+        Ok(MuehleGame { game: view.to_game().map_err(js_error)? })
+    }
+
+    /// The game at a state of [`MuehleGame::view`] or of the HTTP API, without the moves which led to it
+    #[wasm_bindgen(js_name = fromView)]
+    pub fn from_view(view: JsGameView) -> Result<MuehleGame, JsError> {
+        let view: GameView = serde_wasm_bindgen::from_value(view.into())?;
+        Ok(MuehleGame { game: view.to_game().map_err(js_error)? })
+    }
+
+    pub fn view(&self) -> JsGameView {
+        let view = GameView::of(0, &self.game);
+        view.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .expect("views are always serializable")
+            .unchecked_into()
+    }
+
+    /// Position string of the board, 24 of `W`, `B` and `E`
+    pub fn position(&self) -> String {
+        encode_positions(self.game.get_board())
+    }
+
+    /// Legal moves of the side to move, e.g. `["3-4", "3-4x10"]`
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        legal_actions(&self.game).iter().map(|action| action.to_string()).collect()
+    }
+
+    /// Plays `action`, e.g. `3-4x10`, an illegal move throws and leaves the game as it was
+    pub fn play(&mut self, action: &str) -> Result<(), JsError> {
+        if self.game.get_state() == State::Win {
+            return Err(JsError::new("The game is already over"));
+        }
+        let action = action.parse().map_err(js_error)?;
+        play_checked(&mut self.game, action).map_err(js_error)
+    }
+
+    /// The move of the engine for the side to move without playing it, none once the game is over. At full
+    /// strength, the default `level` 20, it searches for `time_limit` seconds.
+    #[wasm_bindgen(js_name = bestMove)]
+    pub fn best_move(&self, time_limit: f64, level: Option<u8>) -> Result<Option<String>, JsError> {
+        let level = level.unwrap_or(MAX_SKILL_LEVEL);
+        if !(MIN_SKILL_LEVEL..=MAX_SKILL_LEVEL).contains(&level) {
+            return Err(JsError::new(&format!("The level has to be from {} to {}", MIN_SKILL_LEVEL, MAX_SKILL_LEVEL)));
+        }
+        if time_limit.is_nan() || time_limit <= 0.0 {
+            return Err(JsError::new("The time limit has to be more than 0 seconds"));
+        }
+        if self.game.get_state() == State::Win {
+            return Ok(None);
+        }
+        let engine_move = engine_move(&RootPosition::of(&self.game), EngineKind::Minimax, Skill::new(level), time_limit);
+        Ok(engine_move.map(|engine_move| engine_move.action.to_string()))
+    }
+}
+
+impl Default for MuehleGame {
+    fn default() -> Self {
+        MuehleGame::new()
+    }
+}
+
+fn js_error(err: FieldError) -> JsError {
+    JsError::new(&err.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::wasm::MuehleGame;
+
+    #[test]
+    fn test_play_against_the_engine() {
+        let mut game = MuehleGame::new();
+        assert_eq!(24, game.legal_moves().len());
+        game.play("3").unwrap();
+        assert_eq!("EEEWEEEEEEEEEEEEEEEEEEEE", game.position());
+
+        let reply = game.best_move(0.1, Some(5)).unwrap().unwrap();
+        assert!(game.legal_moves().contains(&reply));
+        game.play(&reply).unwrap();
+        assert_eq!(22, game.legal_moves().len());
+    }
+}
+
+/// Runs the bindings in a headless browser: `wasm-pack test --headless --firefox -- --lib --no-default-features --features wasm-api`
+#[cfg(all(test, target_arch = "wasm32"))]
+mod browser_tests {
+    use wasm_bindgen_test::*;
+
+    use crate::api::wasm::MuehleGame;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_illegal_moves_throw() {
+        let mut game = MuehleGame::new();
+        assert!(game.play("25").is_err());
+        game.play("0").unwrap();
+        assert!(game.play("0").is_err());
+        assert_eq!(23, game.legal_moves().len());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_view_round_trip() {
+        let board = "EWEBBEEWEEEEEEEEEEEEEEEE";
+        let game = MuehleGame::from_position(board, "white", 7, 7).unwrap();
+        let copy = MuehleGame::from_view(game.view()).unwrap();
+        assert_eq!(board, copy.position());
+        assert!(copy.legal_moves().contains(&"0x3".to_string()));
+        assert!(MuehleGame::from_position(board, "red", 7, 7).is_err());
+        assert!(game.best_move(0.05, None).unwrap().is_some());
+    }
+}
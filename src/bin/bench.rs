@@ -0,0 +1,58 @@
+use muehle_agent::ai::benchmark::{run_benchmark, run_timed_benchmark, BenchmarkResult};
+use muehle_agent::ai::evaluation::MuehleEvaluator;
+use muehle_agent::ai::minimax::default_threads;
+use muehle_agent::ai::ordering::MoveOrdering;
+
+fn print_table(title: &str, results: &[Vec<BenchmarkResult>], value: impl Fn(&BenchmarkResult) -> u64) {
+    println!("{}", title);
+    println!("{:<14}{:>14}{:>14}{:>14}{:>14}{:>12}", "position", "none", "static eval", "no killers", "heuristic", "saved");
+
+    let mut totals = [0u64; 4];
+    for index in 0..results[0].len() {
+        let values: Vec<u64> = results.iter().map(|result| value(&result[index])).collect();
+        for (total, value) in totals.iter_mut().zip(values.iter()) {
+            *total += value;
+        }
+        println!("{:<14}{:>14}{:>14}{:>14}{:>14}{:>11.1}%",
+            results[0][index].name, values[0], values[1], values[2], values[3], 100.0 - 100.0 * values[3] as f64 / values[1] as f64);
+    }
+    println!("{:<14}{:>14}{:>14}{:>14}{:>14}{:>11.1}%\n",
+        "total", totals[0], totals[1], totals[2], totals[3], 100.0 - 100.0 * totals[3] as f64 / totals[1] as f64);
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: bench [depth] | bench --time <seconds> [--threads <n>]");
+    std::process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let evaluator = MuehleEvaluator::default();
+
+    if args.first().map(String::as_str) == Some("--time") {
+        let time_limit: f64 = args.get(1).and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+        let threads: usize = match args.get(2).map(String::as_str) {
+            Some("--threads") => args.get(3).and_then(|value| value.parse().ok()).unwrap_or_else(|| usage()),
+            Some(_) => usage(),
+            None => default_threads()
+        };
+        println!("Depth reached in {} seconds with {} threads\n", time_limit, threads);
+        for (name, depth) in run_timed_benchmark(time_limit, threads, &evaluator) {
+            println!("{:<14}{:>6}", name, depth);
+        }
+        return
+    }
+
+    let depth: usize = match args.first() {
+        Some(value) => value.parse().unwrap_or_else(|_| usage()),
+        None => 4
+    };
+
+    let orderings = [MoveOrdering::None, MoveOrdering::StaticEvaluation, MoveOrdering::History, MoveOrdering::Heuristic];
+    let results: Vec<Vec<BenchmarkResult>> = orderings.iter().map(|ordering| run_benchmark(depth, *ordering, &evaluator)).collect();
+
+    println!("Iterative deepening up to depth {}\n", depth);
+    print_table("Nodes", &results, |result| result.nodes);
+    print_table("Static evaluations", &results, |result| result.evaluations);
+    print_table("Milliseconds", &results, |result| (result.seconds * 1000.0) as u64);
+}
@@ -0,0 +1,54 @@
+use std::process::exit;
+
+use muehle_agent::ai::book::{build_book, BookOptions};
+use muehle_agent::ai::evaluation::MuehleEvaluator;
+
+const USAGE: &str = "Usage: book [options]
+    --plies <n>       placements from the empty board which get an entry (default 6)
+    --depth <n>       search depth for scoring the placements (default 5)
+    --candidates <n>  candidates per position at most (default 3)
+    --margin <n>      leave out placements scoring worse than the best by more (default 150)
+    --output <file>   where the book is written (default src/ai/book.txt)";
+
+fn parse_arguments() -> Result<(BookOptions, String), String> {
+    let mut options = BookOptions::default();
+    let mut output = "src/ai/book.txt".to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+        let number = || value.parse::<usize>().map_err(|_| format!("Invalid number \"{}\" for {}", value, flag));
+
+        match flag.as_str() {
+            "--plies" => options.plies = number()?,
+            "--depth" => options.depth = number()?,
+            "--candidates" => options.candidates = number()?,
+            "--margin" => options.margin = number()? as isize,
+            "--output" => output = value.clone(),
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+    Ok((options, output))
+}
+
+fn main() {
+    let (options, output) = parse_arguments().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+
+    let book = build_book(&options, &MuehleEvaluator::default(), |positions| eprint!("\r{} positions", positions));
+    eprintln!();
+
+    let content = format!("# Opening book for the setup, see src/ai/book.rs for the format.\n\
+        # Generated by: cargo run --release --bin book -- --plies {} --depth {} --candidates {} --margin {}\n{}",
+        options.plies, options.depth, options.candidates, options.margin, book);
+    if let Err(error) = std::fs::write(&output, content) {
+        eprintln!("Could not write the book to {}: {}", output, error);
+        exit(1);
+    }
+    println!("{} positions written to {}", book.len(), output);
+}
@@ -0,0 +1,15 @@
+use std::io::stdin;
+use std::process::exit;
+
+use muehle_agent::ui::console::{parse_arguments, Console};
+
+fn main() {
+    let arguments = parse_arguments("console", std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+
+    println!("Mühle in the console, enter help for the commands\n");
+    let mut console = Console::new(arguments.white, arguments.black, arguments.max_plies);
+    console.run(stdin().lock());
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+use std::process::exit;
+
+use muehle_agent::ai::timer;
+use muehle_agent::ai::endgame::{EndgameDatabase, Outcome, Table, ENDGAME_DIRECTORY};
+
+const USAGE: &str = "Usage: endgame [options]
+    --pieces <n>      solve all endgames with 3 up to n pieces per side (default 3)
+    --output <dir>    where the tables are written (default endgame)";
+
+fn parse_arguments() -> Result<(usize, String), String> {
+    let (mut pieces, mut output) = (3, ENDGAME_DIRECTORY.to_string());
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+
+        match flag.as_str() {
+            "--pieces" => pieces = match value.parse::<usize>() {
+                Ok(pieces) if (3..=9).contains(&pieces) => pieces,
+                _ => return Err(format!("Invalid piece count \"{}\", expected 3 to 9", value))
+            },
+            "--output" => output = value,
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+    Ok((pieces, output))
+}
+
+fn print_summary(table: &Table, seconds: f64) {
+    let (mut wins, mut losses, mut draws, mut longest) = (0, 0, 0, 0);
+    for outcome in table.outcomes() {
+        match outcome {
+            Outcome::Win(distance) => { wins += 1; longest = longest.max(distance) },
+            Outcome::Loss(distance) => { losses += 1; longest = longest.max(distance) },
+            Outcome::Draw => draws += 1
+        }
+    }
+    println!("{}v{}: {} positions, {} wins, {} losses, {} draws, longest {} plies ({:.1}s)",
+        table.own, table.opponent, Table::size(table.own, table.opponent), wins, losses, draws, longest, seconds);
+}
+
+fn main() {
+    let (pieces, output) = parse_arguments().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+
+    let mut last_time = timer::time();
+    let database = EndgameDatabase::generate(pieces, |table| {
+        print_summary(table, timer::time() - last_time);
+        last_time = timer::time();
+    });
+
+    if let Err(error) = database.save(Path::new(&output)) {
+        eprintln!("Could not write the tables to {}: {}", output, error);
+        exit(1);
+    }
+    println!("Tables written to {}", output);
+}
@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use muehle_agent::ai::endgame::EndgameDatabase;
+use muehle_agent::ai::evaluation::MuehleEvaluator;
+use muehle_agent::ai::protocol::{go, new_search, parse_command, parse_position, Command, EngineOptions, GoLimits, ENGINE_AUTHOR, ENGINE_NAME};
+use muehle_agent::ai::RootPosition;
+
+fn send(line: String) {
+    let mut stdout = stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+/// Lines of stdin, the channel closes at the end of the input
+fn read_input() -> Receiver<String> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for line in stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break
+            }
+        }
+    });
+    receiver
+}
+
+/// Runs the search on another thread and keeps answering commands until it is done, returns false on quit.
+/// Other commands wait in `pending` until the search is over.
+fn search(input: &Receiver<String>, pending: &mut VecDeque<String>, evaluator: &MuehleEvaluator, root: &RootPosition, options: &EngineOptions, limits: &GoLimits) -> bool {
+    let search = new_search(evaluator, root, options, limits);
+    let mut running = true;
+
+    thread::scope(|scope| {
+        let handle = scope.spawn(|| send(go(&search, root, options, limits, &send)));
+
+        while !handle.is_finished() {
+            match input.recv_timeout(Duration::from_millis(10)) {
+                Ok(line) => match line.trim() {
+                    "stop" => { search.stop(); },
+                    "isready" if pending.is_empty() => send("readyok".to_string()),
+                    "quit" => {
+                        search.stop();
+                        running = false;
+                    },
+                    "" => {},
+                    _ => pending.push_back(line)
+                },
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => {
+                    search.stop();
+                    running = false;
+                    break
+                }
+            }
+        }
+    });
+    running
+}
+
+fn main() {
+    let input = read_input();
+    let evaluator = MuehleEvaluator::default();
+    let mut options = EngineOptions::default();
+    let mut root = parse_position(&["startpos"]).expect("the start position is valid");
+    let mut pending = VecDeque::new();
+
+    while let Some(line) = pending.pop_front().or_else(|| input.recv().ok()) {
+        if line.trim().is_empty() {
+            continue
+        }
+        match parse_command(&line) {
+            Ok(Command::Mei) => {
+                send(format!("id name {}", ENGINE_NAME));
+                send(format!("id author {}", ENGINE_AUTHOR));
+                EngineOptions::describe().into_iter().for_each(send);
+                send("meiok".to_string());
+            },
+            Ok(Command::IsReady) => {
+                EndgameDatabase::shared();
+                send("readyok".to_string());
+            },
+            Ok(Command::NewGame) => root = parse_position(&["startpos"]).expect("the start position is valid"),
+            Ok(Command::SetOption { name, value }) => if let Err(err) = options.set(&name, &value) {
+                send(format!("info string {}", err.message));
+            },
+            Ok(Command::Position(position)) => root = position,
+            Ok(Command::Go(limits)) => if let Err(err) = options.check(&limits) {
+                send(format!("info string {}", err.message));
+            } else if !search(&input, &mut pending, &evaluator, &root, &options, &limits) {
+                break
+            },
+            Ok(Command::Stop) => {},
+            Ok(Command::Quit) => break,
+            Err(err) => send(format!("info string {}", err.message))
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::process::exit;
+
+use muehle_agent::ai::external::{run_match, EngineConfig, EngineProtocol, MatchOptions, TimeControl};
+use muehle_agent::ai::tournament::MatchStats;
+
+const USAGE: &str = "Usage: match --first <command> --second <command> [options]
+    --first <command>          first engine, e.g. \"target/release/engine\"
+    --second <command>         second engine
+    --first-protocol <name>    mei (default) or legacy (P|M W|B <board>)
+    --second-protocol <name>   same for the second engine
+    --games <n>                number of games, each opening is played with both colors (default 10)
+    --time <base>+<inc>        seconds on each clock and added after every move (default 60+1)
+    --opening-plies <n>        random plies at the start of each opening (default 4)
+    --max-plies <n>            longer games are a draw (default 200)
+    --seed <n>                 seed for the openings (default 1)
+    --output <file>            file every game is appended to (default matches.txt)";
+
+struct Arguments {
+    first: EngineConfig,
+    second: EngineConfig,
+    options: MatchOptions,
+}
+
+fn parse_arguments() -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        first: EngineConfig { command: String::new(), protocol: EngineProtocol::Mei },
+        second: EngineConfig { command: String::new(), protocol: EngineProtocol::Mei },
+        options: MatchOptions { output: Some("matches.txt".to_string()), ..MatchOptions::default() },
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+        let number = || value.parse::<usize>().map_err(|_| format!("Invalid number \"{}\" for {}", value, flag));
+
+        match flag.as_str() {
+            "--first" => arguments.first.command = value,
+            "--second" => arguments.second.command = value,
+            "--first-protocol" => arguments.first.protocol = EngineProtocol::parse(&value).map_err(|err| err.message)?,
+            "--second-protocol" => arguments.second.protocol = EngineProtocol::parse(&value).map_err(|err| err.message)?,
+            "--games" => arguments.options.games = number()?,
+            "--time" => arguments.options.time_control = TimeControl::parse(&value).map_err(|err| err.message)?,
+            "--opening-plies" => arguments.options.opening_plies = number()?,
+            "--max-plies" => arguments.options.max_plies = number()?,
+            "--seed" => arguments.options.seed = number()? as u64,
+            "--output" => arguments.options.output = Some(value),
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+
+    if arguments.first.command.is_empty() || arguments.second.command.is_empty() {
+        return Err(format!("Both engines are needed\n{}", USAGE));
+    }
+    Ok(arguments)
+}
+
+fn print_stats(stats: &MatchStats) {
+    let (elo, error) = stats.elo();
+    println!("Games {:>5}  +{} ={} -{}  score {:.3}  elo {:+.1} +/- {:.1}",
+        stats.games(), stats.wins, stats.draws, stats.losses, stats.score(), elo, error);
+}
+
+fn main() {
+    let arguments = parse_arguments().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+
+    let result = run_match(&arguments.first, &arguments.second, &arguments.options, |round, record, stats| {
+        let comment = if record.comment.is_empty() { String::new() } else { format!(", {}", record.comment) };
+        println!("Game {:>4}: {} - {}  {} ({}{})", round, record.white, record.black, record.result(), record.termination, comment);
+        print_stats(stats);
+    });
+
+    match result {
+        Ok(stats) => {
+            println!("\nResult of the first engine");
+            print_stats(&stats);
+        },
+        Err(err) => {
+            eprintln!("{}", err.message);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::process::exit;
+
+#[cfg(not(target_arch = "wasm32"))]
+use muehle_agent::server::rooms::RoomServer;
+#[cfg(not(target_arch = "wasm32"))]
+use muehle_agent::server::ApiServer;
+
+const USAGE: &str = "Usage: server [options]
+    --address <host:port>    where to listen for the HTTP API (default 127.0.0.1:8080)
+    --rooms <host:port>      where to listen for WebSocket rooms (default 127.0.0.1:8081)";
+
+struct Arguments {
+    address: String,
+    rooms: String,
+}
+
+fn parse_arguments() -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        address: "127.0.0.1:8080".to_string(),
+        rooms: "127.0.0.1:8081".to_string(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+
+        match flag.as_str() {
+            "--address" => arguments.address = value,
+            "--rooms" => arguments.rooms = value,
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+    Ok(arguments)
+}
+
+fn main() {
+    let arguments = parse_arguments().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let server = ApiServer::bind(&arguments.address).unwrap_or_else(|err| {
+            eprintln!("{}", err.message);
+            exit(1);
+        });
+        let room_server = RoomServer::bind(&arguments.rooms).unwrap_or_else(|err| {
+            eprintln!("{}", err.message);
+            exit(1);
+        });
+        if let (Some(address), Some(rooms)) = (server.address(), room_server.address()) {
+            println!("Listening on http://{} and ws://{}", address, rooms);
+        }
+        std::thread::scope(|scope| {
+            scope.spawn(|| room_server.run());
+            server.run();
+        });
+    }
+    #[cfg(target_arch = "wasm32")]
+    eprintln!("The server cannot listen on {} in the browser", arguments.address);
+}
@@ -0,0 +1,95 @@
+use std::process::exit;
+
+use muehle_agent::ai::tournament::{run_tournament, MatchStats, PlayerConfig, Sprt, SprtDecision, TournamentOptions};
+
+const USAGE: &str = "Usage: tournament [options]
+    --first <config>         first player, e.g. engine=minimax,level=20,time=0.1,weights=tuned.txt
+    --second <config>        second player, same keys (engine minimax|mcts, level 1-20 or elo, time, weights)
+    --games <n>              number of games, each opening is played with both colors (default 100)
+    --opening-plies <n>      random plies at the start of each opening (default 4)
+    --max-plies <n>          longer games are a draw (default 200)
+    --seed <n>               seed for the openings (default 1)
+    --sprt <elo0>,<elo1>     stop early once the first player is shown to be elo0 or elo1 stronger
+    --alpha <p>              false positive rate of the test (default 0.05)
+    --beta <p>               false negative rate of the test (default 0.05)";
+
+struct Arguments {
+    first: PlayerConfig,
+    second: PlayerConfig,
+    options: TournamentOptions,
+}
+
+fn parse_arguments() -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        first: PlayerConfig::default(),
+        second: PlayerConfig::default(),
+        options: TournamentOptions::default(),
+    };
+    let mut sprt = Sprt::default();
+    let mut use_sprt = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+        let number = || value.parse::<usize>().map_err(|_| format!("Invalid number \"{}\" for {}", value, flag));
+        let probability = || value.parse::<f64>().ok().filter(|p| *p > 0.0 && *p < 0.5).ok_or(format!("Invalid probability \"{}\" for {}", value, flag));
+
+        match flag.as_str() {
+            "--first" => arguments.first = PlayerConfig::parse(&value).map_err(|err| err.message)?,
+            "--second" => arguments.second = PlayerConfig::parse(&value).map_err(|err| err.message)?,
+            "--games" => arguments.options.games = number()?,
+            "--opening-plies" => arguments.options.opening_plies = number()?,
+            "--max-plies" => arguments.options.max_plies = number()?,
+            "--seed" => arguments.options.seed = number()? as u64,
+            "--sprt" => {
+                let bounds = value.split_once(',')
+                    .and_then(|(elo0, elo1)| Some((elo0.parse().ok()?, elo1.parse().ok()?)))
+                    .ok_or(format!("Invalid bounds \"{}\" for --sprt", value))?;
+                (sprt.elo0, sprt.elo1) = bounds;
+                use_sprt = true;
+            },
+            "--alpha" => sprt.alpha = probability()?,
+            "--beta" => sprt.beta = probability()?,
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+
+    if use_sprt {
+        arguments.options.sprt = Some(sprt);
+    }
+    Ok(arguments)
+}
+
+fn print_stats(stats: &MatchStats, sprt: Option<Sprt>) {
+    let (elo, error) = stats.elo();
+    print!("Games {:>5}  +{} ={} -{}  score {:.3}  elo {:+.1} +/- {:.1}",
+        stats.games(), stats.wins, stats.draws, stats.losses, stats.score(), elo, error);
+    if let Some(sprt) = sprt {
+        let (lower, upper) = sprt.bounds();
+        print!("  llr {:.2} [{:.2}, {:.2}]", stats.log_likelihood_ratio(sprt.elo0, sprt.elo1), lower, upper);
+    }
+    println!();
+}
+
+fn main() {
+    let arguments = parse_arguments().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+    let sprt = arguments.options.sprt;
+
+    let stats = run_tournament(&arguments.options, &arguments.first, &arguments.second, |stats| print_stats(stats, sprt));
+
+    println!("\nResult of the first player");
+    print_stats(&stats, sprt);
+    if let Some(sprt) = sprt {
+        match sprt.decide(&stats) {
+            SprtDecision::AcceptH0 => println!("SPRT: H0 accepted (elo {})", sprt.elo0),
+            SprtDecision::AcceptH1 => println!("SPRT: H1 accepted (elo {})", sprt.elo1),
+            SprtDecision::Continue => println!("SPRT: no decision after {} games", stats.games())
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::process::exit;
+
+use muehle_agent::ui::console::{parse_arguments, Console};
+#[cfg(not(target_arch = "wasm32"))]
+use muehle_agent::ui::terminal::Tui;
+
+fn main() {
+    let arguments = parse_arguments("tui", std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+    let console = Console::new(arguments.white, arguments.black, arguments.max_plies);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(err) = Tui::new(console).run() {
+        eprintln!("Terminal error: {}", err);
+        exit(1);
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = console;
+        eprintln!("The terminal version needs a terminal");
+    }
+}
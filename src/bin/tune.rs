@@ -0,0 +1,123 @@
+use std::process::exit;
+
+use muehle_agent::ai::evaluation::{MuehleEvaluator, Weights};
+use muehle_agent::ai::tuning::{fit_scaling, generate_self_play, label_by_search, load_positions, mean_squared_error, save_positions, tune_weights, SelfPlayOptions, TuningOptions};
+
+const USAGE: &str = "Usage: tune [options]
+    --positions <file>       load labelled positions instead of playing games
+    --save-positions <file>  write the labelled positions to a file
+    --games <n>              number of self-play games (default 200)
+    --depth <n>              search depth of the self-play games (default 2)
+    --seed <n>               seed for the random openings (default 1)
+    --label outcome|search   label by game result or by a deeper search (default outcome)
+    --label-depth <n>        search depth for --label search (default 4)
+    --weights <file>         start from these weights instead of the embedded ones
+    --iterations <n>         optimiser iterations (default 2000)
+    --output <file>          where the tuned weights are written (default src/ai/weights.txt)";
+
+struct Arguments {
+    positions: Option<String>,
+    save_positions: Option<String>,
+    self_play: SelfPlayOptions,
+    label_by_search: bool,
+    label_depth: usize,
+    weights: Option<String>,
+    tuning: TuningOptions,
+    output: String,
+}
+
+fn parse_arguments() -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        positions: None,
+        save_positions: None,
+        self_play: SelfPlayOptions::default(),
+        label_by_search: false,
+        label_depth: 4,
+        weights: None,
+        tuning: TuningOptions::default(),
+        output: "src/ai/weights.txt".to_string(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+        let number = || value.parse::<usize>().map_err(|_| format!("Invalid number \"{}\" for {}", value, flag));
+
+        match flag.as_str() {
+            "--positions" => arguments.positions = Some(value.clone()),
+            "--save-positions" => arguments.save_positions = Some(value.clone()),
+            "--games" => arguments.self_play.games = number()?,
+            "--depth" => arguments.self_play.depth = number()?,
+            "--seed" => arguments.self_play.seed = number()? as u64,
+            "--label" => arguments.label_by_search = match value.as_str() {
+                "outcome" => false,
+                "search" => true,
+                _ => return Err(format!("Unknown label \"{}\"", value))
+            },
+            "--label-depth" => arguments.label_depth = number()?,
+            "--weights" => arguments.weights = Some(value.clone()),
+            "--iterations" => arguments.tuning.iterations = number()?,
+            "--output" => arguments.output = value.clone(),
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+    Ok(arguments)
+}
+
+fn main() {
+    let arguments = parse_arguments().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        exit(2);
+    });
+
+    let initial = match &arguments.weights {
+        Some(path) => Weights::from_file(path).unwrap_or_else(|err| {
+            eprintln!("{}", err.message);
+            exit(1);
+        }),
+        None => Weights::default(),
+    };
+    let evaluator = MuehleEvaluator::new(initial);
+
+    let mut positions = match &arguments.positions {
+        Some(path) => load_positions(path).unwrap_or_else(|err| {
+            eprintln!("{}", err.message);
+            exit(1);
+        }),
+        None => {
+            println!("Playing {} self-play games at depth {}", arguments.self_play.games, arguments.self_play.depth);
+            generate_self_play(&arguments.self_play, &evaluator)
+        }
+    };
+    if arguments.label_by_search {
+        let scaling = fit_scaling(&positions, &initial);
+        println!("Labelling {} positions with depth {} searches", positions.len(), arguments.label_depth);
+        label_by_search(&mut positions, arguments.label_depth, scaling, &evaluator);
+    }
+    if let Some(path) = &arguments.save_positions {
+        if let Err(err) = save_positions(path, &positions) {
+            eprintln!("Cannot write positions to {}: {}", path, err);
+        }
+    }
+    println!("{} labelled positions", positions.len());
+
+    let scaling = fit_scaling(&positions, &initial);
+    println!("Scaling {:e}, error before {:.6}", scaling, mean_squared_error(&positions, &initial, scaling));
+
+    let tuned = tune_weights(&positions, &initial, scaling, &arguments.tuning);
+    println!("Error after {:.6}", mean_squared_error(&positions, &tuned, scaling));
+
+    let command: Vec<String> = std::env::args().skip(1).collect();
+    let content = format!(
+        "# Evaluation weights of the MuehleEvaluator, embedded at build time.\n# Generated with `cargo run --release --bin tune -- {}`\n# starting from the previous weights, {} iterations at learning rate {} on {} positions, error {:.6} -> {:.6}.\n\n{}",
+        command.join(" "), arguments.tuning.iterations, arguments.tuning.learning_rate, positions.len(),
+        mean_squared_error(&positions, &initial, scaling), mean_squared_error(&positions, &tuned, scaling), tuned);
+    if let Err(err) = std::fs::write(&arguments.output, content) {
+        eprintln!("Cannot write weights to {}: {}", arguments.output, err);
+        exit(1);
+    }
+    println!("Weights written to {}", arguments.output);
+}
@@ -1,10 +1,19 @@
-use crate::core::enums::{CarryPiece, State};
+use crate::core::enums::{CarryPiece, FieldError, State};
 use crate::core::position::{get_token_at, set_token_at};
-use crate::core::utils::{extract_black_move_count_from_board, extract_white_move_count_from_board, insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+use crate::core::utils::{extract_black_move_count_from_board, extract_white_move_count_from_board, get_number_of_tokens, insert_number_of_possible_moves_to_board, insert_token_count_to_board};
 
 use super::utils::{extract_black_token_count_from_board, extract_white_token_count_from_board};
 
 
+/// The position at the start of a turn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Turn {
+    pub board: u64,
+    pub player_turn: u8,
+    pub setup_pieces_left: u8,
+    pub step_counter: usize,
+}
+
 pub struct Game {
     board: u64,
     player_turn: u8,
@@ -12,6 +21,10 @@ pub struct Game {
     
     state: State,
     setup_pieces_left: u8,
+    /// Finished moves of both players, a move with a capture counts once
+    step_counter: usize,
+    /// Start of every turn so far, the last one is the current turn
+    history: Vec<Turn>,
 }
 
 
@@ -29,7 +42,47 @@ impl Game {
             carry_piece: Option::None,
             state: State::Setup,
             setup_pieces_left: 18,
+            step_counter: 0,
+            history: Vec::new(),
+        }.with_history()
+    }
+
+    /// A game at any point, e.g. to let the computer take over. A header of `board` is ignored, during the setup
+    /// `setup_pieces_left` counts the pieces both players still have in hand and in the `Take` state
+    /// `player_turn` is the player who has just closed a mill.
+    pub fn from_state(board: u64, player_turn: u8, state: State, setup_pieces_left: u8, step_counter: usize) -> Result<Game, FieldError> {
+        if player_turn != 0b11 && player_turn != 0b10 {
+            return Err(FieldError::new(format!("Invalid player turn {:#b}", player_turn)));
+        }
+        if setup_pieces_left > 18 {
+            return Err(FieldError::new(format!("Only 18 pieces can be placed, not {}", setup_pieces_left)));
+        }
+        if (state == State::Setup) != (setup_pieces_left > 0) && state != State::Take {
+            return Err(FieldError::new(format!("{} state with {} pieces left to place", state.to_str(), setup_pieces_left)));
+        }
+
+        let board = board & ((1 << 48) - 1);
+        let mut game = Game {
+            board: insert_number_of_possible_moves_to_board(insert_token_count_to_board(board)),
+            player_turn,
+            carry_piece: Option::None,
+            state,
+            setup_pieces_left,
+            step_counter,
+            history: Vec::new(),
+        };
+        // white places first, so black has the larger half of the pieces in hand
+        let placed_white = 9 - setup_pieces_left / 2;
+        let placed_black = 9 - (setup_pieces_left / 2 + setup_pieces_left % 2);
+        if get_number_of_tokens(board, 0b11) > placed_white || get_number_of_tokens(board, 0b10) > placed_black {
+            return Err(FieldError::new(format!("Too many pieces on the board with {} left to place", setup_pieces_left)));
+        }
+        game.update_state(Option::None);
+        // a pending capture is only the second half of a turn
+        if state == State::Take {
+            return Ok(game)
         }
+        Ok(game.with_history())
     }
     
     pub fn new_example_board() -> Game {
@@ -40,9 +93,39 @@ impl Game {
             carry_piece: Option::None,
             state: State::Normal,
             setup_pieces_left: 0,
+            step_counter: 18,
+            history: Vec::new(),
+        }.with_history()
+    }
+
+    fn with_history(mut self) -> Game {
+        self.history.push(self.current_turn());
+        self
+    }
+
+    fn current_turn(&self) -> Turn {
+        Turn {
+            board: self.board,
+            player_turn: self.player_turn,
+            setup_pieces_left: self.setup_pieces_left,
+            step_counter: self.step_counter,
         }
     }
 
+    pub fn get_history(&self) -> &[Turn] {
+        &self.history
+    }
+
+    /// Goes back to the start of turn `ply` of the history and forgets the turns after it
+    pub fn rewind(&mut self, ply: usize) -> Result<(), FieldError> {
+        let turn = *self.history.get(ply).ok_or(FieldError::new(format!("There is no turn {}", ply)))?;
+        let state = if turn.setup_pieces_left > 0 { State::Setup } else { State::Normal };
+        let mut game = Game::from_state(turn.board, turn.player_turn, state, turn.setup_pieces_left, turn.step_counter)?;
+        game.history = self.history[..=ply].to_vec();
+        *self = game;
+        Ok(())
+    }
+
     pub fn get_board(&self) -> u64 {
         self.board
     }
@@ -56,6 +139,7 @@ impl Game {
     }
 
     pub fn next_player_turn(&mut self) {
+        self.step_counter += 1;
         if self.player_turn == 0b11 {
             self.player_turn = 0b10;
         } else if self.player_turn == 0b10 {
@@ -63,6 +147,7 @@ impl Game {
         } else {
             panic!("invalid player turn detected");
         }
+        self.history.push(self.current_turn());
     }
 
     pub fn get_carry_piece(&self) -> Option<CarryPiece> {
@@ -123,6 +208,10 @@ impl Game {
         }
     }
 
+    pub fn get_step_counter(&self) -> usize {
+        self.step_counter
+    }
+
     pub fn get_setup_pieces_left(&self) -> u8 {
         self.setup_pieces_left
     }
@@ -139,10 +228,6 @@ impl Game {
         let setup_pieces_left = self.setup_pieces_left - 1;
         self.setup_pieces_left = setup_pieces_left;
 
-        let current_player_count: u8 = setup_pieces_left / 2;
-        let next_player_count: u8 = setup_pieces_left / 2 + (setup_pieces_left % 2);
-        println!("White can place {current_player_count} more pieces and Black can place {next_player_count} more pieces");
-
         if setup_pieces_left == 0 {
             self.update_state(Option::Some(State::Normal));
         }
@@ -172,4 +257,26 @@ impl Game {
         new_board = insert_token_count_to_board(new_board);
         self.set_board(new_board);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::enums::State;
+    use crate::core::game::Game;
+    use crate::core::position::decode_positions;
+    use crate::core::utils::{extract_black_token_count_from_board, extract_white_token_count_from_board, insert_number_of_possible_moves_to_board, insert_token_count_to_board};
+
+    #[test]
+    fn test_from_state_with_header() {
+        // boards of a game carry the token and move counts above the 48 position bits, stale ones are replaced
+        let board = insert_number_of_possible_moves_to_board(insert_token_count_to_board(decode_positions("EWEBBEEWEEEEEEEEEEEEEEEE".to_string())));
+        let stale = board | (0b111111 << 48);
+
+        let game = Game::from_state(stale, 0b11, State::Setup, 14, 4).unwrap();
+        assert_eq!(board, game.get_board());
+        assert_eq!(2, extract_white_token_count_from_board(game.get_board()));
+        assert_eq!(2, extract_black_token_count_from_board(game.get_board()));
+        assert_eq!(State::Setup, game.get_state());
+        assert!(Game::from_state(stale, 0b11, State::Setup, 16, 4).is_err());
+    }
+}
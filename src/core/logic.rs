@@ -138,7 +138,6 @@ pub fn compute_button_up(position: usize, game: &mut Game) -> Result<(), FieldEr
                     
                     if is_mill_closing(board_before, game.get_board(), carry_piece_color) {
                         game.update_state(Option::Some(State::Take));
-                        println!("{} has created a mill", carry_piece_color);
                     } else {
                         game.next_player_turn();
                     }
@@ -161,12 +160,15 @@ pub fn compute_button_up(position: usize, game: &mut Game) -> Result<(), FieldEr
 pub fn compute_computer_step(action: Action, game: &mut Game) -> Result<(), FieldError> {
     let (start_position, end_position, beatable_position): (Option<usize>, usize, Option<usize>) = action.into();
 
-    if start_position.is_some() && compute_button_down(start_position.unwrap(), game).is_err() {
-        panic!("Invalid AI start position: {}", start_position.unwrap());
-    }
+    // after a mill the computer only has to take a piece
+    if game.get_state() != State::Take {
+        if start_position.is_some() && compute_button_down(start_position.unwrap(), game).is_err() {
+            panic!("Invalid AI start position: {}", start_position.unwrap());
+        }
 
-    if compute_button_up(end_position, game).is_err() {
-        panic!("Invalid AI end position: {}", end_position);
+        if compute_button_up(end_position, game).is_err() {
+            panic!("Invalid AI end position: {}", end_position);
+        }
     }
     
     if let Some(beatable_position) = beatable_position {
@@ -178,6 +180,47 @@ pub fn compute_computer_step(action: Action, game: &mut Game) -> Result<(), Fiel
     Ok(())
 }
 
+/// Like [`compute_computer_step`], but for actions which may be illegal, e.g. from another program.
+/// An illegal action is an error and can leave the game half way through the action.
+pub fn compute_checked_step(action: Action, game: &mut Game) -> Result<(), FieldError> {
+    let (start_position, end_position, beatable_position): (Option<usize>, usize, Option<usize>) = action.into();
+    let positions_mask: u64 = (1 << 48) - 1;
+
+    match game.get_state() {
+        State::Win => return Err(FieldError::new("The game is already over".to_string())),
+        State::Take => {},
+        state => {
+            match (state, start_position) {
+                (State::Setup, Some(_)) => return Err(FieldError::new("Pieces can only be placed during the setup".to_string())),
+                (State::Normal, None) => return Err(FieldError::new("All pieces are placed, a piece has to be moved".to_string())),
+                (_, Some(start_position)) if compute_button_down(start_position, game).is_err() => {
+                    return Err(FieldError::new(format!("Cannot move the piece on {}", start_position)));
+                },
+                _ => {}
+            }
+
+            let board_before: u64 = game.get_board();
+            compute_button_up(end_position, game)?;
+            if game.get_board() & positions_mask == board_before & positions_mask {
+                return Err(FieldError::new(format!("Cannot move to {}", end_position)));
+            }
+        }
+    }
+
+    match (game.get_state(), beatable_position) {
+        (State::Take, None) => Err(FieldError::new("The move closes a mill, a piece has to be taken".to_string())),
+        (State::Take, Some(beatable_position)) => {
+            compute_button_up(beatable_position, game)?;
+            if game.get_state() == State::Take {
+                return Err(FieldError::new(format!("Cannot take the piece on {}", beatable_position)));
+            }
+            Ok(())
+        },
+        (_, Some(_)) => Err(FieldError::new("The move closes no mill, no piece can be taken".to_string())),
+        (_, None) => Ok(())
+    }
+}
+
 
 pub fn compute_bottom_panel(old_white: Difficulty, old_black: Difficulty, index: usize) -> (Difficulty, Difficulty) {
     let toggle = |old: Difficulty, new: Difficulty| {
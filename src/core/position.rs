@@ -13,6 +13,8 @@ pub const BLACK_TOKEN_FIRST_POSITION: u64 =          0b0000000000000001000000000
 pub const WHITE_POSSIBLE_MOVES_FIRST_POSITION: u64 = 0b0000100000000000000000000000000000000000000000000000000000000000;
 pub const BLACK_POSSIBLE_MOVES_FIRST_POSITION: u64 = 0b0000000001000000000000000000000000000000000000000000000000000000;
 
+use crate::core::utils::MILLS;
+
 #[allow(dead_code)]
 pub fn encode_positions(board: u64) -> String {
     let mut encoded_positions = String::new();
@@ -82,22 +84,56 @@ pub fn create_token_iter<'a>(board: u64) -> impl Iterator<Item = u8> + 'a {
     })
 }
 
+/// Column and row of each position in the 7 x 7 grid of the drawn board
+pub const BOARD_GRID: [(usize, usize); 24] = [
+    (3, 0), (6, 0), (6, 3), (6, 6), (3, 6), (0, 6), (0, 3), (0, 0),
+    (3, 1), (5, 1), (5, 3), (5, 5), (3, 5), (1, 5), (1, 3), (1, 1),
+    (3, 2), (4, 2), (4, 3), (4, 4), (3, 4), (2, 4), (2, 3), (2, 2),
+];
+
+/// Draws the board with its lines, `cell` gives the text of every position and all texts need the same width
+pub fn draw_board(cell: impl Fn(usize) -> String) -> String {
+    let cells: Vec<String> = (0..24).map(cell).collect();
+    let width = cells.iter().map(|text| text.chars().count()).max().unwrap_or(1);
+    let step = width + 3;
+    let mut canvas = vec![vec![' '; 6 * step + width]; 13];
+
+    let at = |position: usize| {
+        let (column, row) = BOARD_GRID[position];
+        (column * step, row * 2)
+    };
+    for (position, text) in cells.iter().enumerate() {
+        let (x, y) = at(position);
+        for (offset, char) in text.chars().enumerate() {
+            canvas[y][x + offset] = char;
+        }
+    }
+    // every mill lies on one line of the board, its middle position is connected to both ends
+    for mill in MILLS {
+        for (first, second) in [(mill[0], mill[1]), (mill[1], mill[2])] {
+            let ((x1, y1), (x2, y2)) = (at(first), at(second));
+            if y1 == y2 {
+                (x1.min(x2) + width..x1.max(x2)).for_each(|x| canvas[y1][x] = '-');
+            } else {
+                (y1.min(y2) + 1..y1.max(y2)).for_each(|y| canvas[y][x1 + width / 2] = '|');
+            }
+        }
+    }
+
+    canvas.iter()
+        .map(|line| line.iter().collect::<String>().trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 #[allow(dead_code)]
 pub fn print_board(board: u64) {
-    let board_vec = create_token_iter(board).map(|token| if token == 0b00 { "E" } else if token == 0b10 { "B" } else { "W" }).collect::<Vec<&str>>();
-    println!("{}------------{}------------{}   ", board_vec[7], board_vec[0], board_vec[1]);
-    println!("|            |            |      ");
-    println!("|   {}--------{}--------{}   |   ", board_vec[15], board_vec[8], board_vec[9]);
-    println!("|   |        |        |   |      ");
-    println!("|   |   {}----{}----{}   |   |   ", board_vec[23], board_vec[16], board_vec[17]);
-    println!("|   |   |         |   |   |      ");
-    println!("{}---{}---{}         {}---{}---{}", board_vec[6], board_vec[14], board_vec[22], board_vec[18], board_vec[10], board_vec[2]);
-    println!("|   |   |         |   |   |      ");
-    println!("|   |   {}----{}----{}   |   |   ", board_vec[21], board_vec[20], board_vec[19]);
-    println!("|   |        |        |   |      ");
-    println!("|   {}--------{}--------{}   |   ", board_vec[13], board_vec[12], board_vec[11]);
-    println!("|            |            |      ");
-    println!("{}------------{}------------{}   ", board_vec[5], board_vec[4], board_vec[3]);
+    let tokens: Vec<u8> = create_token_iter(board).collect();
+    println!("{}", draw_board(|position| match tokens[position] {
+        0b11 => "W",
+        0b10 => "B",
+        _ => "E"
+    }.to_string()));
 }
 
 #[cfg(test)]
@@ -232,4 +268,19 @@ pub mod tests {
             assert_eq!(expected_positions[23 - index], position);
         }
     }
+
+    #[test]
+    fn test_draw_board() {
+        use crate::core::position::draw_board;
+        let drawing = draw_board(|position| format!("{:>2}", position));
+        let lines: Vec<&str> = drawing.lines().collect();
+
+        assert_eq!(13, lines.len());
+        assert_eq!(" 7------------- 0------------- 1", lines[0]);
+        assert_eq!(" |   15-------- 8-------- 9    |", lines[2]);
+        assert_eq!(" 6---14---22        18---10--- 2", lines[6]);
+
+        let drawing = draw_board(|position| if position == 7 { "W".to_string() } else { "E".to_string() });
+        assert!(drawing.starts_with("W-----------E-----------E\n|           |           |\n"));
+    }
 }
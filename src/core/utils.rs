@@ -94,6 +94,26 @@ pub const NEIGHBORS: [[usize; 4]; 24] = [
     [16, 22, 24, 24],
 ];
 
+// same order as POSSIBLE_MILLS_WHITE, but as board positions
+pub const MILLS: [[usize; 3]; 16] = [
+    [7, 0, 1],
+    [15, 8, 9],
+    [23, 16, 17],
+    [6, 14, 22],
+    [18, 10, 2],
+    [21, 20, 19],
+    [13, 12, 11],
+    [5, 4, 3],
+    [7, 6, 5],
+    [15, 14, 13],
+    [23, 22, 21],
+    [0, 8, 16],
+    [20, 12, 4],
+    [17, 18, 19],
+    [9, 10, 11],
+    [1, 2, 3],
+];
+
 use crate::ai::action::Action;
 use crate::core::position::{get_token_at, negate_token, BLACK_POSSIBLE_MOVES_FIRST_POSITION, WHITE_POSSIBLE_MOVES_FIRST_POSITION};
 use crate::ai::{Phase, PhaseType};
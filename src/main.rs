@@ -1,12 +1,21 @@
+#[cfg(feature = "ui")]
 pub mod ui;
 pub mod ai;
 pub mod core;
+pub mod api;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
 
+#[cfg(feature = "ui")]
 use good_web_game as ggez;
+#[cfg(feature = "ui")]
 use ggez::GameResult;
+#[cfg(feature = "ui")]
 use ggez::conf::Conf;
+#[cfg(feature = "ui")]
 use ui::Engine;
 
+#[cfg(feature = "ui")]
 #[allow(dead_code)]
 fn main() -> GameResult {
     let conf = Conf::default()
@@ -14,6 +23,16 @@ fn main() -> GameResult {
         .window_resizable(true)
         .window_title("Mühle KI | Purpurax".to_string());
 
-    ggez::start(conf, move |context, quad_ctx|
-        Box::new(Engine::new(context, quad_ctx).unwrap()))
+    #[cfg(not(target_arch = "wasm32"))]
+    let remote = ui::remote::parse_arguments(std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    });
+
+    ggez::start(conf, move |context, quad_ctx| {
+        let engine = Engine::new(context, quad_ctx).unwrap();
+        #[cfg(not(target_arch = "wasm32"))]
+        let engine = engine.with_remote(remote);
+        Box::new(engine)
+    })
 }
\ No newline at end of file
@@ -0,0 +1,311 @@
+//! JSON API over HTTP for the rules of `core` and the engines of `ai`, the games are held in memory.
+//!
+//! - `POST /games` starts a game and answers `201` with its state
+//! - `GET /games/<id>` state of a game
+//! - `GET /games/<id>/moves` legal moves of the side to move, e.g. `{"moves": ["3-4", "3-4x10"]}`
+//! - `POST /games/<id>/moves` with `{"move": "3-4x10"}` plays a move and answers with the new state
+//! - `POST /games/<id>/ai` lets an engine play, the body is optional: `{"engine": "minimax" | "mcts",
+//!   "level": 1 to 20, "difficulty": "easy" | "medium" | "hard", "time": <seconds at full strength>}`
+//! - `DELETE /games/<id>` forgets a game
+//!
+//! A state looks like `{"id": 1, "board": "<24 x W|B|E>", "player": "white", "state": "setup", "winner": null,
+//! "plies": 0, "in_hand": {"white": 9, "black": 9}, "pieces": {"white": 0, "black": 0}, "moves": []}`.
+//! Errors are answered with `400`, `404`, `409` or `503` and `{"error": "<message>"}`. A fixed number of workers
+//! answers the requests, games nobody asked about for a while are forgotten and a full server answers `503`.
+
+pub mod rooms;
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::ai::action::Action;
+use crate::ai::engine::{EngineKind, EngineMove};
+use crate::ai::skill::{Skill, MAX_SKILL_LEVEL, MIN_SKILL_LEVEL};
+use crate::ai::{RootPosition, MOVE_TIME};
+use crate::api::{engine_move, legal_actions, play_checked, GameView};
+use crate::core::enums::{Difficulty, FieldError, State};
+use crate::core::game::Game;
+use crate::core::logic::compute_computer_step;
+
+/// Longest request body which is read
+const MAX_BODY: u64 = 64 * 1024;
+/// Longest search an AI move may ask for
+const MAX_TIME: f64 = 30.0;
+/// Default of [`Games::max_games`]
+const MAX_GAMES: usize = 10_000;
+/// Default of [`Games::idle_game_timeout`]
+const IDLE_GAME_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// Default of [`Games::max_searches`]
+const MAX_SEARCHES: usize = 2;
+/// Default of [`ApiServer::workers`]
+const WORKERS: usize = 8;
+
+#[derive(Debug)]
+pub struct ServerError {
+    pub message: String,
+}
+impl ServerError {
+    pub fn new(message: String) -> ServerError {
+        ServerError { message }
+    }
+}
+
+/// A request which could not be answered, with the HTTP status to answer it with
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+impl ApiError {
+    pub fn new(status: u16, message: String) -> ApiError {
+        ApiError { status, message }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveRequest {
+    #[serde(rename = "move")]
+    action: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AiRequest {
+    engine: Option<String>,
+    level: Option<u8>,
+    difficulty: Option<String>,
+    time: Option<f64>,
+}
+impl AiRequest {
+    fn engine(&self) -> Result<EngineKind, ApiError> {
+        match self.engine.as_deref() {
+            None | Some("minimax") => Ok(EngineKind::Minimax),
+            Some("mcts") => Ok(EngineKind::Mcts),
+            Some(engine) => Err(ApiError::new(400, format!("Unknown engine \"{}\", expected minimax or mcts", engine)))
+        }
+    }
+
+    fn skill(&self) -> Result<Skill, ApiError> {
+        let difficulty = match self.difficulty.as_deref() {
+            None => None,
+            Some("easy") => Some(Difficulty::Easy),
+            Some("medium") => Some(Difficulty::Medium),
+            Some("hard") => Some(Difficulty::Hard),
+            Some(difficulty) => return Err(ApiError::new(400, format!("Unknown difficulty \"{}\", expected easy, medium or hard", difficulty)))
+        };
+        match (self.level, difficulty) {
+            (Some(_), Some(_)) => Err(ApiError::new(400, "Give either a level or a difficulty".to_string())),
+            (Some(level), None) if !(MIN_SKILL_LEVEL..=MAX_SKILL_LEVEL).contains(&level) => {
+                Err(ApiError::new(400, format!("The level has to be from {} to {}", MIN_SKILL_LEVEL, MAX_SKILL_LEVEL)))
+            },
+            (Some(level), None) => Ok(Skill::new(level)),
+            (None, Some(difficulty)) => Ok(Skill::from(difficulty)),
+            (None, None) => Ok(Skill::new(MAX_SKILL_LEVEL))
+        }
+    }
+
+    fn time_limit(&self) -> Result<f64, ApiError> {
+        match self.time {
+            None => Ok(MOVE_TIME),
+            Some(time) if time > 0.0 && time <= MAX_TIME => Ok(time),
+            Some(_) => Err(ApiError::new(400, format!("The time has to be more than 0 and at most {} seconds", MAX_TIME)))
+        }
+    }
+}
+
+struct StoredGame {
+    game: Game,
+    last_used: Instant,
+}
+
+/// The games of the API by their id
+pub struct Games {
+    games: Mutex<HashMap<u64, StoredGame>>,
+    last_id: AtomicU64,
+    searches: AtomicUsize,
+    /// Games held at once, no game can be started beyond
+    pub max_games: usize,
+    /// How long a game nobody asked about is kept
+    pub idle_game_timeout: Duration,
+    /// AI moves searched at once, the ones beyond are answered with `503`
+    pub max_searches: usize,
+}
+impl Default for Games {
+    fn default() -> Self {
+        Games {
+            games: Mutex::new(HashMap::new()),
+            last_id: AtomicU64::new(0),
+            searches: AtomicUsize::new(0),
+            max_games: MAX_GAMES,
+            idle_game_timeout: IDLE_GAME_TIMEOUT,
+            max_searches: MAX_SEARCHES,
+        }
+    }
+}
+impl Games {
+    pub fn create(&self) -> Result<GameView, ApiError> {
+        let mut games = self.games.lock().unwrap();
+        games.retain(|_, stored| stored.last_used.elapsed() < self.idle_game_timeout);
+        if games.len() >= self.max_games {
+            return Err(ApiError::new(503, "There are too many games, try again later".to_string()));
+        }
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let game = Game::new();
+        let view = GameView::of(id, &game);
+        games.insert(id, StoredGame { game, last_used: Instant::now() });
+        Ok(view)
+    }
+
+    fn with_game<T>(&self, id: u64, f: impl FnOnce(&mut Game) -> Result<T, ApiError>) -> Result<T, ApiError> {
+        let mut games = self.games.lock().unwrap();
+        let stored = games.get_mut(&id)
+            .filter(|stored| stored.last_used.elapsed() < self.idle_game_timeout)
+            .ok_or(ApiError::new(404, format!("There is no game {}", id)))?;
+        stored.last_used = Instant::now();
+        f(&mut stored.game)
+    }
+
+    pub fn view(&self, id: u64) -> Result<GameView, ApiError> {
+        self.with_game(id, |game| Ok(GameView::of(id, game)))
+    }
+
+    pub fn moves(&self, id: u64) -> Result<Vec<Action>, ApiError> {
+        self.with_game(id, |game| Ok(legal_actions(game)))
+    }
+
+    /// Plays `text` if it is a legal move, an illegal move leaves the game as it was
+    pub fn play(&self, id: u64, text: &str) -> Result<GameView, ApiError> {
+        let action: Action = text.parse().map_err(|err: FieldError| ApiError::new(400, err.message))?;
+        self.with_game(id, |game| {
+            if game.get_state() == State::Win {
+                return Err(ApiError::new(409, "The game is already over".to_string()));
+            }
+            play_checked(game, action).map_err(|err| ApiError::new(400, err.message))?;
+            Ok(GameView::of(id, game))
+        })
+    }
+
+    /// Lets an engine play the side to move. The search runs without holding the games, a move played
+    /// in the meantime makes its result useless.
+    pub fn ai_move(&self, id: u64, engine: EngineKind, skill: Skill, time_limit: f64) -> Result<(EngineMove, GameView), ApiError> {
+        let (root, ply) = self.with_game(id, |game| match game.get_state() {
+            State::Win => Err(ApiError::new(409, "The game is already over".to_string())),
+            _ => Ok((RootPosition::of(game), game.get_history().len()))
+        })?;
+
+        self.searches.fetch_update(Ordering::AcqRel, Ordering::Acquire, |searches| (searches < self.max_searches).then_some(searches + 1))
+            .map_err(|_| ApiError::new(503, "Too many AI moves are searched at the moment, try again later".to_string()))?;
+        let engine_move = engine_move(&root, engine, skill, time_limit);
+        self.searches.fetch_sub(1, Ordering::AcqRel);
+        let engine_move = engine_move.ok_or(ApiError::new(409, "There is no legal move".to_string()))?;
+
+        self.with_game(id, |game| {
+            if game.get_history().len() != ply {
+                return Err(ApiError::new(409, "The game has changed during the search".to_string()));
+            }
+            compute_computer_step(engine_move.action, game).map_err(|err| ApiError::new(500, err.message))?;
+            Ok(GameView::of(id, game))
+        }).map(|view| (engine_move, view))
+    }
+
+    pub fn remove(&self, id: u64) -> Result<(), ApiError> {
+        self.games.lock().unwrap().remove(&id)
+            .filter(|stored| stored.last_used.elapsed() < self.idle_game_timeout)
+            .map(|_| ())
+            .ok_or(ApiError::new(404, format!("There is no game {}", id)))
+    }
+
+    /// Answers a request with its status and JSON body
+    pub fn handle(&self, method: &Method, url: &str, body: &str) -> Result<(u16, Value), ApiError> {
+        let path = url.split('?').next().unwrap_or_default();
+        let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        match (method, parts.as_slice()) {
+            (Method::Post, ["games"]) => Ok((201, json!(self.create()?))),
+            (method, ["games", id, rest @ ..]) => {
+                let id: u64 = id.parse().map_err(|_| ApiError::new(404, format!("There is no game {}", id)))?;
+                match (method, rest) {
+                    (Method::Get, []) => Ok((200, json!(self.view(id)?))),
+                    (Method::Delete, []) => self.remove(id).map(|_| (200, json!({ "id": id }))),
+                    (Method::Get, ["moves"]) => {
+                        let moves: Vec<String> = self.moves(id)?.iter().map(|action| action.to_string()).collect();
+                        Ok((200, json!({ "moves": moves })))
+                    },
+                    (Method::Post, ["moves"]) => {
+                        let request: MoveRequest = parse_body(body)?;
+                        Ok((200, json!(self.play(id, &request.action)?)))
+                    },
+                    (Method::Post, ["ai"]) => {
+                        let request: AiRequest = if body.trim().is_empty() { AiRequest::default() } else { parse_body(body)? };
+                        let (engine_move, view) = self.ai_move(id, request.engine()?, request.skill()?, request.time_limit()?)?;
+                        Ok((200, json!({
+                            "move": engine_move.action.to_string(),
+                            "score": engine_move.score.map(|score| score.to_string()),
+                            "game": view,
+                        })))
+                    },
+                    _ => Err(ApiError::new(404, format!("No route for {} {}", method, path)))
+                }
+            },
+            _ => Err(ApiError::new(404, format!("No route for {} {}", method, path)))
+        }
+    }
+}
+
+fn parse_body<T: for<'a> Deserialize<'a>>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|err| ApiError::new(400, format!("Invalid request body: {}", err)))
+}
+
+pub struct ApiServer {
+    http: Server,
+    pub games: Games,
+    /// Threads which answer the requests, an AI move holds one of them while it searches
+    pub workers: usize,
+}
+impl ApiServer {
+    /// Listens on `address`, e.g. `127.0.0.1:8080`, port 0 picks a free port
+    pub fn bind(address: &str) -> Result<ApiServer, ServerError> {
+        let http = Server::http(address).map_err(|err| ServerError::new(format!("Cannot listen on {}: {}", address, err)))?;
+        Ok(ApiServer { http, games: Games::default(), workers: WORKERS })
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Answers requests on [`ApiServer::workers`] threads until the process ends
+    pub fn run(&self) {
+        thread::scope(|scope| {
+            for _ in 0..self.workers {
+                scope.spawn(|| {
+                    for request in self.http.incoming_requests() {
+                        self.respond(request);
+                    }
+                });
+            }
+        });
+    }
+
+    fn respond(&self, mut request: Request) {
+        let mut body = String::new();
+        let result = match request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+            Ok(_) => self.games.handle(request.method(), request.url(), &body),
+            Err(_) => Err(ApiError::new(400, "The request body is no UTF-8 text".to_string()))
+        };
+        let (status, value) = result.unwrap_or_else(|err| (err.status, json!({ "error": err.message })));
+
+        let content_type = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+        let response = Response::from_string(value.to_string()).with_status_code(status).with_header(content_type);
+        // the client may be gone already, there is nobody left to tell
+        let _ = request.respond(response);
+    }
+}
@@ -0,0 +1,446 @@
+//! Rooms for games between two humans over WebSocket, the server checks every move with `core`.
+//!
+//! Every message is a JSON object with a `type`. The client sends
+//! - `{"type": "create"}` to open a room and play white in it
+//! - `{"type": "join", "code": "KXMTR"}` to play black in the room with the join code
+//! - `{"type": "spectate", "code": "KXMTR"}` to watch it
+//! - `{"type": "rejoin", "code": "KXMTR", "token": "..."}` to take the seat of a lost connection again
+//! - `{"type": "move", "move": "3-4x10"}` to play on its own turn once both players are there
+//!
+//! The server answers a seat with `{"type": "joined", "code": ..., "color": "white" | "black" | null, "token": ...}`,
+//! sends `{"type": "state", "game": <state as in the HTTP API>, "white_connected": true, ...}` to everyone in the
+//! room after each change and `{"type": "error", "message": ...}` to a client whose message was rejected.
+//!
+//! A room which nobody is in any more is kept for a rejoin for ten minutes, a finished game is closed right away.
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tungstenite::{accept, Message};
+
+use crate::ai::random::Rng;
+use crate::api::{play_checked, GameView};
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::server::ServerError;
+
+/// How long a connection waits for a message of its client before it passes on the messages of the room
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Letters of join codes, without the ones which are easily mixed up
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+const CODE_LENGTH: usize = 5;
+/// Default of [`Rooms::empty_room_timeout`]
+const EMPTY_ROOM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Default of [`Rooms::max_rooms`]
+const MAX_ROOMS: usize = 1000;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ClientMessage {
+    Create,
+    Join { code: String },
+    Spectate { code: String },
+    Rejoin { code: String, token: String },
+    Move {
+        #[serde(rename = "move")]
+        action: String,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ServerMessage {
+    Joined { code: String, color: Option<String>, token: Option<String> },
+    State { game: GameView, white_connected: bool, black_connected: bool, spectators: usize },
+    Error { message: String },
+}
+impl ServerMessage {
+    pub fn to_text(&self) -> String {
+        serde_json::to_string(self).expect("messages are always serializable")
+    }
+}
+
+/// One client of the server, the room sends to it through `sender`
+pub struct Connection {
+    id: u64,
+    sender: Sender<String>,
+    /// Join code of its room and its color there, none for spectators
+    room: Option<(String, Option<u8>)>,
+}
+
+struct Seat {
+    token: String,
+    /// Id of the connection which plays the seat right now
+    connection: Option<(u64, Sender<String>)>,
+}
+
+struct Room {
+    id: u64,
+    game: Game,
+    /// White and black
+    seats: [Option<Seat>; 2],
+    spectators: Vec<(u64, Sender<String>)>,
+    /// When the last client left
+    emptied_at: Option<Instant>,
+}
+impl Room {
+    fn is_connected(&self, side: usize) -> bool {
+        self.seats[side].as_ref().is_some_and(|seat| seat.connection.is_some())
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.is_connected(0) && !self.is_connected(1) && self.spectators.is_empty()
+    }
+
+    /// Sends the state to everyone in the room and forgets the connections which are gone
+    fn broadcast(&mut self) {
+        let text = ServerMessage::State {
+            game: GameView::of(self.id, &self.game),
+            white_connected: self.is_connected(0),
+            black_connected: self.is_connected(1),
+            spectators: self.spectators.len(),
+        }.to_text();
+
+        for seat in self.seats.iter_mut().flatten() {
+            if seat.connection.as_ref().is_some_and(|(_, sender)| sender.send(text.clone()).is_err()) {
+                seat.connection = None;
+            }
+        }
+        self.spectators.retain(|(_, sender)| sender.send(text.clone()).is_ok());
+    }
+}
+
+fn side_of(color: u8) -> usize {
+    if color == 0b11 { 0 } else { 1 }
+}
+
+fn color_name(color: Option<u8>) -> Option<String> {
+    color.map(|color| if color == 0b11 { "white" } else { "black" }.to_string())
+}
+
+/// All rooms of a server by their join code
+pub struct Rooms {
+    rooms: Mutex<HashMap<String, Room>>,
+    rng: Mutex<Rng>,
+    last_id: AtomicU64,
+    /// Rooms open at once, no room can be created beyond
+    pub max_rooms: usize,
+    /// How long a room without clients waits for a rejoin
+    pub empty_room_timeout: Duration,
+}
+impl Default for Rooms {
+    fn default() -> Self {
+        Rooms {
+            rooms: Mutex::new(HashMap::new()),
+            rng: Mutex::new(Rng::from_time()),
+            last_id: AtomicU64::new(0),
+            max_rooms: MAX_ROOMS,
+            empty_room_timeout: EMPTY_ROOM_TIMEOUT,
+        }
+    }
+}
+impl Rooms {
+    pub fn connect(&self, sender: Sender<String>) -> Connection {
+        Connection { id: self.last_id.fetch_add(1, Ordering::Relaxed) + 1, sender, room: None }
+    }
+
+    fn random_code(&self, taken: &HashMap<String, Room>) -> String {
+        let mut rng = self.rng.lock().unwrap();
+        loop {
+            let code: String = (0..CODE_LENGTH).map(|_| CODE_LETTERS[rng.below(CODE_LETTERS.len())] as char).collect();
+            if !taken.contains_key(&code) {
+                return code
+            }
+        }
+    }
+
+    /// Closes the rooms which have been empty for longer than the timeout
+    fn expire(&self, rooms: &mut HashMap<String, Room>) {
+        rooms.retain(|_, room| !room.is_empty() || room.emptied_at.is_none_or(|emptied_at| emptied_at.elapsed() < self.empty_room_timeout));
+    }
+
+    fn random_token(&self) -> String {
+        let mut rng = self.rng.lock().unwrap();
+        format!("{:016x}{:016x}", rng.next_u64(), rng.next_u64())
+    }
+
+    /// Handles a message of `connection`, a rejected message is an error for its client only
+    pub fn handle(&self, connection: &mut Connection, message: ClientMessage) -> Result<(), FieldError> {
+        let is_joining = !matches!(message, ClientMessage::Move { .. });
+        if is_joining && connection.room.is_some() {
+            return Err(FieldError::new("This connection is in a room already".to_string()));
+        }
+
+        let mut rooms = self.rooms.lock().unwrap();
+        let seat_connection = Some((connection.id, connection.sender.clone()));
+        let (code, color, token) = match message {
+            ClientMessage::Create => {
+                self.expire(&mut rooms);
+                if rooms.len() >= self.max_rooms {
+                    return Err(FieldError::new("There are too many rooms open, try again later".to_string()));
+                }
+                let code = self.random_code(&rooms);
+                let token = self.random_token();
+                let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+                let white = Seat { token: token.clone(), connection: seat_connection };
+                rooms.insert(code.clone(), Room { id, game: Game::new(), seats: [Some(white), None], spectators: Vec::new(), emptied_at: None });
+                (code, Some(0b11), Some(token))
+            },
+            ClientMessage::Join { code } => {
+                let token = self.random_token();
+                let room = rooms.get_mut(&code).ok_or(FieldError::new(format!("There is no room {}", code)))?;
+                if room.seats[1].is_some() {
+                    return Err(FieldError::new(format!("Room {} is full, spectate it instead", code)));
+                }
+                room.seats[1] = Some(Seat { token: token.clone(), connection: seat_connection });
+                (code, Some(0b10), Some(token))
+            },
+            ClientMessage::Spectate { code } => {
+                let room = rooms.get_mut(&code).ok_or(FieldError::new(format!("There is no room {}", code)))?;
+                room.spectators.push((connection.id, connection.sender.clone()));
+                (code, None, None)
+            },
+            ClientMessage::Rejoin { code, token } => {
+                let room = rooms.get_mut(&code).ok_or(FieldError::new(format!("There is no room {}", code)))?;
+                let side = room.seats.iter()
+                    .position(|seat| seat.as_ref().is_some_and(|seat| seat.token == token))
+                    .ok_or(FieldError::new(format!("The token is not valid for room {}", code)))?;
+                room.seats[side].as_mut().expect("the seat is taken").connection = seat_connection;
+                (code, Some(if side == 0 { 0b11 } else { 0b10 }), Some(token))
+            },
+            ClientMessage::Move { action } => {
+                let Some((code, color)) = &connection.room else {
+                    return Err(FieldError::new("Create or join a room first".to_string()));
+                };
+                let room = rooms.get_mut(code).ok_or(FieldError::new(format!("There is no room {}", code)))?;
+                let Some(color) = color else {
+                    return Err(FieldError::new("Spectators cannot move".to_string()));
+                };
+                let seat = room.seats[side_of(*color)].as_ref().expect("the seat was taken");
+                if seat.connection.as_ref().is_none_or(|(id, _)| *id != connection.id) {
+                    return Err(FieldError::new("The seat was taken over by a rejoin".to_string()));
+                }
+                if room.seats[1].is_none() {
+                    return Err(FieldError::new("Wait for your opponent to join".to_string()));
+                }
+                if room.game.get_state() != State::Win && room.game.get_player_turn() != *color {
+                    return Err(FieldError::new("It is not your turn".to_string()));
+                }
+                play_checked(&mut room.game, action.parse()?)?;
+                room.broadcast();
+                return Ok(())
+            }
+        };
+
+        let joined = ServerMessage::Joined { code: code.clone(), color: color_name(color), token };
+        let _ = connection.sender.send(joined.to_text());
+        connection.room = Some((code.clone(), color));
+        rooms.get_mut(&code).expect("the room was found above").broadcast();
+        Ok(())
+    }
+
+    /// Frees the seat of a closed connection for a rejoin, rooms of finished games are closed once everyone left
+    /// and the others once they were empty for the timeout
+    pub fn leave(&self, connection: &Connection) {
+        let Some((code, color)) = &connection.room else { return };
+        let mut rooms = self.rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(code) else { return };
+
+        match color {
+            Some(color) => {
+                let seat = room.seats[side_of(*color)].as_mut().expect("the seat was taken");
+                // after a rejoin the seat belongs to the new connection
+                if seat.connection.as_ref().is_some_and(|(id, _)| *id == connection.id) {
+                    seat.connection = None;
+                }
+            },
+            None => room.spectators.retain(|(id, _)| *id != connection.id)
+        }
+
+        if room.is_empty() && room.game.get_state() == State::Win {
+            rooms.remove(code);
+        } else {
+            room.broadcast();
+            if room.is_empty() {
+                room.emptied_at = Some(Instant::now());
+            }
+        }
+        self.expire(&mut rooms);
+    }
+}
+
+pub struct RoomServer {
+    listener: TcpListener,
+    pub rooms: Rooms,
+}
+impl RoomServer {
+    /// Listens on `address`, e.g. `127.0.0.1:8081`, port 0 picks a free port
+    pub fn bind(address: &str) -> Result<RoomServer, ServerError> {
+        let listener = TcpListener::bind(address).map_err(|err| ServerError::new(format!("Cannot listen on {}: {}", address, err)))?;
+        Ok(RoomServer { listener, rooms: Rooms::default() })
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Serves connections until the process ends, each on its own thread
+    pub fn run(&self) {
+        thread::scope(|scope| {
+            for stream in self.listener.incoming().flatten() {
+                scope.spawn(|| self.serve(stream));
+            }
+        });
+    }
+
+    fn serve(&self, stream: TcpStream) {
+        let Ok(mut socket) = accept(stream) else { return };
+        if socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+            return
+        }
+        let (sender, receiver) = channel();
+        let mut connection = self.rooms.connect(sender);
+
+        loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    let result = serde_json::from_str(&text)
+                        .map_err(|err| FieldError::new(format!("Invalid message: {}", err)))
+                        .and_then(|message| self.rooms.handle(&mut connection, message));
+                    if let Err(err) = result {
+                        let _ = connection.sender.send(ServerMessage::Error { message: err.message }.to_text());
+                    }
+                },
+                Ok(Message::Close(_)) => break,
+                Ok(_) => {},
+                Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+                Err(_) => break
+            }
+
+            if receiver.try_iter().any(|text| socket.send(Message::Text(text)).is_err()) {
+                break
+            }
+        }
+        self.rooms.leave(&connection);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpStream};
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    use tungstenite::stream::MaybeTlsStream;
+    use tungstenite::{connect, Message, WebSocket};
+
+    use crate::server::rooms::{ClientMessage, RoomServer, Rooms, ServerMessage};
+
+    type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+    fn open(address: SocketAddr, message: ClientMessage) -> Socket {
+        let (mut socket, _) = connect(format!("ws://{}", address)).unwrap();
+        send(&mut socket, message);
+        socket
+    }
+
+    fn send(socket: &mut Socket, message: ClientMessage) {
+        socket.send(Message::Text(serde_json::to_string(&message).unwrap())).unwrap();
+    }
+
+    fn receive(socket: &mut Socket) -> ServerMessage {
+        loop {
+            if let Message::Text(text) = socket.read().unwrap() {
+                return serde_json::from_str(&text).unwrap()
+            }
+        }
+    }
+
+    /// Skips messages until the state after `plies` plies
+    fn state_after(socket: &mut Socket, plies: usize) -> ServerMessage {
+        loop {
+            let message = receive(socket);
+            if matches!(&message, ServerMessage::State { game, .. } if game.plies == plies) {
+                return message
+            }
+        }
+    }
+
+    /// Skips the states until the next error
+    fn next_error(socket: &mut Socket) -> ServerMessage {
+        loop {
+            let message = receive(socket);
+            if matches!(message, ServerMessage::Error { .. }) {
+                return message
+            }
+        }
+    }
+
+    #[test]
+    fn test_room_with_reconnect_and_spectator() {
+        let server = RoomServer::bind("127.0.0.1:0").unwrap();
+        let address = server.address().unwrap();
+        thread::spawn(move || server.run());
+
+        let mut white = open(address, ClientMessage::Create);
+        let ServerMessage::Joined { code, color, token: Some(white_token) } = receive(&mut white) else { panic!("not joined") };
+        assert_eq!(Some("white".to_string()), color);
+
+        send(&mut white, ClientMessage::Move { action: "0".to_string() });
+        assert_eq!(ServerMessage::Error { message: "Wait for your opponent to join".to_string() }, next_error(&mut white));
+
+        let mut black = open(address, ClientMessage::Join { code: code.clone() });
+        let ServerMessage::Joined { color, token: Some(black_token), .. } = receive(&mut black) else { panic!("not joined") };
+        assert_eq!(Some("black".to_string()), color);
+        let mut spectator = open(address, ClientMessage::Spectate { code: code.clone() });
+
+        send(&mut black, ClientMessage::Move { action: "0".to_string() });
+        assert_eq!(ServerMessage::Error { message: "It is not your turn".to_string() }, next_error(&mut black));
+        send(&mut white, ClientMessage::Move { action: "0".to_string() });
+        state_after(&mut black, 1);
+        send(&mut black, ClientMessage::Move { action: "0".to_string() });
+        assert_eq!(ServerMessage::Error { message: "Cannot move to 0".to_string() }, next_error(&mut black));
+        send(&mut black, ClientMessage::Move { action: "8".to_string() });
+        state_after(&mut spectator, 2);
+
+        // white loses the connection and gets the game back with its token
+        drop(white);
+        let ServerMessage::State { white_connected, .. } = state_after(&mut spectator, 2) else { unreachable!() };
+        assert!(!white_connected);
+        let mut white = open(address, ClientMessage::Rejoin { code: code.clone(), token: white_token });
+        receive(&mut white);
+        let ServerMessage::State { game, white_connected, spectators, .. } = receive(&mut white) else { panic!("no state") };
+        assert_eq!((vec!["0".to_string(), "8".to_string()], true, 1), (game.moves, white_connected, spectators));
+
+        // a rejoin takes the seat from the connection which is still open
+        let mut black_again = open(address, ClientMessage::Rejoin { code, token: black_token });
+        receive(&mut black_again);
+        send(&mut black, ClientMessage::Move { action: "16".to_string() });
+        assert_eq!(ServerMessage::Error { message: "The seat was taken over by a rejoin".to_string() }, next_error(&mut black));
+    }
+
+    #[test]
+    fn test_empty_rooms_expire() {
+        let rooms = Rooms { max_rooms: 1, empty_room_timeout: Duration::from_millis(100), ..Rooms::default() };
+        let (sender, _receiver) = channel();
+        let mut first = rooms.connect(sender.clone());
+        let mut second = rooms.connect(sender);
+
+        rooms.handle(&mut first, ClientMessage::Create).unwrap();
+        assert!(rooms.handle(&mut second, ClientMessage::Create).is_err());
+        // the room still waits for its creator to rejoin
+        rooms.leave(&first);
+        assert!(rooms.handle(&mut second, ClientMessage::Create).is_err());
+
+        thread::sleep(Duration::from_millis(150));
+        rooms.handle(&mut second, ClientMessage::Create).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+use std::io::{stdout, BufRead, Write};
+
+use crate::ai::action::Action;
+use crate::ai::analysis::player_name;
+use crate::ai::compute_hint;
+use crate::ai::engine::{EngineKind, EngineMove};
+use crate::ai::skill::{Skill, MAX_SKILL_LEVEL};
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::core::logic::{compute_checked_step, compute_computer_step};
+use crate::core::position::{draw_board, negate_token};
+
+const HELP: &str = "Commands:
+    7          place a piece on 7
+    3-4        move the piece on 3 to 4, or fly there with three pieces left
+    3-4x10     move and take the piece on 10 with the mill it closes
+    x10        take the piece on 10 after a mill, 10 works as well
+    undo       take back your last move
+    hint       show the best move
+    board      show the board again
+    help       show this text
+    quit       end the game";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Player {
+    Human,
+    Computer(EngineKind, Skill),
+}
+impl Player {
+    /// `human`, `minimax` or `mcts`, the engines optionally followed by a level, e.g. `mcts:12`
+    pub fn parse(text: &str) -> Result<Player, FieldError> {
+        let (name, level) = text.split_once(':').unwrap_or((text, ""));
+        let skill = match level {
+            "" => Skill::new(MAX_SKILL_LEVEL),
+            level => Skill::new(level.parse().map_err(|_| FieldError::new(format!("Invalid level \"{}\"", level)))?)
+        };
+        match name {
+            "human" if level.is_empty() => Ok(Player::Human),
+            "minimax" => Ok(Player::Computer(EngineKind::Minimax, skill)),
+            "mcts" => Ok(Player::Computer(EngineKind::Mcts, skill)),
+            _ => Err(FieldError::new(format!("Unknown player \"{}\", expected human, minimax[:level] or mcts[:level]", text)))
+        }
+    }
+}
+
+const OPTIONS: &str = "    --white <player>     human (default), minimax or mcts, the engines with an optional level, e.g. mcts:12
+    --black <player>     same for black (default minimax)
+    --max-plies <n>      a game between two engines is a draw after n plies (default 200)";
+
+/// The sides and the ply limit of a game in the console or the terminal
+pub struct Arguments {
+    pub white: Player,
+    pub black: Player,
+    pub max_plies: usize,
+}
+
+/// Reads the options of the `program` binary, for `--help` or an error the message to show is returned
+pub fn parse_arguments(program: &str, mut args: impl Iterator<Item = String>) -> Result<Arguments, String> {
+    let usage = format!("Usage: {} [options]\n{}", program, OPTIONS);
+    let mut arguments = Arguments {
+        white: Player::Human,
+        black: Player::parse("minimax").expect("minimax is a player"),
+        max_plies: 200,
+    };
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(usage);
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+
+        match flag.as_str() {
+            "--white" => arguments.white = Player::parse(&value).map_err(|err| err.message)?,
+            "--black" => arguments.black = Player::parse(&value).map_err(|err| err.message)?,
+            "--max-plies" => arguments.max_plies = value.parse().map_err(|_| format!("Invalid number \"{}\" for {}", value, flag))?,
+            _ => return Err(format!("Unknown option {}\n{}", flag, usage))
+        }
+    }
+    Ok(arguments)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command {
+    Play(Action),
+    Take(usize),
+    Undo,
+    Hint,
+    Board,
+    Help,
+    Quit,
+}
+
+/// Reads one line of the player, in the `Take` state a single position is the piece to take
+pub fn parse_command(line: &str, state: State) -> Result<Command, FieldError> {
+    let line = line.trim();
+    match line {
+        "undo" | "u" => return Ok(Command::Undo),
+        "hint" | "h" => return Ok(Command::Hint),
+        "board" | "b" => return Ok(Command::Board),
+        "help" | "?" => return Ok(Command::Help),
+        "quit" | "q" => return Ok(Command::Quit),
+        "" => return Err(FieldError::new("Enter a move or help".to_string())),
+        _ => {}
+    }
+
+    let take = line.strip_prefix('x');
+    if state == State::Take || take.is_some() {
+        let position = take.unwrap_or(line);
+        return match position.parse::<usize>() {
+            Ok(position) if position < 24 => Ok(Command::Take(position)),
+            _ => Err(FieldError::new(format!("\"{}\" is no position to take, expected a number from 0 to 23", position)))
+        }
+    }
+    line.parse().map(Command::Play)
+}
+
+pub struct Console {
+    pub game: Game,
+    pub white: Player,
+    pub black: Player,
+    /// Games between two computers are a draw after this many plies
+    max_plies: usize,
+}
+impl Console {
+    pub fn new(white: Player, black: Player, max_plies: usize) -> Console {
+        Console { game: Game::new(), white, black, max_plies }
+    }
+
+    pub fn player(&self) -> Player {
+        if self.game.get_player_turn() == 0b11 { self.white } else { self.black }
+    }
+
+    /// The board next to the numbers of the positions and what happens next
+    pub fn render(&self) -> String {
+        let board = draw_board(|position| match self.game.get_token_at(position) {
+            0b11 => "W",
+            0b10 => "B",
+            _ => "."
+        }.to_string());
+        let legend = draw_board(|position| format!("{:>2}", position));
+
+        let mut text = String::new();
+        for (board_line, legend_line) in board.lines().zip(legend.lines()) {
+            text += &format!("{:<30}{}\n", board_line, legend_line);
+        }
+        text + &self.status()
+    }
+
+    /// Whether a game between two computers has gone on for too long and ends in a draw
+    pub fn is_move_limit_reached(&self) -> bool {
+        self.white != Player::Human && self.black != Player::Human && self.game.get_step_counter() >= self.max_plies
+    }
+
+    pub fn status(&self) -> String {
+        if self.is_move_limit_reached() {
+            return format!("Draw after {} plies", self.max_plies)
+        }
+        let player = self.game.get_player_turn();
+        let name = player_name(player);
+        let pieces_left = self.game.get_setup_pieces_left();
+        match self.game.get_state() {
+            State::Win => format!("{} wins", player_name(self.game.get_winner())),
+            State::Take => format!("{} has closed a mill and takes a piece of {}", name, player_name(negate_token(player))),
+            State::Setup => {
+                let in_hand = if player == 0b11 { pieces_left / 2 } else { pieces_left / 2 + pieces_left % 2 };
+                format!("{} to place, {} pieces in hand", name, in_hand)
+            },
+            State::Normal if self.game.get_piece_count(player) == 3 => format!("{} to move, with three pieces left they fly", name),
+            State::Normal => format!("{} to move", name)
+        }
+    }
+
+    /// Plays the move of a human, an illegal move leaves the game as it was. A move which closes a mill
+    /// but takes nothing waits for [`Console::take`].
+    pub fn play(&mut self, action: Action) -> Result<(), FieldError> {
+        if self.game.get_state() == State::Take {
+            return Err(FieldError::new("Take a piece first, e.g. x10".to_string()));
+        }
+        let ply = self.game.get_history().len() - 1;
+        match compute_checked_step(action, &mut self.game) {
+            Ok(()) => Ok(()),
+            Err(_) if self.game.get_state() == State::Take && action.beatable_position.is_none() => Ok(()),
+            Err(err) => {
+                self.game.rewind(ply)?;
+                Err(err)
+            }
+        }
+    }
+
+    pub fn take(&mut self, position: usize) -> Result<(), FieldError> {
+        if self.game.get_state() != State::Take {
+            return Err(FieldError::new("There is nothing to take without a mill".to_string()));
+        }
+        compute_checked_step(Action::new(None, position, Some(position)), &mut self.game)
+    }
+
+    /// Takes back moves until it is the turn of a human again, a pending capture takes back the mill
+    pub fn undo(&mut self) -> Result<(), FieldError> {
+        let history = self.game.get_history();
+        let current = history.len() - 1;
+        if self.game.get_state() == State::Take {
+            return self.game.rewind(current);
+        }
+
+        let is_human = |player: u8| (if player == 0b11 { self.white } else { self.black }) == Player::Human;
+        let ply = (0..current).rev()
+            .find(|ply| is_human(history[*ply].player_turn))
+            .ok_or(FieldError::new("There is no move to take back".to_string()))?;
+        self.game.rewind(ply)
+    }
+
+    /// Lets the engine of the side to move play, none if it is a human or the game is over
+    pub fn computer_step(&mut self) -> Option<EngineMove> {
+        let Player::Computer(engine, skill) = self.player() else { return None };
+        if self.game.get_state() == State::Win {
+            return None
+        }
+        let engine_move = engine.engine().compute_step(&self.game, skill)?;
+        compute_computer_step(engine_move.action, &mut self.game).ok()?;
+        Some(engine_move)
+    }
+
+    /// Plays until the game is over or the human quits, the moves of humans are read from `input`
+    pub fn run(&mut self, mut input: impl BufRead) {
+        println!("{}\n", self.render());
+        loop {
+            if self.game.get_state() == State::Win || self.is_move_limit_reached() {
+                return
+            }
+
+            if let Player::Computer(engine, _) = self.player() {
+                let name = player_name(self.game.get_player_turn());
+                match self.computer_step() {
+                    Some(engine_move) => println!("{} ({}) plays {}, {}\n{}\n", name, engine.engine().name(), engine_move.action, engine_move.statistics(), self.render()),
+                    None => {
+                        println!("{} found no move", name);
+                        return
+                    }
+                }
+                continue
+            }
+
+            print!("> ");
+            let _ = stdout().flush();
+            let mut line = String::new();
+            if input.read_line(&mut line).unwrap_or(0) == 0 {
+                return
+            }
+
+            let result = match parse_command(&line, self.game.get_state()) {
+                Ok(Command::Play(action)) => self.play(action),
+                Ok(Command::Take(position)) => self.take(position),
+                Ok(Command::Undo) => self.undo(),
+                Ok(Command::Hint) => {
+                    match compute_hint(&self.game) {
+                        Some(action) => println!("Hint: {}", action),
+                        None => println!("There is no move left")
+                    }
+                    continue
+                },
+                Ok(Command::Board) => Ok(()),
+                Ok(Command::Help) => {
+                    println!("{}", HELP);
+                    continue
+                },
+                Ok(Command::Quit) => return,
+                Err(err) => Err(err)
+            };
+            match result {
+                Ok(()) => println!("{}\n", self.render()),
+                Err(err) => println!("Error: {}", err.message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::enums::State;
+    use crate::ui::console::{parse_arguments, parse_command, Command, Console, Player};
+
+    fn play(console: &mut Console, moves: &[&str]) {
+        for text in moves {
+            match parse_command(text, console.game.get_state()).unwrap() {
+                Command::Play(action) => console.play(action).unwrap(),
+                Command::Take(position) => console.take(position).unwrap(),
+                _ => panic!("{} is no move", text)
+            }
+        }
+    }
+
+    #[test]
+    fn test_moves_errors_and_undo() {
+        let mut console = Console::new(Player::Human, Player::Human, 200);
+        play(&mut console, &["0", "8", "1", "9"]);
+
+        assert!(console.play("0".parse().unwrap()).is_err_and(|err| err.message == "Cannot move to 0"));
+        assert!(console.play("3-4".parse().unwrap()).is_err());
+        assert!(parse_command("25", State::Setup).is_err());
+        assert_eq!(4, console.game.get_step_counter());
+
+        // closing the mill 7 0 1 waits for the piece to take
+        play(&mut console, &["7"]);
+        assert_eq!(State::Take, console.game.get_state());
+        assert!(console.take(0).is_err());
+        assert!(console.render().ends_with("White has closed a mill and takes a piece of Black"));
+        play(&mut console, &["x8"]);
+        assert_eq!(0, console.game.get_token_at(8));
+
+        console.undo().unwrap();
+        assert_eq!(0b11, console.game.get_player_turn());
+        assert_eq!(0b10, console.game.get_token_at(8));
+        assert_eq!(0, console.game.get_token_at(7));
+        assert_eq!(4, console.game.get_history().len() - 1);
+
+        assert!(Player::parse("mcts:5").is_ok());
+        assert!(Player::parse("human:5").is_err());
+        assert_eq!(Ok(Command::Take(3)), parse_command("3", State::Take).map_err(|err| err.message));
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let args = |text: &str| text.split_whitespace().map(String::from).collect::<Vec<_>>().into_iter();
+
+        let arguments = parse_arguments("tui", args("--black human --white mcts:12 --max-plies 50")).unwrap();
+        assert_eq!((Player::Human, 50), (arguments.black, arguments.max_plies));
+        assert_eq!(Player::parse("mcts:12").unwrap(), arguments.white);
+
+        assert_eq!(Player::parse("minimax").unwrap(), parse_arguments("tui", args("")).unwrap().black);
+        assert!(parse_arguments("console", args("--help")).is_err_and(|usage| usage.starts_with("Usage: console [options]")));
+        assert!(parse_arguments("tui", args("--white")).is_err_and(|message| message == "Missing value for --white"));
+    }
+}
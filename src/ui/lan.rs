@@ -0,0 +1,379 @@
+//! Games between two native clients on the local network over plain TCP, without a server in between.
+//!
+//! Every message is one line of text:
+//! - `hello <version> white|black` with the color of the sender, the host says it first and plays white
+//! - `move <move>`, e.g. `move 3-4x10`
+//! - `sync <plies> <24 x W|B|E>` the state of the sender after its move
+//! - `resign`, `draw offer` and `draw accept`, a move declines the open offer
+//! - `bye <reason>` before the connection is closed
+//!
+//! Both sides play every move on their own `core::Game` and check it. An illegal move, a different state
+//! or any other message which does not fit ends the game with a `bye`.
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use crate::ai::action::Action;
+use crate::ai::analysis::player_name;
+use crate::api::play_checked;
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::core::position::{encode_positions, negate_token};
+use crate::ui::online::MoveInput;
+
+const VERSION: u32 = 1;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum LanMessage {
+    Hello { version: u32, color: u8 },
+    Move(Action),
+    Sync { plies: usize, board: String },
+    Resign,
+    DrawOffer,
+    DrawAccept,
+    Bye(String),
+}
+impl LanMessage {
+    pub fn parse(line: &str) -> Result<LanMessage, FieldError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let invalid = || FieldError::new(format!("Invalid message \"{}\"", line.trim()));
+        match words.as_slice() {
+            ["hello", version, color] => Ok(LanMessage::Hello {
+                version: version.parse().map_err(|_| invalid())?,
+                color: match *color { "white" => 0b11, "black" => 0b10, _ => return Err(invalid()) },
+            }),
+            ["move", action] => Ok(LanMessage::Move(action.parse()?)),
+            ["sync", plies, board] => Ok(LanMessage::Sync { plies: plies.parse().map_err(|_| invalid())?, board: board.to_string() }),
+            ["resign"] => Ok(LanMessage::Resign),
+            ["draw", "offer"] => Ok(LanMessage::DrawOffer),
+            ["draw", "accept"] => Ok(LanMessage::DrawAccept),
+            ["bye", reason @ ..] => Ok(LanMessage::Bye(reason.join(" "))),
+            _ => Err(invalid())
+        }
+    }
+}
+impl fmt::Display for LanMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LanMessage::Hello { version, color } => write!(f, "hello {} {}", version, player_name(*color).to_lowercase()),
+            LanMessage::Move(action) => write!(f, "move {}", action),
+            LanMessage::Sync { plies, board } => write!(f, "sync {} {}", plies, board),
+            LanMessage::Resign => write!(f, "resign"),
+            LanMessage::DrawOffer => write!(f, "draw offer"),
+            LanMessage::DrawAccept => write!(f, "draw accept"),
+            LanMessage::Bye(reason) => write!(f, "bye {}", reason)
+        }
+    }
+}
+
+/// What the connection thread tells the client
+enum LanEvent {
+    Listening(String),
+    Message(LanMessage),
+    /// A line which is no message, the connection is closed with a bye
+    Invalid(String),
+    Closed(String),
+}
+
+pub struct LanClient {
+    /// None once the connection is closed
+    outgoing: Option<Sender<LanMessage>>,
+    incoming: Receiver<LanEvent>,
+    /// Own color, the one who joins learns it from the hello of the host
+    pub color: Option<u8>,
+    /// Both sides have said hello
+    pub is_connected: bool,
+    /// Side with an open draw offer
+    pub draw_offer: Option<u8>,
+    /// How the game ended besides the rules, e.g. a resignation or a lost connection
+    pub outcome: Option<String>,
+    /// Where the host waits for the other side
+    pub address: Option<String>,
+    input: MoveInput,
+}
+impl LanClient {
+    fn new(color: Option<u8>, run: impl FnOnce(Receiver<LanMessage>, Sender<LanEvent>) + Send + 'static) -> LanClient {
+        let (outgoing, outgoing_receiver) = channel();
+        let (incoming_sender, incoming) = channel();
+        thread::spawn(move || run(outgoing_receiver, incoming_sender));
+        LanClient {
+            outgoing: Some(outgoing), incoming, color, is_connected: false, draw_offer: None, outcome: None, address: None,
+            input: MoveInput::default(),
+        }
+    }
+
+    /// Waits for the other side on `address`, e.g. `0.0.0.0:9000`, and plays white
+    pub fn host(address: &str) -> LanClient {
+        let address = address.to_string();
+        LanClient::new(Some(0b11), move |outgoing, incoming| {
+            let listener = match TcpListener::bind(&address) {
+                Ok(listener) => listener,
+                Err(err) => {
+                    let _ = incoming.send(LanEvent::Closed(format!("Cannot listen on {}: {}", address, err)));
+                    return
+                }
+            };
+            let local_address = listener.local_addr().map_or(address, |local_address| local_address.to_string());
+            let _ = incoming.send(LanEvent::Listening(local_address));
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    // the host speaks first
+                    let _ = writeln!(&stream, "{}", LanMessage::Hello { version: VERSION, color: 0b11 });
+                    talk(stream, outgoing, incoming);
+                },
+                Err(err) => {
+                    let _ = incoming.send(LanEvent::Closed(format!("No connection: {}", err)));
+                }
+            }
+        })
+    }
+
+    /// Joins the host at `address`, e.g. `192.168.1.20:9000`, and plays black
+    pub fn join(address: &str) -> LanClient {
+        let address = address.to_string();
+        LanClient::new(None, move |outgoing, incoming| {
+            match TcpStream::connect(&address) {
+                Ok(stream) => talk(stream, outgoing, incoming),
+                Err(err) => {
+                    let _ = incoming.send(LanEvent::Closed(format!("Cannot connect to {}: {}", address, err)));
+                }
+            }
+        })
+    }
+
+    fn send(&self, message: LanMessage) {
+        if let Some(outgoing) = &self.outgoing {
+            let _ = outgoing.send(message);
+        }
+    }
+
+    /// Says bye and closes the connection
+    fn disconnect(&mut self, reason: String) {
+        self.send(LanMessage::Bye(reason.clone()));
+        self.outgoing = None;
+        if self.outcome.is_none() {
+            self.outcome = Some(format!("Disconnected: {}", reason));
+        }
+    }
+
+    /// Handles what the other side sent and plays its moves on `game`, which is the same on both sides
+    pub fn poll(&mut self, game: &mut Game) {
+        while let Ok(event) = self.incoming.try_recv() {
+            let result = match event {
+                LanEvent::Listening(address) => {
+                    self.address = Some(address);
+                    Ok(())
+                },
+                LanEvent::Message(message) => self.receive(game, message),
+                LanEvent::Invalid(reason) => Err(FieldError::new(reason)),
+                LanEvent::Closed(reason) => {
+                    self.outgoing = None;
+                    self.outcome.get_or_insert(reason);
+                    Ok(())
+                }
+            };
+            if let Err(err) = result {
+                self.disconnect(err.message);
+            }
+        }
+    }
+
+    fn receive(&mut self, game: &mut Game, message: LanMessage) -> Result<(), FieldError> {
+        if self.outcome.is_some() {
+            return Ok(())
+        }
+        let opponent = self.color.map(negate_token);
+        match message {
+            LanMessage::Hello { version, .. } if version != VERSION => {
+                return Err(FieldError::new(format!("Version {} is not supported, expected {}", version, VERSION)))
+            },
+            LanMessage::Hello { .. } if self.is_connected => return Err(FieldError::new("Hello twice".to_string())),
+            LanMessage::Hello { color, .. } => {
+                match self.color {
+                    Some(own) if own == color => return Err(FieldError::new(format!("Both sides want to play {}", player_name(color)))),
+                    Some(_) => {},
+                    None => {
+                        self.color = Some(negate_token(color));
+                        self.send(LanMessage::Hello { version: VERSION, color: negate_token(color) });
+                    }
+                }
+                self.is_connected = true;
+            },
+            _ if !self.is_connected => return Err(FieldError::new("Say hello first".to_string())),
+            LanMessage::Move(action) => {
+                if Some(game.get_player_turn()) != opponent || game.get_state() == State::Win {
+                    return Err(FieldError::new(format!("Move {} out of turn", action)));
+                }
+                play_checked(game, action).map_err(|err| FieldError::new(format!("Illegal move {}: {}", action, err.message)))?;
+                self.draw_offer = None;
+            },
+            LanMessage::Sync { plies, board } => {
+                if plies != game.get_step_counter() || board != encode_positions(game.get_board()) {
+                    return Err(FieldError::new(format!("The games differ after {} plies", plies)));
+                }
+            },
+            LanMessage::Resign => self.outcome = opponent.map(|opponent| format!("{} resigned", player_name(opponent))),
+            LanMessage::DrawOffer => self.draw_offer = opponent,
+            LanMessage::DrawAccept if self.color.is_some() && self.draw_offer == self.color => {
+                self.outcome = Some("Draw agreed".to_string());
+            },
+            LanMessage::DrawAccept => return Err(FieldError::new("No draw was offered".to_string())),
+            LanMessage::Bye(reason) => {
+                self.outgoing = None;
+                self.outcome = Some(format!("The opponent left: {}", reason));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_own_turn(&self, game: &Game) -> bool {
+        self.is_connected && self.outcome.is_none() && self.color == Some(game.get_player_turn()) && game.get_state() != State::Win
+    }
+
+    /// Plays the release of the mouse on `position` on `game` and sends the move once it is complete
+    pub fn release(&mut self, game: &mut Game, position: usize) -> Result<(), FieldError> {
+        if !self.is_own_turn(game) {
+            return Err(FieldError::new("It is not your turn".to_string()));
+        }
+        if let Some(action) = self.input.release(game, position)? {
+            self.draw_offer = None;
+            self.send(LanMessage::Move(action));
+            self.send(LanMessage::Sync { plies: game.get_step_counter(), board: encode_positions(game.get_board()) });
+        }
+        Ok(())
+    }
+
+    pub fn resign(&mut self) {
+        let Some(color) = self.color.filter(|_| self.is_connected && self.outcome.is_none()) else { return };
+        self.send(LanMessage::Resign);
+        self.outcome = Some(format!("{} resigned", player_name(color)));
+    }
+
+    /// Offers a draw, or accepts the offer of the other side
+    pub fn offer_draw(&mut self) {
+        let Some(color) = self.color.filter(|_| self.is_connected && self.outcome.is_none()) else { return };
+        if self.draw_offer == Some(negate_token(color)) {
+            self.send(LanMessage::DrawAccept);
+            self.outcome = Some("Draw agreed".to_string());
+        } else {
+            self.send(LanMessage::DrawOffer);
+            self.draw_offer = Some(color);
+        }
+    }
+
+    /// One line about the game, e.g. `LAN: you play black, white offers a draw (D to accept)`
+    pub fn status(&self) -> String {
+        if let Some(outcome) = &self.outcome {
+            return format!("LAN: {}", outcome)
+        }
+        let Some(color) = self.color.filter(|_| self.is_connected) else {
+            return match &self.address {
+                Some(address) => format!("LAN: waiting for an opponent on {}", address),
+                None => "LAN: connecting...".to_string()
+            }
+        };
+        let mut status = format!("LAN: you play {} (R to resign, D for a draw)", player_name(color).to_lowercase());
+        match self.draw_offer {
+            Some(offer) if offer == color => status += ", you offered a draw",
+            Some(offer) => status += &format!(", {} offers a draw (D to accept)", player_name(offer).to_lowercase()),
+            None => {}
+        }
+        status
+    }
+}
+
+/// Passes the lines of the other side on and writes the messages of the client until one side closes
+fn talk(stream: TcpStream, outgoing: Receiver<LanMessage>, incoming: Sender<LanEvent>) {
+    let Ok(reader) = stream.try_clone() else { return };
+    let reader_events = incoming.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            let (event, is_valid) = match LanMessage::parse(&line) {
+                Ok(message) => (LanEvent::Message(message), true),
+                Err(err) => (LanEvent::Invalid(err.message), false)
+            };
+            if reader_events.send(event).is_err() || !is_valid {
+                return
+            }
+        }
+        let _ = reader_events.send(LanEvent::Closed("The connection is closed".to_string()));
+    });
+
+    let mut writer = &stream;
+    for message in outgoing {
+        let is_bye = matches!(message, LanMessage::Bye(_));
+        if writeln!(writer, "{}", message).is_err() || is_bye {
+            break
+        }
+    }
+    // the client is gone or has said bye
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::game::Game;
+    use crate::ui::lan::{LanClient, LanMessage};
+
+    fn wait_until(client: &mut LanClient, game: &mut Game, done: impl Fn(&LanClient, &Game) -> bool) {
+        for _ in 0..500 {
+            client.poll(game);
+            if done(client, game) {
+                return
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("timed out, {}", client.status());
+    }
+
+    #[test]
+    fn test_lan_game_between_two_clients() {
+        let (mut host_game, mut join_game) = (Game::new(), Game::new());
+        let mut host = LanClient::host("127.0.0.1:0");
+        wait_until(&mut host, &mut host_game, |host, _| host.address.is_some());
+        let mut join = LanClient::join(host.address.as_ref().unwrap());
+        wait_until(&mut join, &mut join_game, |join, _| join.is_connected);
+        wait_until(&mut host, &mut host_game, |host, _| host.is_connected);
+        assert_eq!(Some(0b10), join.color);
+
+        host.release(&mut host_game, 0).unwrap();
+        wait_until(&mut join, &mut join_game, |_, game| game.get_step_counter() == 1);
+        join.offer_draw();
+        wait_until(&mut host, &mut host_game, |host, _| host.draw_offer == Some(0b10));
+        host.offer_draw();
+        wait_until(&mut join, &mut join_game, |join, _| join.outcome.is_some());
+        assert_eq!(Some("Draw agreed".to_string()), join.outcome);
+
+        assert_eq!(Ok(LanMessage::Bye("games differ".to_string())), LanMessage::parse("bye games differ").map_err(|err| err.message));
+        assert!(LanMessage::parse("hello one white").is_err());
+    }
+
+    #[test]
+    fn test_illegal_move_disconnects() {
+        let mut game = Game::new();
+        let mut host = LanClient::host("127.0.0.1:0");
+        wait_until(&mut host, &mut game, |host, _| host.address.is_some());
+
+        let mut stream = TcpStream::connect(host.address.as_ref().unwrap()).unwrap();
+        let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        assert_eq!("hello 1 white", lines.next().unwrap().unwrap());
+        writeln!(stream, "hello 1 black").unwrap();
+        wait_until(&mut host, &mut game, |host, _| host.is_connected);
+
+        // black moves out of turn
+        writeln!(stream, "move 8").unwrap();
+        wait_until(&mut host, &mut game, |host, _| host.outcome.is_some());
+        assert_eq!(Some("Disconnected: Move 8 out of turn".to_string()), host.outcome);
+        assert_eq!("bye Move 8 out of turn", lines.next().unwrap().unwrap());
+        assert!(lines.next().is_none());
+    }
+}
@@ -1,9 +1,18 @@
+pub mod console;
 pub mod rendering;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod terminal;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod online;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lan;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
 
 use good_web_game::graphics::Color;
 use good_web_game as ggez;
 use ggez::{event, graphics, GameError, GameResult, Context};
-use ggez::event::EventHandler;
+use ggez::event::{EventHandler, KeyCode, KeyMods};
 use ggez::graphics::{DrawParam, Image, Rect};
 use ggez::cgmath::{Point2, Vector2};
 
@@ -14,13 +23,33 @@ use Option;
 use crate::core::enums::{State, Difficulty};
 use crate::core::game::Game;
 use crate::core::logic::{coords_to_board_position, coords_to_bottom_panel_position, is_restart_clicked, compute_bottom_panel, compute_button_down, compute_button_up, compute_computer_step};
-use crate::ai;
+use crate::ai::action::Action;
+use crate::ai::analysis::{analyze_game, player_name, Analysis, MoveClass};
+use crate::ai::compute_hint;
+use crate::ai::engine::EngineKind;
+use crate::ai::score::Score;
+use crate::ai::skill::Skill;
+#[cfg(not(target_arch = "wasm32"))]
+use remote::Remote;
 
 pub struct Engine {
     game: Game,
 
     computer_white: Difficulty,
     computer_black: Difficulty,
+    engine_white: EngineKind,
+    engine_black: EngineKind,
+    /// Side of the last computer move and how its engine rated it
+    last_score: Option<(u8, Score)>,
+    /// Suggested action for the human player and the step it belongs to
+    hint: Option<(usize, Action)>,
+    hints_used: usize,
+    /// Analysis of the finished game and the move of it which is shown
+    analysis: Option<Analysis>,
+    review_index: usize,
+    /// Other player online or on the local network, the moves are sent there as well
+    #[cfg(not(target_arch = "wasm32"))]
+    remote: Option<Remote>,
     
     images: HashMap<String, Image>,
     offsets: Point2<f32>,
@@ -42,6 +71,15 @@ impl Engine {
             game,
             computer_white: Difficulty::Off,
             computer_black: Difficulty::Off,
+            engine_white: EngineKind::Minimax,
+            engine_black: EngineKind::Minimax,
+            last_score: None,
+            hint: None,
+            hints_used: 0,
+            analysis: None,
+            review_index: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            remote: None,
             images,
             offsets,
             scales,
@@ -49,6 +87,20 @@ impl Engine {
         })
     }
 
+    /// Plays against another human online or on the local network, the computer players are off then
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_remote(mut self, remote: Option<Remote>) -> Engine {
+        self.remote = remote;
+        self
+    }
+
+    fn is_remote(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        { self.remote.is_some() }
+        #[cfg(target_arch = "wasm32")]
+        { false }
+    }
+
     fn load_images(ctx: &mut Context, quad_ctx: &mut GraphicsContext) -> HashMap<String, Image> {
         let board: Image = Image::new(ctx, quad_ctx, "/assets/muehle_board.png").unwrap();
         let piece_white: Image = Image::new(ctx, quad_ctx, "/assets/muehle_white_piece.png").unwrap();
@@ -88,6 +140,67 @@ impl Engine {
         images
     }
     
+    /// Searches the best action for the human player to move and shows it until the turn is over
+    pub fn hint(&mut self) {
+        let state = self.game.get_state();
+        if state == State::Win || self.is_computer_turn() || self.hint.is_some() {
+            return
+        }
+
+        self.game.undo_carry();
+        if let Some(action) = compute_hint(&self.game) {
+            self.hint = Some((self.game.get_step_counter(), action));
+            self.hints_used += 1;
+        }
+    }
+
+    /// Analyses the finished game, saves the report and shows its moves one by one. Closes it again if it is shown.
+    pub fn analyze(&mut self) {
+        if self.analysis.is_some() {
+            self.analysis = None;
+            return
+        }
+        if self.game.get_state() != State::Win {
+            return
+        }
+
+        let analysis = analyze_game(self.game.get_history(), ANALYSIS_DEPTH);
+        export_analysis(&analysis);
+        self.review_index = 0;
+        self.analysis = Some(analysis);
+    }
+
+    fn step_review(&mut self, forward: bool) {
+        let count = self.analysis.as_ref().map_or(0, |analysis| analysis.moves.len());
+        if count == 0 {
+            return
+        }
+        self.review_index = if forward {
+            (self.review_index + 1).min(count - 1)
+        } else {
+            self.review_index.saturating_sub(1)
+        };
+    }
+
+    /// The position of the reviewed move and the move played in it
+    fn review_position(&self) -> Option<(Game, Action)> {
+        let analyzed = self.analysis.as_ref()?.moves.get(self.review_index)?;
+        let turn = self.game.get_history()[analyzed.ply];
+        let state = if turn.setup_pieces_left > 0 { State::Setup } else { State::Normal };
+        let game = Game::from_state(turn.board, turn.player_turn, state, turn.setup_pieces_left, turn.step_counter).ok()?;
+        Some((game, analyzed.played))
+    }
+
+    /// Whether the human may not move, against a remote player also during the turn of the opponent
+    fn is_computer_turn(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(remote) = &self.remote {
+            return !remote.is_own_turn(&self.game)
+        }
+        self.computer_white != Difficulty::Off && self.game.get_player_turn() == 0b11 ||
+            self.computer_black != Difficulty::Off && self.game.get_player_turn() == 0b10
+    }
+
     fn calculate_offsets(window_width: f32, window_height: f32) -> Point2<f32> {
         const GAME_IMAGES_WIDTH: f32 = 1280.0;
         const GAME_IMAGES_HEIGHT: f32= 1280.0 + 80.0 + 240.0;
@@ -118,8 +231,36 @@ impl Engine {
     }
 }
 
+/// Plies searched below every move of an analysed game
+const ANALYSIS_DEPTH: usize = 3;
+
+/// Writes the report next to the game, in the browser it is only printed
+fn export_analysis(analysis: &Analysis) {
+    println!("{}", analysis.to_text());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    for (path, content) in [("analysis.txt", analysis.to_text()), ("analysis.json", analysis.to_json())] {
+        match std::fs::write(path, content) {
+            Ok(()) => println!("Analysis saved to {}", path),
+            Err(e) => println!("Could not save the analysis to {}: {}", path, e)
+        }
+    }
+}
+
 impl EventHandler<GameError> for Engine {
     fn update(&mut self, _ctx: &mut Context, _quad_ctx: &mut GraphicsContext) -> GameResult {
+        if self.hint.as_ref().is_some_and(|(step, _)| *step != self.game.get_step_counter()) {
+            self.hint = None;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(remote) = &mut self.remote {
+            // a piece in the hand of the player is not taken away by a new state
+            if self.game.get_carry_piece().is_none() {
+                remote.poll(&mut self.game);
+            }
+            self.force_draw = false;
+            return Ok(())
+        }
         if self.force_draw {
             self.force_draw = false;
             return Ok(())
@@ -128,17 +269,20 @@ impl EventHandler<GameError> for Engine {
             return Ok(())
         }
 
-        let action =
+        let player_turn = self.game.get_player_turn();
+        let engine_move =
             if self.computer_white != Difficulty::Off && self.game.get_player_turn() == 0b11 {
-                ai::compute_step(&self.game, self.computer_white)
+                self.engine_white.engine().compute_step(&self.game, Skill::from(self.computer_white))
             } else if self.computer_black != Difficulty::Off && self.game.get_player_turn() == 0b10 {
-                ai::compute_step(&self.game, self.computer_black)
+                self.engine_black.engine().compute_step(&self.game, Skill::from(self.computer_black))
             } else {
                 Option::None
             };
         
-        if action.is_some() {
-            match compute_computer_step(action.unwrap(), &mut self.game) {
+        if let Some(engine_move) = engine_move {
+            println!("{} plays {}, {}", player_name(player_turn), engine_move.action, engine_move.statistics());
+            self.last_score = engine_move.score.map(|score| (player_turn, score));
+            match compute_computer_step(engine_move.action, &mut self.game) {
                 Ok(()) => {},
                 Err(_) => {
                     println!("Invalid move from computer");
@@ -176,11 +320,16 @@ impl EventHandler<GameError> for Engine {
         let param: DrawParam = DrawParam::new().dest(dest).scale(self.scales);
         graphics::draw(ctx, quad_ctx, &image, param)?;
     
-        /* Drawing on each field a piece or a marker */
+        /* Drawing on each field a piece or a marker, or the reviewed move of the analysis */
+        let review = self.review_position();
         for position in 0..24 {
             let comp_white: bool = self.computer_white != Difficulty::Off;
             let comp_black: bool = self.computer_black != Difficulty::Off;
-            let image = rendering::calculate_image(&self.game, position, &self.images, comp_white, comp_black);
+            let image = match (&review, &self.hint) {
+                (Some((game, played)), _) => rendering::calculate_hint_image(game, position, played, &self.images),
+                (None, Some((_, hint))) => rendering::calculate_hint_image(&self.game, position, hint, &self.images),
+                (None, None) => rendering::calculate_image(&self.game, position, &self.images, comp_white, comp_black)
+            };
             if image.is_none() {
                 continue
             }
@@ -206,6 +355,58 @@ impl EventHandler<GameError> for Engine {
             graphics::draw(ctx, quad_ctx, &image, param)?;
         }
 
+        /* Engines of both sides */
+        let text = graphics::Text::new(format!(
+            "White: {}  Black: {}  (W/B to switch, H for a hint)",
+            self.engine_white.engine().name(),
+            self.engine_black.engine().name()));
+        let param: DrawParam = DrawParam::new().dest(Point2::new(self.offsets.x, self.offsets.y));
+        graphics::draw(ctx, quad_ctx, &text, param)?;
+
+        /* Score of the last computer move, e.g. "White: win in 5" */
+        if let Some((player, score)) = self.last_score {
+            let text = graphics::Text::new(format!("{}: {}", if player == 0b11 { "White" } else { "Black" }, score));
+            let param: DrawParam = DrawParam::new().dest(Point2::new(self.offsets.x, self.offsets.y + 20.0));
+            graphics::draw(ctx, quad_ctx, &text, param)?;
+        }
+
+        /* Game against a remote player, e.g. "Room KXMTR: you play white, black is not connected" */
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(remote) = &self.remote {
+            let text = graphics::Text::new(remote.status());
+            let param: DrawParam = DrawParam::new().dest(Point2::new(self.offsets.x, self.offsets.y + 40.0));
+            graphics::draw(ctx, quad_ctx, &text, param)?;
+        }
+
+        /* Hints the human players needed */
+        if self.game.get_state() == State::Win {
+            let text = graphics::Text::new(format!("Hints used: {}  (A to analyse the game)", self.hints_used));
+            let param: DrawParam = DrawParam::new().dest(Point2::new(self.offsets.x, self.offsets.y + 60.0));
+            graphics::draw(ctx, quad_ctx, &text, param)?;
+        }
+
+        /* Analysis, e.g. "12. White 3-4 blunder (+300 -> -1200), best was 5-4x10" */
+        if let Some(analysis) = &self.analysis {
+            let mut lines = Vec::new();
+            if let Some(analyzed) = analysis.moves.get(self.review_index) {
+                let mut line = format!("{}. {} {} {} ({} -> {})", analyzed.ply + 1, player_name(analyzed.player), analyzed.played, analyzed.class, analyzed.before, analyzed.after);
+                if analyzed.class != MoveClass::Best {
+                    line += &format!(", best was {}", analyzed.best);
+                }
+                lines.push(line);
+            }
+            for player in [0b11, 0b10] {
+                lines.push(format!("{}: {} inaccuracies, {} mistakes, {} blunders", player_name(player),
+                    analysis.count(player, MoveClass::Inaccuracy), analysis.count(player, MoveClass::Mistake), analysis.count(player, MoveClass::Blunder)));
+            }
+            lines.push("Left/Right to step through the moves".to_string());
+
+            for (index, line) in lines.into_iter().enumerate() {
+                let text = graphics::Text::new(line);
+                let param: DrawParam = DrawParam::new().dest(Point2::new(self.offsets.x, self.offsets.y + 80.0 + 20.0 * index as f32));
+                graphics::draw(ctx, quad_ctx, &text, param)?;
+            }
+        }
 
         graphics::present(ctx, quad_ctx)
     }
@@ -232,6 +433,16 @@ impl EventHandler<GameError> for Engine {
         let logical_x: f32 = (x - self.offsets.x) / self.scales.x;
         let logical_y: f32 = (y - self.offsets.y) / self.scales.y;
 
+        /* against a remote player there are no computer players and the game cannot be restarted */
+        if self.is_remote() {
+            if !self.is_computer_turn() {
+                if let Ok(position) = coords_to_board_position(logical_x, logical_y) {
+                    let _ = compute_button_down(position, &mut self.game);
+                }
+            }
+            return
+        }
+
         /* bottom panel */
         match coords_to_bottom_panel_position(logical_x, logical_y) {
             Ok(index) => {
@@ -245,14 +456,17 @@ impl EventHandler<GameError> for Engine {
         /* restart button */
         if is_restart_clicked(logical_x, logical_y) {
             self.game = Game::new();
+            self.last_score = None;
+            self.hint = None;
+            self.hints_used = 0;
+            self.analysis = None;
             self.force_draw = true;
             println!("Game restarted");
             return
         }
 
         /* game board */
-        if self.computer_white != Difficulty::Off && self.game.get_player_turn() == 0b11 || 
-            self.computer_black != Difficulty::Off && self.game.get_player_turn() == 0b10 {
+        if self.is_computer_turn() {
             return
         }
 
@@ -268,6 +482,34 @@ impl EventHandler<GameError> for Engine {
         };
     }
 
+    fn key_down_event(
+            &mut self,
+            ctx: &mut Context,
+            _quad_ctx: &mut GraphicsContext,
+            keycode: KeyCode,
+            _keymods: KeyMods,
+            _repeat: bool) {
+        match keycode {
+            KeyCode::W => self.engine_white = self.engine_white.next(),
+            KeyCode::B => self.engine_black = self.engine_black.next(),
+            KeyCode::H => self.hint(),
+            KeyCode::A => self.analyze(),
+            KeyCode::Left => self.step_review(false),
+            KeyCode::Right => self.step_review(true),
+            KeyCode::Escape => event::quit(ctx),
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::R => if let Some(Remote::Lan(lan)) = &mut self.remote {
+                lan.resign();
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::D => if let Some(Remote::Lan(lan)) = &mut self.remote {
+                lan.offer_draw();
+            },
+            _ => return
+        }
+        self.force_draw = true;
+    }
+
     fn mouse_button_up_event(
             &mut self,
             _ctx: &mut Context,
@@ -292,6 +534,15 @@ impl EventHandler<GameError> for Engine {
                     return println!("{}", e.message);
                 }
             };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(remote) = &mut self.remote {
+            if let Err(e) = remote.release(&mut self.game, position) {
+                println!("{}", e.message);
+            }
+            self.force_draw = true;
+            return
+        }
         
         if compute_button_up(position, &mut self.game).is_err() {
             return println!("Invalid move to {}.logical_x {}.logical_y from player {}", logical_x, logical_y, self.game.get_player_turn());
@@ -0,0 +1,257 @@
+//! Client of [`crate::server::rooms`]. Online the board only shows the state of the server, a move of the
+//! own turn is played on it for the moment and sent to the server, which answers with the new state.
+
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{connect, Message, WebSocket};
+
+use crate::ai::action::Action;
+use crate::api::GameView;
+use crate::core::enums::{FieldError, State};
+use crate::core::game::Game;
+use crate::core::logic::compute_button_up;
+use crate::server::rooms::{ClientMessage, ServerMessage};
+
+/// How long the connection waits for the server before it sends the moves of the player
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Pause before a lost connection is opened again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct OnlineClient {
+    outgoing: Sender<ClientMessage>,
+    incoming: Receiver<ServerMessage>,
+    pub code: Option<String>,
+    /// Own color, none while spectating or before the server answered
+    pub color: Option<u8>,
+    /// Last state of the server
+    pub view: Option<GameView>,
+    /// Whether white and black are connected
+    pub connected: [bool; 2],
+    /// Last error of the server or the connection
+    pub message: Option<String>,
+    input: MoveInput,
+}
+impl OnlineClient {
+    /// Connects to `url`, e.g. `ws://localhost:8081`, on its own thread and sends `first`. A lost connection
+    /// is opened again and takes its seat back.
+    pub fn connect(url: &str, first: ClientMessage) -> OnlineClient {
+        let (outgoing, outgoing_receiver) = channel();
+        let (incoming_sender, incoming) = channel();
+        let url = url.to_string();
+        thread::spawn(move || run_connection(&url, first, outgoing_receiver, incoming_sender));
+
+        OnlineClient { outgoing, incoming, code: None, color: None, view: None, connected: [false; 2], message: None, input: MoveInput::default() }
+    }
+
+    /// Handles the messages of the server, the game to show if its state has changed. While a mill waits for
+    /// the piece to take the shown game is kept.
+    pub fn poll(&mut self) -> Option<Game> {
+        let mut is_changed = false;
+        while let Ok(message) = self.incoming.try_recv() {
+            match message {
+                ServerMessage::Joined { code, color, .. } => {
+                    self.code = Some(code);
+                    self.color = color.map(|color| if color == "white" { 0b11 } else { 0b10 });
+                },
+                ServerMessage::State { game, white_connected, black_connected, .. } => {
+                    self.view = Some(game);
+                    self.connected = [white_connected, black_connected];
+                    self.message = None;
+                    is_changed |= !self.input.is_pending();
+                },
+                ServerMessage::Error { message } => {
+                    // a rejected move is taken back by showing the state of the server again
+                    self.message = Some(message);
+                    self.input = MoveInput::default();
+                    is_changed = true;
+                }
+            }
+        }
+        if !is_changed {
+            return None
+        }
+        self.view.as_ref()?.to_game().ok()
+    }
+
+    pub fn is_own_turn(&self, game: &Game) -> bool {
+        self.color == Some(game.get_player_turn()) && game.get_state() != State::Win
+    }
+
+    /// Plays the release of the mouse on `position` on the shown game and sends the move once it is complete
+    pub fn release(&mut self, game: &mut Game, position: usize) -> Result<(), FieldError> {
+        if !self.is_own_turn(game) {
+            return Err(FieldError::new("It is not your turn".to_string()));
+        }
+        let Some(action) = self.input.release(game, position)? else { return Ok(()) };
+        self.outgoing.send(ClientMessage::Move { action: action.to_string() })
+            .map_err(|_| FieldError::new("The connection is closed".to_string()))
+    }
+
+    /// One line about the room, e.g. `Room KXMTR: you play white, black is not connected`
+    pub fn status(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.clone()
+        }
+        let Some(code) = &self.code else {
+            return "Connecting...".to_string()
+        };
+        let role = match self.color {
+            Some(0b11) => "you play white",
+            Some(_) => "you play black",
+            None => "you are watching"
+        };
+        let absent: Vec<&str> = ["white", "black"].into_iter().zip(self.connected)
+            .filter(|(_, connected)| !connected)
+            .map(|(name, _)| name)
+            .collect();
+        match absent.as_slice() {
+            [] => format!("Room {}: {}", code, role),
+            [name] => format!("Room {}: {}, {} is not connected", code, role, name),
+            _ => format!("Room {}: {}, nobody is connected", code, role)
+        }
+    }
+}
+
+/// Puts the action of the own turn together from the releases of the mouse on the shown game
+#[derive(Default)]
+pub struct MoveInput {
+    /// Move which closed a mill and waits for the piece to take
+    pending: Option<Action>,
+}
+impl MoveInput {
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Plays the release on `game`, the whole action once it is complete, i.e. after a mill with the piece to take
+    pub fn release(&mut self, game: &mut Game, position: usize) -> Result<Option<Action>, FieldError> {
+        let start_position = game.get_carry_piece().map(|piece| piece.position);
+        let (state, player) = (game.get_state(), game.get_player_turn());
+        compute_button_up(position, game)?;
+        if game.get_state() == state && game.get_player_turn() == player {
+            return Ok(None)
+        }
+
+        match (state, self.pending.take()) {
+            (State::Take, Some(action)) => Ok(Some(Action::new(action.start_position, action.end_position, Some(position)))),
+            (State::Take, None) => Err(FieldError::new("The move which closed the mill is unknown".to_string())),
+            _ if game.get_state() == State::Take => {
+                self.pending = Some(Action::new(start_position, position, None));
+                Ok(None)
+            },
+            _ => Ok(Some(Action::new(start_position, position, None)))
+        }
+    }
+}
+
+/// Talks to the server until the client is dropped, a lost connection is opened again with a rejoin
+fn run_connection(url: &str, mut hello: ClientMessage, outgoing: Receiver<ClientMessage>, incoming: Sender<ServerMessage>) {
+    loop {
+        let message = match talk(url, &mut hello, &outgoing, &incoming) {
+            Ok(()) => return,
+            Err(message) => message
+        };
+        if incoming.send(ServerMessage::Error { message: format!("{}, reconnecting", message) }).is_err() {
+            return
+        }
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+/// Ok once the client is gone, the reason if the connection is lost
+fn talk(url: &str, hello: &mut ClientMessage, outgoing: &Receiver<ClientMessage>, incoming: &Sender<ServerMessage>) -> Result<(), String> {
+    let (mut socket, _) = connect(url).map_err(|err| format!("Cannot connect to {}: {}", url, err))?;
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream.set_read_timeout(Some(POLL_INTERVAL)).map_err(|err| err.to_string())?;
+    }
+    send(&mut socket, hello)?;
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let Ok(message) = serde_json::from_str::<ServerMessage>(&text) else { continue };
+                // the seat is taken back with the token after a reconnect
+                if let ServerMessage::Joined { code, token: Some(token), .. } = &message {
+                    *hello = ClientMessage::Rejoin { code: code.clone(), token: token.clone() };
+                }
+                if incoming.send(message).is_err() {
+                    return Ok(())
+                }
+            },
+            Ok(Message::Close(_)) => return Err("The server closed the connection".to_string()),
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(err) => return Err(format!("Connection lost: {}", err))
+        }
+
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => send(&mut socket, &message)?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(())
+            }
+        }
+    }
+}
+
+fn send(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>, message: &ClientMessage) -> Result<(), String> {
+    let text = serde_json::to_string(message).expect("messages are always serializable");
+    socket.send(Message::Text(text)).map_err(|err| format!("Connection lost: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::game::Game;
+    use crate::server::rooms::{ClientMessage, RoomServer};
+    use crate::ui::online::OnlineClient;
+
+    /// Polls until the server has sent the state after `plies` plies
+    fn wait_for(client: &mut OnlineClient, plies: usize) -> Game {
+        for _ in 0..500 {
+            if let Some(game) = client.poll() {
+                if game.get_step_counter() == plies {
+                    return game
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("no state after {} plies", plies);
+    }
+
+    #[test]
+    fn test_moves_are_sent_to_the_server() {
+        let server = RoomServer::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", server.address().unwrap());
+        thread::spawn(move || server.run());
+
+        let mut white = OnlineClient::connect(&url, ClientMessage::Create);
+        let mut game = wait_for(&mut white, 0);
+        let code = white.code.clone().unwrap();
+        let mut black = OnlineClient::connect(&url, ClientMessage::Join { code });
+        let mut black_game = wait_for(&mut black, 0);
+        assert!(black.status().ends_with("you play black"));
+
+        assert!(black.release(&mut black_game, 8).is_err());
+        for (ply, position) in [0, 8, 1, 9].into_iter().enumerate() {
+            let (client, shown) = if ply % 2 == 0 { (&mut white, &mut game) } else { (&mut black, &mut black_game) };
+            client.release(shown, position).unwrap();
+            game = wait_for(&mut white, ply + 1);
+            black_game = wait_for(&mut black, ply + 1);
+        }
+
+        // the mill 7 0 1 is only sent together with the piece it takes
+        white.release(&mut game, 7).unwrap();
+        white.release(&mut game, 8).unwrap();
+        let black_game = wait_for(&mut black, 5);
+        assert_eq!(0, black_game.get_token_at(8));
+        assert_eq!(Some("7x8"), black.view.as_ref().and_then(|view| view.moves.last()).map(|action| action.as_str()));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::core::enums::FieldError;
+use crate::core::game::Game;
+use crate::server::rooms::ClientMessage;
+use crate::ui::lan::LanClient;
+use crate::ui::online::OnlineClient;
+
+pub const USAGE: &str = "Usage: muehle_agent [options]
+    --online <url>         play against another human through a room server, e.g. ws://localhost:8081,
+                           without --join or --spectate a new room is opened
+    --join <code>          play black in the room with this join code
+    --spectate <code>      watch the game in the room
+    --lan-host <address>   wait for a player on the local network and play white, e.g. 0.0.0.0:9000
+    --lan-join <address>   play black against the host at the address, e.g. 192.168.1.20:9000";
+
+/// The other player of a game which is not only played on this computer
+pub enum Remote {
+    Online(OnlineClient),
+    Lan(LanClient),
+}
+impl Remote {
+    pub fn is_own_turn(&self, game: &Game) -> bool {
+        match self {
+            Remote::Online(online) => online.is_own_turn(game),
+            Remote::Lan(lan) => lan.is_own_turn(game)
+        }
+    }
+
+    /// Takes in what the other side sent, online the shown game is replaced by the state of the server
+    pub fn poll(&mut self, game: &mut Game) {
+        match self {
+            Remote::Online(online) => if let Some(state) = online.poll() {
+                *game = state;
+            },
+            Remote::Lan(lan) => lan.poll(game)
+        }
+    }
+
+    pub fn release(&mut self, game: &mut Game, position: usize) -> Result<(), FieldError> {
+        match self {
+            Remote::Online(online) => online.release(game, position),
+            Remote::Lan(lan) => lan.release(game, position)
+        }
+    }
+
+    pub fn status(&self) -> String {
+        match self {
+            Remote::Online(online) => online.status(),
+            Remote::Lan(lan) => lan.status()
+        }
+    }
+}
+
+/// The other player from the command line, none to play locally
+pub fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Option<Remote>, String> {
+    let mut url = None;
+    let mut first = ClientMessage::Create;
+    let mut lan = None;
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+
+        match flag.as_str() {
+            "--online" => url = Some(value),
+            "--join" => first = ClientMessage::Join { code: value.to_uppercase() },
+            "--spectate" => first = ClientMessage::Spectate { code: value.to_uppercase() },
+            "--lan-host" => lan = Some((true, value)),
+            "--lan-join" => lan = Some((false, value)),
+            _ => return Err(format!("Unknown option {}\n{}", flag, USAGE))
+        }
+    }
+    match (url, lan) {
+        (Some(_), Some(_)) => Err(format!("Play either online or on the local network\n{}", USAGE)),
+        (Some(url), None) => Ok(Some(Remote::Online(OnlineClient::connect(&url, first)))),
+        (None, Some((true, address))) => Ok(Some(Remote::Lan(LanClient::host(&address)))),
+        (None, Some((false, address))) => Ok(Some(Remote::Lan(LanClient::join(&address)))),
+        (None, None) if first == ClientMessage::Create => Ok(None),
+        (None, None) => Err(format!("--join and --spectate need the server with --online\n{}", USAGE))
+    }
+}
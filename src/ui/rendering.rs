@@ -5,6 +5,7 @@ use good_web_game as ggez;
 use ggez::cgmath::Point2;
 use ggez::graphics::Image;
 
+use crate::ai::action::Action;
 use crate::core::game::Game;
 use crate::core::utils::{is_beat_possible, is_move_valid, possible_move_count_of_position};
 use crate::core::enums::{CarryPiece, Difficulty, State};
@@ -21,6 +22,29 @@ pub fn calculate_image(game: &Game, position: usize, images: &HashMap<String, Im
 }
 
 
+/// Only the squares of the hinted action are outlined, all other pieces are drawn plainly
+pub fn calculate_hint_image(game: &Game, position: usize, hint: &Action, images: &HashMap<String, Image>) -> Option<Image> {
+    let player = if game.get_player_turn() == 0b11 { "white" } else { "black" };
+    let opponent = if game.get_player_turn() == 0b11 { "black" } else { "white" };
+    let field_color: u8 = game.get_token_at(position);
+
+    let image =
+        if hint.beatable_position == Some(position) && field_color != 0b00 {
+            images[format!("take {}", opponent).as_str()].clone()
+        } else if hint.start_position == Some(position) && field_color != 0b00 {
+            images[format!("{} outlined", player).as_str()].clone()
+        } else if hint.end_position == position && field_color == 0b00 && game.get_state() != State::Take {
+            images[format!("empty {} outlined", player).as_str()].clone()
+        } else {
+            match field_color {
+                0b11 => images["white"].clone(),
+                0b10 => images["black"].clone(),
+                _ => return Option::None
+            }
+        };
+    Option::Some(image)
+}
+
 /// Calculates the centered and scaled image position for 160x160 images
 pub fn calculate_image_position(position: usize, offsets: Point2<f32>, scales: Vector2<f32>) -> Point2<f32> {
     let x: f32 = 
@@ -0,0 +1,340 @@
+use std::io::{self, stdout, Write};
+use std::time::Duration;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::ai::action::Action;
+use crate::ai::analysis::player_name;
+use crate::ai::compute_hint;
+use crate::ai::timer;
+use crate::ai::RootPosition;
+use crate::core::enums::State;
+use crate::core::position::{draw_board, negate_token, BOARD_GRID};
+use crate::core::utils::{is_beat_possible, NEIGHBORS};
+use crate::ui::console::{Console, Player};
+
+/// Characters of a point on the screen and between the columns of the grid
+const CELL_WIDTH: u16 = 3;
+const CELL_STEP: u16 = CELL_WIDTH + 3;
+const BOARD_X: u16 = 2;
+const BOARD_Y: u16 = 1;
+const PANEL_X: u16 = BOARD_X + 6 * CELL_STEP + CELL_WIDTH + 4;
+/// Rows of the move list in the side panel
+const MOVE_ROWS: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// The point next to `position` on a line of the board in `direction`
+pub fn neighbor_in(position: usize, direction: Direction) -> Option<usize> {
+    let (column, row) = BOARD_GRID[position];
+    NEIGHBORS[position].iter().copied()
+        .filter(|neighbor| *neighbor < 24)
+        .find(|neighbor| {
+            let (neighbor_column, neighbor_row) = BOARD_GRID[*neighbor];
+            match direction {
+                Direction::Up => neighbor_column == column && neighbor_row < row,
+                Direction::Down => neighbor_column == column && neighbor_row > row,
+                Direction::Left => neighbor_row == row && neighbor_column < column,
+                Direction::Right => neighbor_row == row && neighbor_column > column
+            }
+        })
+}
+
+/// A full screen game in the terminal, played with the arrow keys
+pub struct Tui {
+    pub console: Console,
+    cursor: usize,
+    /// Piece which is about to be moved
+    selected: Option<usize>,
+    /// Suggested action and the ply it belongs to
+    hint: Option<(usize, Action)>,
+    message: String,
+    /// What the engine of the last computer move found
+    last_search: String,
+    /// Depth and nodes or playouts of the last search
+    last_statistics: String,
+}
+impl Tui {
+    pub fn new(console: Console) -> Tui {
+        Tui { console, cursor: 0, selected: None, hint: None, message: String::new(), last_search: String::new(), last_statistics: String::new() }
+    }
+
+    fn ply(&self) -> usize {
+        self.console.game.get_history().len() - 1
+    }
+
+    /// Points where the selected piece can go, where a piece can be placed or which piece can be taken
+    pub fn targets(&self) -> Vec<usize> {
+        let game = &self.console.game;
+        let player = game.get_player_turn();
+        match game.get_state() {
+            State::Setup => (0..24).filter(|position| game.get_token_at(*position) == 0).collect(),
+            State::Take => (0..24)
+                .filter(|position| game.get_token_at(*position) == negate_token(player) && is_beat_possible(game.get_board(), *position, player))
+                .collect(),
+            State::Normal => match self.selected {
+                Some(selected) => {
+                    let mut targets: Vec<usize> = self.legal_actions().iter()
+                        .filter(|action| action.start_position == Some(selected))
+                        .map(|action| action.end_position)
+                        .collect();
+                    targets.sort();
+                    targets.dedup();
+                    targets
+                },
+                None => Vec::new()
+            },
+            State::Win => Vec::new()
+        }
+    }
+
+    fn legal_actions(&self) -> Vec<Action> {
+        let root = RootPosition::of(&self.console.game);
+        root.forward_boards().into_iter().map(|forward_board| root.action(forward_board)).collect()
+    }
+
+    /// Handles a key of the human player, returns false to quit
+    pub fn press(&mut self, code: KeyCode) -> bool {
+        self.message.clear();
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(Direction::Up),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(Direction::Down),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(Direction::Left),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(Direction::Right),
+            KeyCode::Enter | KeyCode::Char(' ') => self.select(),
+            KeyCode::Esc => self.selected = None,
+            KeyCode::Char('u') => {
+                self.selected = None;
+                if let Err(err) = self.console.undo() {
+                    self.message = err.message;
+                }
+            },
+            KeyCode::Char('?') => self.show_hint(),
+            KeyCode::Char('q') => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn move_cursor(&mut self, direction: Direction) {
+        if let Some(neighbor) = neighbor_in(self.cursor, direction) {
+            self.cursor = neighbor;
+        }
+    }
+
+    fn select(&mut self) {
+        let game = &self.console.game;
+        if game.get_state() == State::Win || self.console.player() != Player::Human || self.console.is_move_limit_reached() {
+            return
+        }
+        let position = self.cursor;
+        let is_own_piece = game.get_token_at(position) == game.get_player_turn();
+
+        let result = match (game.get_state(), self.selected) {
+            (State::Take, _) => self.console.take(position),
+            (State::Setup, _) => self.console.play(Action::new(None, position, None)),
+            (_, Some(selected)) if selected == position => {
+                self.selected = None;
+                Ok(())
+            },
+            (_, Some(selected)) if !is_own_piece => {
+                self.selected = None;
+                self.console.play(Action::new(Some(selected), position, None))
+            },
+            _ if is_own_piece && self.legal_actions().iter().any(|action| action.start_position == Some(position)) => {
+                self.selected = Some(position);
+                Ok(())
+            },
+            _ => {
+                self.message = "Select one of your pieces which can move".to_string();
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            self.message = err.message;
+        }
+    }
+
+    fn show_hint(&mut self) {
+        if self.console.player() != Player::Human || self.console.game.get_state() == State::Win {
+            return
+        }
+        match compute_hint(&self.console.game) {
+            Some(action) => {
+                self.message = format!("Hint: {}", action);
+                self.hint = Some((self.ply(), action));
+            },
+            None => self.message = "There is no move left".to_string()
+        }
+    }
+
+    /// Lets the engine of the side to move play and keeps what it found for the side panel, false if it found no move
+    fn computer_step(&mut self) -> bool {
+        let name = player_name(self.console.game.get_player_turn());
+        let start_time = timer::time();
+        match self.console.computer_step() {
+            Some(engine_move) => {
+                let score = engine_move.score.map(|score| format!(", score {}", score)).unwrap_or_default();
+                self.last_search = format!("{} played {}{} in {:.2} s", name, engine_move.action, score, timer::time() - start_time);
+                self.last_statistics = engine_move.statistics();
+                true
+            },
+            None => {
+                self.message = format!("{} found no move", name);
+                false
+            }
+        }
+    }
+
+    /// The moves of the game so far, one entry per turn
+    fn move_list(&self) -> Vec<String> {
+        self.console.game.get_history().windows(2)
+            .map(|turns| RootPosition::of_turn(&turns[0]).action_to(&turns[1]).map_or("?".to_string(), |action| action.to_string()))
+            .collect()
+    }
+
+    pub fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, Clear(ClearType::All), SetForegroundColor(Color::DarkGrey))?;
+        let lines = draw_board(|_| " ".repeat(CELL_WIDTH as usize));
+        for (row, line) in lines.lines().enumerate() {
+            queue!(out, MoveTo(BOARD_X, BOARD_Y + row as u16), Print(line))?;
+        }
+
+        let game = &self.console.game;
+        let targets = self.targets();
+        let hint = self.hint.filter(|(ply, _)| *ply == self.ply()).map(|(_, action)| action);
+        for (position, (column, row)) in BOARD_GRID.iter().enumerate() {
+            let is_hinted = hint.is_some_and(|action| action.start_position == Some(position) || action.end_position == position || action.beatable_position == Some(position));
+            let background = if Some(position) == self.selected {
+                Color::Blue
+            } else if targets.contains(&position) && game.get_state() == State::Take {
+                Color::DarkRed
+            } else if targets.contains(&position) && game.get_state() == State::Normal {
+                Color::DarkGreen
+            } else if is_hinted {
+                Color::DarkMagenta
+            } else {
+                Color::Reset
+            };
+            let (text, foreground) = match game.get_token_at(position) {
+                0b11 => ("●", Color::White),
+                0b10 => ("●", Color::Red),
+                _ => ("+", Color::DarkGrey)
+            };
+
+            queue!(out, MoveTo(BOARD_X + *column as u16 * CELL_STEP, BOARD_Y + *row as u16 * 2), SetBackgroundColor(background))?;
+            if position == self.cursor {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(out, Print(" "), SetForegroundColor(foreground), Print(text), Print(" "), SetAttribute(Attribute::Reset), ResetColor)?;
+        }
+
+        self.draw_panel(out)?;
+        out.flush()
+    }
+
+    fn draw_panel(&self, out: &mut impl Write) -> io::Result<()> {
+        let describe = |player: Player| match player {
+            Player::Human => "human".to_string(),
+            Player::Computer(engine, skill) => format!("{} level {}", engine.engine().name(), skill.level())
+        };
+        queue!(out, MoveTo(PANEL_X, BOARD_Y), SetAttribute(Attribute::Bold), Print("Mühle"), SetAttribute(Attribute::Reset))?;
+        for (row, player, color) in [(1, self.console.white, Color::White), (2, self.console.black, Color::Red)] {
+            let name = if row == 1 { "White" } else { "Black" };
+            queue!(out, MoveTo(PANEL_X, BOARD_Y + row), Print(name), Print(" "), SetForegroundColor(color), Print("●"), ResetColor, Print(" "), Print(describe(player)))?;
+        }
+        queue!(out, MoveTo(PANEL_X, BOARD_Y + 4), Print(self.console.status()))?;
+        queue!(out, MoveTo(PANEL_X, BOARD_Y + 5), SetForegroundColor(Color::Yellow), Print(&self.message), ResetColor)?;
+        queue!(out, MoveTo(PANEL_X, BOARD_Y + 7), Print(&self.last_search))?;
+        queue!(out, MoveTo(PANEL_X, BOARD_Y + 8), Print(&self.last_statistics))?;
+
+        let moves = self.move_list();
+        let pairs: Vec<String> = moves.chunks(2).enumerate()
+            .map(|(index, pair)| format!("{:>3}. {:<10}{}", index + 1, pair[0], pair.get(1).map_or("", |text| text.as_str())))
+            .collect();
+        let mut lines = vec!["Moves".to_string()];
+        lines.extend(pairs.iter().skip(pairs.len().saturating_sub(MOVE_ROWS)).cloned());
+        lines.extend(["".to_string(), "arrows move, enter selects, esc cancels".to_string(), "u undo, ? hint, q quit".to_string()]);
+        for (row, line) in lines.iter().enumerate() {
+            queue!(out, MoveTo(PANEL_X, BOARD_Y + 9 + row as u16), Print(line))?;
+        }
+        Ok(())
+    }
+
+    /// Plays in the alternate screen of the terminal until the player quits
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut out = stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        let result = self.event_loop(&mut out);
+        execute!(out, Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<()> {
+        loop {
+            self.draw(out)?;
+
+            let is_over = self.console.game.get_state() == State::Win || self.console.is_move_limit_reached();
+            // a game between two computers can still be quit
+            if !is_over && self.console.player() != Player::Human && self.computer_step() && !event::poll(Duration::ZERO)? {
+                continue
+            }
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.press(key.code) {
+                    return Ok(())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+
+    use crate::core::enums::State;
+    use crate::ui::console::{Console, Player};
+    use crate::ui::terminal::{neighbor_in, Direction, Tui};
+
+    #[test]
+    fn test_cursor_and_moves() {
+        assert_eq!(Some(0), neighbor_in(7, Direction::Right));
+        assert_eq!(Some(8), neighbor_in(0, Direction::Down));
+        assert_eq!(None, neighbor_in(22, Direction::Right));
+        assert_eq!(Some(14), neighbor_in(22, Direction::Left));
+
+        let mut tui = Tui::new(Console::new(Player::Human, Player::Human, 200));
+        assert_eq!(24, tui.targets().len());
+        // white places on 0 and 1, black on 8 and 2
+        for keys in [vec![], vec![KeyCode::Down], vec![KeyCode::Up, KeyCode::Right], vec![KeyCode::Down]] {
+            for key in keys {
+                tui.press(key);
+            }
+            tui.press(KeyCode::Enter);
+        }
+        assert_eq!(4, tui.console.game.get_step_counter());
+
+        // 7 closes the mill 7 0 1 and 8 can be taken
+        for key in [KeyCode::Up, KeyCode::Left, KeyCode::Left, KeyCode::Enter] {
+            tui.press(key);
+        }
+        assert_eq!(State::Take, tui.console.game.get_state());
+        assert!(tui.targets().contains(&8));
+        assert_eq!(vec!["0", "8", "1", "2"], tui.move_list());
+        assert!(tui.press(KeyCode::Char('u')));
+        assert!(!tui.press(KeyCode::Char('q')));
+    }
+}
@@ -0,0 +1,104 @@
+//! The HTTP API of `server` on localhost
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use muehle_agent::server::ApiServer;
+
+fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method, path, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+fn start(server: ApiServer) -> SocketAddr {
+    let address = server.address().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+#[test]
+fn test_play_a_game() {
+    let address = start(ApiServer::bind("127.0.0.1:0").unwrap());
+
+    let (status, game) = request(address, "POST", "/games", "");
+    assert_eq!(201, status);
+    assert_eq!("E".repeat(24), game["board"]);
+    let path = format!("/games/{}", game["id"]);
+
+    let (_, moves) = request(address, "GET", &format!("{}/moves", path), "");
+    assert_eq!(24, moves["moves"].as_array().unwrap().len());
+
+    let (status, game) = request(address, "POST", &format!("{}/moves", path), r#"{"move": "0"}"#);
+    assert_eq!((200, "black"), (status, game["player"].as_str().unwrap()));
+    assert_eq!(1, request(address, "GET", &path, "").1["plies"]);
+
+    let (status, played) = request(address, "POST", &format!("{}/ai", path), r#"{"engine": "mcts", "level": 1}"#);
+    assert_eq!(200, status);
+    assert_eq!(vec![Value::from("0"), played["move"].clone()], played["game"]["moves"].as_array().unwrap().clone());
+    assert_eq!(8, played["game"]["in_hand"]["black"]);
+
+    assert_eq!(200, request(address, "DELETE", &path, "").0);
+    assert_eq!(404, request(address, "GET", &path, "").0);
+}
+
+#[test]
+fn test_errors() {
+    let address = start(ApiServer::bind("127.0.0.1:0").unwrap());
+    let (_, game) = request(address, "POST", "/games", "");
+    let path = format!("/games/{}", game["id"]);
+
+    // unknown games and routes
+    for (method, missing) in [("GET", "/games/x"), ("GET", "/games/999"), ("GET", "/games/999/moves"), ("POST", "/games/999/ai"), ("DELETE", "/games/999"), ("GET", "/players")] {
+        assert_eq!(404, request(address, method, missing, "").0, "{} {}", method, missing);
+    }
+    assert_eq!(404, request(address, "PUT", &path, "").0);
+
+    // illegal moves leave the game as it was
+    request(address, "POST", &format!("{}/moves", path), r#"{"move": "0"}"#);
+    let (status, error) = request(address, "POST", &format!("{}/moves", path), r#"{"move": "0"}"#);
+    assert_eq!((400, "Cannot move to 0"), (status, error["error"].as_str().unwrap()));
+    assert_eq!(400, request(address, "POST", &format!("{}/moves", path), r#"{"move": "3-4"}"#).0);
+    assert_eq!(400, request(address, "POST", &format!("{}/moves", path), r#"{"move": "25"}"#).0);
+    assert_eq!(1, request(address, "GET", &path, "").1["plies"]);
+
+    // malformed bodies and options
+    for body in ["", "{", r#"{"move": 3}"#, r#"{"move": "1", "player": "white"}"#] {
+        assert_eq!(400, request(address, "POST", &format!("{}/moves", path), body).0, "{}", body);
+    }
+    for body in [r#"{"level": 25}"#, r#"{"engine": "random"}"#, r#"{"level": 3, "difficulty": "easy"}"#, r#"{"time": 60}"#] {
+        assert_eq!(400, request(address, "POST", &format!("{}/ai", path), body).0, "{}", body);
+    }
+    assert_eq!(1, request(address, "GET", &path, "").1["plies"]);
+}
+
+#[test]
+fn test_limits() {
+    let mut server = ApiServer::bind("127.0.0.1:0").unwrap();
+    server.games.max_games = 2;
+    server.games.max_searches = 0;
+    server.games.idle_game_timeout = Duration::from_millis(300);
+    let address = start(server);
+
+    let (_, first) = request(address, "POST", "/games", "");
+    assert_eq!(201, request(address, "POST", "/games", "").0);
+    let (status, error) = request(address, "POST", "/games", "");
+    assert_eq!((503, "There are too many games, try again later"), (status, error["error"].as_str().unwrap()));
+    assert_eq!(503, request(address, "POST", &format!("/games/{}/ai", first["id"]), "").0);
+
+    // games nobody asked about are forgotten and make room for new ones
+    thread::sleep(Duration::from_millis(400));
+    assert_eq!(404, request(address, "GET", &format!("/games/{}", first["id"]), "").0);
+    assert_eq!(201, request(address, "POST", "/games", "").0);
+}